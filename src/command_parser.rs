@@ -3,10 +3,10 @@ use std::path;
 use clap::Parser;
 use log::{error, info, warn};
 
-use crate::{io_uring::{UringMode, UringSqFillingMode, UringTaskWork}, util::{self, statistic::{MultiplexPort, OutputFormat, Parameter, SimulateConnection, UringParameter}, ExchangeFunction, IOModel, NPerfMode}};
+use crate::{io_uring::{UringMode, UringSqFillingMode, UringTaskWork}, util::{self, statistic::{MultiplexPort, OutputFormat, Parameter, UringParameter}, EpollTrigger, ExchangeFunction, IOModel, NPerfMode, Transport}};
 use crate::net::{self, socket_options::SocketOptions};
 
-#[derive(Parser,Default,Debug)]
+#[derive(Parser,Default,Debug,Clone)]
 #[clap(version, about="A network performance measurement tool")]
 #[allow(non_camel_case_types)]
 pub struct nPerf {
@@ -14,10 +14,34 @@ pub struct nPerf {
     #[arg(default_value_t, value_enum)]
     mode: NPerfMode,
 
-    /// IP address to measure against/listen on
+    /// IPv4 or IPv6 address or hostname to measure against/listen on. Use :: to listen on all IPv6 (and IPv4-mapped) addresses
     #[arg(short = 'a',long, default_value_t = String::from("0.0.0.0"))]
     ip: String,
 
+    /// Local IP address the sender binds its sockets to. Uses the wildcard address by default
+    #[arg(long)]
+    source_ip: Option<String>,
+
+    /// Control channel addresses (IP or hostname with optional port) of the receivers, if --ip is a multicast group. The receivers join the group and report their statistics to the sender
+    #[arg(long, value_delimiter = ',')]
+    pub multicast_receivers: Vec<String>,
+
+    /// Time to live (hop limit for IPv6) of multicast datagrams
+    #[arg(long, default_value_t = crate::DEFAULT_MULTICAST_TTL)]
+    multicast_ttl: u32,
+
+    /// Loop multicast datagrams back to receivers on the sending host
+    #[arg(long, default_value_t = false)]
+    multicast_loop: bool,
+
+    /// Bind all sockets to the given network interface (SO_BINDTODEVICE), e.g. to force the traffic over a specific NIC or VLAN. Requires CAP_NET_RAW
+    #[arg(long)]
+    interface: Option<String>,
+
+    /// Transport of the data sockets: udp, unix for unix domain datagram sockets on the local host, or tcp with one connection per thread. The control channel always uses TCP. Only used on the sender side
+    #[arg(long, default_value_t, value_enum)]
+    transport: Transport,
+
     /// Port number for sender to measure against and receiver to listen on
     #[arg(short, long, default_value_t = crate::DEFAULT_RECEIVER_PORT)]
     pub port: u16,
@@ -26,15 +50,31 @@ pub struct nPerf {
     #[arg(short, long, default_value_t = crate::DEFAULT_SENDER_PORT)]
     pub sender_port: u16,
 
+    /// TCP port number of the control channel between sender and receiver. Uses the same port number as --port by default
+    #[arg(long)]
+    pub control_port: Option<u16>,
+
     /// Start multiple sender/receiver threads in parallel. The port number is incremented automatically for every thread.
     #[arg(long, default_value_t = 1)]
     parallel: u16,
 
+    /// Reverse the direction of the test: the receiver transmits and the sender measures. Only used on the sender side
+    #[arg(short = 'R', long, default_value_t = false)]
+    reverse: bool,
+
+    /// Run the test in both directions at the same time: sender and receiver transmit and measure simultaneously. Only used on the sender side
+    #[arg(long, default_value_t = false)]
+    bidir: bool,
+
     /// Do not finish the execution after the first measurement
     #[arg(short, long, default_value_t = false)]
     pub run_infinite: bool,
 
-    /// Interval printouts of the statistic in seconds (0 to disable). WARNING: Interval statistics are printed at the end of the test, not at the interval!
+    /// Run the receiver as daemon, which accepts tests from multiple senders at the same time. Every test gets its own data ports, threads and statistics
+    #[arg(long, default_value_t = false)]
+    pub daemon: bool,
+
+    /// Interval printouts of the statistic in seconds (0 to disable). The intervals of all threads are merged and printed while the test is running
     #[arg(short, long, default_value_t = crate::DEFAULT_INTERVAL)]
     interval: f64,
 
@@ -46,6 +86,14 @@ pub struct nPerf {
     #[arg(short = 't', long, default_value_t = crate::DEFAULT_DURATION)]
     time: u64,
 
+    /// Milliseconds a receiver thread waits for the first datagram, before it ends without data (0 to wait until the sender stops). Only used on the receiving side
+    #[arg(long, default_value_t = crate::DEFAULT_RECEIVE_START_TIMEOUT)]
+    receive_start_timeout: u64,
+
+    /// Milliseconds a receiver thread waits for a subsequent datagram, before it ends the measurement (0 to wait until the sender stops). Only used on the receiving side
+    #[arg(long, default_value_t = crate::DEFAULT_RECEIVE_IDLE_TIMEOUT)]
+    receive_idle_timeout: u64,
+
     /// Pin each thread to an individual core. The receiver threads start from the last core downwards, while the sender threads are pinned from the first core upwards.
     #[arg(long, default_value_t = false)]
     with_core_affinity: bool,
@@ -94,6 +142,14 @@ pub struct nPerf {
     #[arg(long, default_value_t, value_enum)]
     io_model: IOModel,
 
+    /// epoll: Level- or edge-triggered readiness notifications
+    #[arg(long, default_value_t, value_enum)]
+    epoll_trigger: EpollTrigger,
+
+    /// epoll: Register the socket with EPOLLEXCLUSIVE, so only one of the threads sharing a socket is woken up
+    #[arg(long, default_value_t = false)]
+    epoll_exclusive: bool,
+
     /// Define the type the output 
     #[arg(long, default_value_t, value_enum)]
     output_format: OutputFormat,
@@ -122,9 +178,13 @@ pub struct nPerf {
     #[arg(long, default_value_t, value_enum)]
     multiplex_port_receiver: MultiplexPort,
 
-    /// CURRENTLY IGNORED. Simulate a single QUIC connection or one QUIC connection per thread.
-    #[arg(long, default_value_t, value_enum)]
-    simulate_connection: SimulateConnection,
+    /// Amount of simulated QUIC-like connections, whose IDs are carried in every datagram. The connections are spread round robin over the threads, so a thread sends on several connections, or a connection is sent by several threads. Defaults to one connection per thread. Only used on the sender side
+    #[arg(long)]
+    connections: Option<u32>,
+
+    /// Amount of sockets every thread drives in its event loop, each to its own port. Thread i uses the ports following port + i * N, like a QUIC server handling many connections per worker. Only used on the sender side
+    #[arg(long, default_value_t = 1)]
+    sockets_per_thread: u16,
 
     /// io_uring: Which mode to use
     #[arg(long, default_value_t, value_enum)]
@@ -182,9 +242,22 @@ impl nPerf {
             return None;
         }
     
-        let ipv4 = match net::parse_ipv4(&self.ip) {
+        let source_ip = match self.source_ip.as_deref().map(net::parse_ip).transpose() {
             Ok(x) => x,
-            Err(_) => { error!("Invalid IPv4 address!"); return None; },
+            Err(_) => { error!("Invalid source IP address!"); return None; },
+        };
+
+        let ip = match net::resolve_ip(&self.ip, source_ip) {
+            Ok(x) => x,
+            Err(x) => { error!("{}", x); return None; },
+        };
+
+        // The defaults fit into a 1500 byte MTU with IPv4 headers, so they are reduced for the longer IPv6 headers
+        let (datagram_size, with_mss) = if ip.is_ipv6() {
+            (if self.datagram_size == crate::DEFAULT_UDP_DATAGRAM_SIZE { crate::DEFAULT_UDP_DATAGRAM_SIZE_IPV6 } else { self.datagram_size },
+             if self.with_mss == crate::DEFAULT_MSS { crate::DEFAULT_MSS_IPV6 } else { self.with_mss })
+        } else {
+            (self.datagram_size, self.with_mss)
         };
     
         let packet_buffer_size = match self.exchange_function {
@@ -196,21 +269,18 @@ impl nPerf {
             info!("GSO/GRO enabled with buffer size {}", self.with_gso_buffer);
             self.with_gso_buffer
         } else {
-            self.with_mss
+            with_mss
         };
 
-        let simulate_connection = match self.multiplex_port_receiver {
-            MultiplexPort::Sharing => SimulateConnection::Single,
-            _ => SimulateConnection::Multiple
-        };
+        let amount_connections = self.connections.unwrap_or(self.parallel as u32);
 
-        info!("Simulate connection: {:?}", simulate_connection);
+        info!("Simulated connections: {}", amount_connections);
         info!("Exchange function used: {:?}", self.exchange_function);
         info!("MSS used: {}", mss);
         info!("IO model used: {:?}", self.io_model);
-        info!("UDP datagram size used: {}", self.datagram_size);
+        info!("UDP datagram size used: {}", datagram_size);
 
-        let socket_options = self.parse_socket_options(self.mode);
+        let socket_options = self.parse_socket_options(self.mode, datagram_size, ip.is_multicast());
 
         let uring_parameters = UringParameter {
             uring_mode: self.uring_mode,
@@ -229,21 +299,31 @@ impl nPerf {
             self.label_run.clone(),
             self.repetition_id,
             self.mode,
-            ipv4, 
+            self.reverse,
+            self.bidir,
+            ip, 
+            source_ip,
+            self.interface.clone(),
+            self.transport,
             self.parallel,
             self.interval,
             self.output_format, 
             self.output_file_path.clone(),
             self.io_model, 
+            self.epoll_trigger,
+            self.epoll_exclusive,
             self.time, 
+            self.receive_start_timeout,
+            self.receive_idle_timeout,
             mss, 
-            self.datagram_size, 
+            datagram_size, 
             packet_buffer_size, 
             socket_options, 
             self.exchange_function,
             self.multiplex_port,
             self.multiplex_port_receiver,
-            simulate_connection,
+            amount_connections,
+            self.sockets_per_thread,
             self.with_core_affinity,
             self.with_numa_affinity,
             uring_parameters
@@ -258,6 +338,11 @@ impl nPerf {
             return None;
         }
 
+        if (parameter.datagram_size as usize) < net::LEN_HEADER {
+            error!("UDP datagram size is too small! Minimum is the header length of {}", net::LEN_HEADER);
+            return None;
+        }
+
         if parameter.mode == util::NPerfMode::Sender && self.multiplex_port_receiver == MultiplexPort::Sharding && (self.multiplex_port == MultiplexPort::Sharing || self.multiplex_port == MultiplexPort::Sharding ) {
             warn!("Sharding on receiver side doesn't work, if sender side is set to sharing or sharding (uses one port), since all traffic would be balanced to one thread (see man for SO_REUSEPORT)!");
         }
@@ -273,14 +358,200 @@ impl nPerf {
             warn!("If receiver/sender is running on the same machine, with the same amount of threads, multiple threads are going to run on the same core! Available cores: {}", cores_amount);
         }
 
+        if parameter.mode == util::NPerfMode::Receiver && self.reverse {
+            warn!("Reverse mode is requested by the sender! Parameter is ignored");
+            parameter.reverse = false;
+        }
+
+        if parameter.ip.is_multicast() {
+            if parameter.mode == util::NPerfMode::Receiver {
+                error!("Receivers join the multicast group of the sender! Set --ip to the address to listen for the control channel on");
+                return None;
+            } else if self.multicast_receivers.is_empty() {
+                error!("Multicast needs the control channel addresses of the receivers set with --multicast-receivers!");
+                return None;
+            } else if parameter.reverse || parameter.bidirectional {
+                error!("Multicast can't be combined with reverse or bidirectional mode!");
+                return None;
+            } else if parameter.multiplex_port_receiver == MultiplexPort::Sharding {
+                // Every socket bound to the group port receives its own copy of each datagram
+                error!("Multicast can't be combined with sharding on the receiver side!");
+                return None;
+            }
+        } else if !self.multicast_receivers.is_empty() {
+            warn!("Multicast receivers are only used, if --ip is a multicast group! Parameter is ignored");
+        }
+
+        if parameter.mode == util::NPerfMode::Receiver && parameter.source_ip.is_some() {
+            warn!("Source IP is only used on the sender side! The receiver binds to --ip. Parameter is ignored");
+            parameter.source_ip = None;
+        }
+
+        if parameter.source_ip.is_some_and(|source_ip| source_ip.is_ipv6() != parameter.ip.is_ipv6()) {
+            error!("Source IP and IP of the receiver have to be of the same address family!");
+            return None;
+        }
+
+        if parameter.interface.as_ref().is_some_and(|interface| interface.is_empty() || interface.len() >= libc::IFNAMSIZ) {
+            error!("Interface name has to be between 1 and {} characters long!", libc::IFNAMSIZ - 1);
+            return None;
+        }
+
+        if parameter.mode == util::NPerfMode::Receiver && parameter.transport != Transport::Udp {
+            warn!("Transport is requested by the sender! Parameter is ignored");
+            parameter.transport = Transport::Udp;
+        }
+
+        if parameter.amount_connections == 0 || parameter.amount_connections as usize > crate::MAX_CONNECTION_ID {
+            error!("Amount of connections has to be between 1 and {}!", crate::MAX_CONNECTION_ID);
+            return None;
+        }
+
+        // Unix domain sockets and TCP share the exchange functions and IO models with UDP, but not the options of the UDP stack
+        if parameter.transport != Transport::Udp {
+            if self.with_gsro {
+                error!("GSO and GRO are only available with the UDP transport!");
+                return None;
+            } else if parameter.reverse || parameter.bidirectional || parameter.ip.is_multicast() {
+                error!("Reverse, bidirectional and multicast mode are only available with the UDP transport!");
+                return None;
+            } else if self.multiplex_port == MultiplexPort::Sharding || self.multiplex_port_receiver == MultiplexPort::Sharding {
+                error!("Sharding (SO_REUSEPORT) is only available with the UDP transport!");
+                return None;
+            }
+        }
+
+        if parameter.transport == Transport::Unix {
+            if self.bandwidth > 0 {
+                error!("Socket pacing is only available with the UDP and TCP transport!");
+                return None;
+            } else if parameter.source_ip.is_some() || parameter.interface.is_some() {
+                error!("Source IP and interface are only available with the UDP and TCP transport!");
+                return None;
+            }
+        }
+
+        // Every sender thread opens its own connection to a receiver thread
+        if parameter.transport == Transport::Tcp {
+            if self.multiplex_port != MultiplexPort::Individual || self.multiplex_port_receiver != MultiplexPort::Individual {
+                error!("TCP only supports individual ports on both sides, since every thread uses its own connection!");
+                return None;
+            } else if parameter.exchange_function == ExchangeFunction::Mmsg {
+                error!("TCP only supports the exchange functions normal and msg!");
+                return None;
+            } else if self.connections.is_some() {
+                error!("TCP doesn't simulate connections, since every thread uses its own connection!");
+                return None;
+            }
+        }
+
+        if parameter.sockets_per_thread == 0 || parameter.amount_threads.checked_mul(parameter.sockets_per_thread).is_none() {
+            error!("Amount of sockets per thread has to be at least 1, and the threads can't use more than {} sockets in total!", u16::MAX);
+            return None;
+        }
+
+        // Every socket of a thread has its own port on both sides
+        if parameter.sockets_per_thread > 1 {
+            if parameter.transport == Transport::Tcp {
+                error!("TCP doesn't support several sockets per thread, since every thread uses its own connection!");
+                return None;
+            } else if parameter.reverse || parameter.bidirectional || parameter.ip.is_multicast() {
+                error!("Reverse, bidirectional and multicast mode don't support several sockets per thread!");
+                return None;
+            } else if self.multiplex_port == MultiplexPort::Sharing || self.multiplex_port_receiver != MultiplexPort::Individual {
+                error!("Several sockets per thread need individual ports on the receiver side and can't share a socket on the sender side!");
+                return None;
+            } else if self.without_non_blocking {
+                error!("Several sockets per thread need nonblocking sockets, since a blocked socket would stall the others!");
+                return None;
+            } else if parameter.io_model == IOModel::IoUring && (self.uring_mode == UringMode::ProvidedBuffer || self.uring_mode == UringMode::Multishot) {
+                error!("io_uring only supports the modes normal and zerocopy with several sockets per thread!");
+                return None;
+            }
+        }
+
+        // nPerf builds the frames itself, and every receiver thread filters the frames to its own port
+        if parameter.io_model == IOModel::PacketRing && parameter.mode == util::NPerfMode::Sender {
+            if parameter.transport != Transport::Udp || !parameter.ip.is_ipv4() || parameter.ip.is_multicast() {
+                error!("Packet rings only support unicast UDP over IPv4!");
+                return None;
+            } else if self.with_gsro {
+                error!("Packet rings don't support GSO and GRO, since every frame carries one datagram!");
+                return None;
+            } else if parameter.reverse || parameter.bidirectional {
+                error!("Packet rings don't support reverse and bidirectional mode!");
+                return None;
+            } else if parameter.sockets_per_thread > 1 || self.multiplex_port != MultiplexPort::Individual || self.multiplex_port_receiver != MultiplexPort::Individual {
+                error!("Packet rings need individual ports on both sides and one socket per thread!");
+                return None;
+            } else if self.bandwidth > 0 {
+                error!("Socket pacing isn't available with packet rings!");
+                return None;
+            }
+        }
+
+        // Only the receiver uses AF_XDP, its XDP program redirects the IPv4/UDP frames to the data ports of the test
+        if parameter.io_model == IOModel::AfXdp && parameter.mode == util::NPerfMode::Sender {
+            let frame_len = crate::net::packet_ring::LEN_FRAME_HEADERS as u32 + parameter.datagram_size;
+            if parameter.transport != Transport::Udp || !parameter.ip.is_ipv4() || parameter.ip.is_multicast() {
+                error!("AF_XDP only supports unicast UDP over IPv4!");
+                return None;
+            } else if self.with_gsro {
+                error!("AF_XDP doesn't support GSO and GRO, since every frame carries one datagram!");
+                return None;
+            } else if parameter.reverse || parameter.bidirectional {
+                error!("AF_XDP doesn't support reverse and bidirectional mode!");
+                return None;
+            } else if parameter.sockets_per_thread > 1 || self.multiplex_port != MultiplexPort::Individual || self.multiplex_port_receiver != MultiplexPort::Individual {
+                error!("AF_XDP needs individual ports on both sides and one socket per thread, since every thread binds its own socket to a receive queue!");
+                return None;
+            } else if frame_len > crate::XDP_FRAME_SIZE - crate::XDP_PACKET_HEADROOM {
+                error!("Frames of {} bytes don't fit into the AF_XDP frames, which hold up to {} bytes!", frame_len, crate::XDP_FRAME_SIZE - crate::XDP_PACKET_HEADROOM);
+                return None;
+            }
+        }
+
+        if parameter.mode == util::NPerfMode::Sender && self.daemon {
+            warn!("Daemon mode is only available on the receiver side! Parameter is ignored");
+        }
+
+        if parameter.mode == util::NPerfMode::Receiver && self.bidir {
+            warn!("Bidirectional mode is requested by the sender! Parameter is ignored");
+            parameter.bidirectional = false;
+        }
+
+        if parameter.reverse && parameter.bidirectional {
+            error!("Reverse and bidirectional mode can't be combined!");
+            return None;
+        }
+
+        if (parameter.reverse || parameter.bidirectional) && (self.multiplex_port != MultiplexPort::Individual || self.multiplex_port_receiver != MultiplexPort::Individual) {
+            error!("Reverse and bidirectional mode only support individual ports on both sides, since every thread punches its own path through NAT!");
+            return None;
+        }
+
+        if parameter.bidirectional && parameter.amount_threads.checked_mul(2).and_then(|x| self.port.checked_add(x)).is_none() {
+            error!("Bidirectional mode uses the {} ports following the port {}, which exceeds the maximum port number!", parameter.amount_threads, self.port);
+            return None;
+        }
+
+        if self.port.checked_add(parameter.amount_ports()).is_none() {
+            error!("The {} sockets of all threads use the ports following the port {}, which exceeds the maximum port number!", parameter.amount_ports(), self.port);
+            return None;
+        }
+
         if parameter.mode == util::NPerfMode::Receiver && self.time != crate::DEFAULT_DURATION {
-            warn!("Time is ignored in receiver mode!");
+            warn!("Time is ignored in receiver mode! The receiver uses the parameters pushed by the sender.");
         }
 
         if parameter.io_model != IOModel::IoUring && (self.uring_mode != UringMode::Normal || self.uring_ring_size != crate::DEFAULT_URING_RING_SIZE) {
             warn!("Uring specific parameters are only used with io-model io_uring enabled!");
         }
 
+        if parameter.io_model != IOModel::Epoll && (self.epoll_trigger != EpollTrigger::Level || self.epoll_exclusive) {
+            warn!("Epoll specific parameters are only used with io-model epoll enabled!");
+        }
+
         if !self.uring_ring_size.is_power_of_two() {
             error!("Uring ring size must be a power of 2!");
             return None;
@@ -301,13 +572,13 @@ impl nPerf {
             return None;
         }
 
-        if self.interval > 0.0 && (self.interval * (self.time as f64 / self.interval).round() - self.time as f64).abs() > 1e-9  {
-            error!("Interval doesn't fit perfect in the time!");
-            return None;
+        if self.io_model == IOModel::IoUring && (self.uring_mode == UringMode::ProvidedBuffer || self.uring_mode == UringMode::Multishot) && parameter.mode == util::NPerfMode::Sender && !parameter.reverse {
+            warn!("Provided buffers and multishot are only available with io_uring on the receiver! The sender pushes the mode to the receiver and sends in normal mode.");
         }
 
-        if self.interval > 0.0 && self.time == 0 && self.mode == NPerfMode::Receiver {
-            error!("Interval is set but time is 0! Time must be set when interval output is enabled!");
+        // The receiver runs for the test duration of the sender, so its interval is checked against it as soon as the sender configures the test
+        if self.mode == NPerfMode::Sender && self.interval > 0.0 && (self.interval * (self.time as f64 / self.interval).round() - self.time as f64).abs() > 1e-9  {
+            error!("Interval doesn't fit perfect in the time!");
             return None;
         }
 
@@ -329,7 +600,7 @@ impl nPerf {
             }
         }
 
-        if self.io_model == IOModel::IoUring && self.uring_mode == UringMode::Normal || self.uring_mode == UringMode::Zerocopy {
+        if self.io_model == IOModel::IoUring && (self.uring_mode == UringMode::Normal || self.uring_mode == UringMode::Zerocopy || self.mode == NPerfMode::Sender) {
             warn!("Setting packet_buffer_size to {}!", parameter.uring_parameter.buffer_size);
             parameter.packet_buffer_size = parameter.uring_parameter.buffer_size as usize;
         }
@@ -362,9 +633,9 @@ impl nPerf {
     }
 
 
-    fn parse_socket_options(&self, mode: NPerfMode) -> SocketOptions {
+    fn parse_socket_options(&self, mode: NPerfMode, datagram_size: u32, multicast: bool) -> SocketOptions {
         let gso = if self.with_gsro && mode == util::NPerfMode::Sender {
-            Some(self.datagram_size)
+            Some(datagram_size)
         } else {
             None
         };
@@ -393,6 +664,8 @@ impl nPerf {
             self.bandwidth
         } / 8 * 1000 * 1000;
         info!("Bandwidth per thread: {} Bytes/s", bandwidth_per_thread);
+
+        let multicast_ttl = if mode == NPerfMode::Sender && multicast { Some(self.multicast_ttl) } else { None };
         
         SocketOptions::new(
             !self.without_non_blocking, 
@@ -402,7 +675,9 @@ impl nPerf {
             gro, 
             bandwidth_per_thread,
             recv_buffer_size, 
-            send_buffer_size
+            send_buffer_size,
+            multicast_ttl,
+            self.multicast_loop
         )
     }
 }
@@ -3,138 +3,771 @@ use log::{debug, error, info, warn};
 use crate::command_parser::nPerf;
 use crate::io_uring::normal::IoUringNormal;
 use crate::io_uring::IoUringOperatingModes;
+use crate::net::{self, control_channel::{ControlChannel, ControlMessage}};
 use crate::net::socket::Socket;
-use crate::node::{sender::Sender, receiver::Receiver, Node};
+use crate::net::{packet_ring::PacketRing, xdp::{self, XdpProgram, XdpSocket}, FrameRing};
+use crate::node::{sender::Sender, receiver::Receiver, Node, StartBarrier};
 use crate::util::core_affinity_manager::CoreAffinityManager;
-use crate::util::{statistic::{MultiplexPort, Parameter, SimulateConnection}, NPerfMode};
+use crate::util::cpu_util::{CpuUtil, ThreadCpuTime};
+use crate::util::counters::ThreadCounters;
+use crate::util::interval_reporter::IntervalReporter;
+use crate::util::session_manager::SessionManager;
+use crate::util::packet_buffer::PacketBuffer;
+use crate::util::timer::TestTimer;
+use crate::util::{interrupt, statistic::{CombinedStatistic, MultiplexPort, Parameter}, IOModel, NPerfMode, Transport};
 use crate::Statistic;
 
+use std::net::TcpListener;
 use std::os::fd::RawFd;
-use std::sync::{Arc, Mutex};
-use std::{net::SocketAddrV4, thread};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier, Mutex};
+use std::time::Instant;
+use std::{net::SocketAddr, thread};
 extern crate core_affinity;
 
+// The instant the thread was released from the start barrier is returned next to its statistics
+type NodeResult = Result<(Statistic, Instant), &'static str>;
+
+// All node threads of a single test, together with the resources they share
+struct NodeThreads {
+    handles: Vec<thread::JoinHandle<NodeResult>>,
+    socket: Option<Socket>,
+    // The io_uring instance owning the shared SQ_POLL thread has to outlive all node threads
+    _io_uring: Option<IoUringNormal>,
+    cpu_util: CpuUtil,
+    barrier: Arc<Barrier>,
+    // Only set for the transmitting threads of a reverse test, which accept the hole punches of the measuring side, before they wait at the start barrier
+    bound_barrier: Option<Arc<Barrier>>,
+    timer: Arc<TestTimer>,
+    timer_handle: Option<thread::JoinHandle<()>>,
+    // Prints the merged interval statistics while the test is running
+    interval_reporter: Option<Arc<IntervalReporter>>,
+    interval_reporter_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl NodeThreads {
+    // Waits until the sockets of all threads are set up, and starts the clock of the test at the moment the threads are released
+    fn start(&mut self) {
+        self.barrier.wait();
+        self.timer_handle = Some(TestTimer::start(&self.timer));
+        self.interval_reporter_handle = self.interval_reporter.as_ref().map(IntervalReporter::start);
+    }
+
+    // Waits until the transmitting threads of a reverse test bound their sockets, so the measuring side may send its hole punches
+    fn wait_bound(&self) {
+        if let Some(bound_barrier) = &self.bound_barrier {
+            bound_barrier.wait();
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.handles.iter().all(|handle| handle.is_finished())
+    }
+}
+
 impl nPerf {
     pub fn exec(self, parameter: Parameter) -> Option<Statistic> {
         info!("Starting nPerf...");
         debug!("Running with Parameter: {:?}", parameter);
 
+        // SIGINT and SIGTERM stop the test early, which still ends with the statistics collected so far
+        if let Err(x) = interrupt::install_handler() {
+            warn!("{}", x);
+        }
+
         let core_affinity_manager = Arc::new(Mutex::new(CoreAffinityManager::new(parameter.mode, None, parameter.numa_affinity)));
 
         if parameter.core_affinity {
             core_affinity_manager.lock().unwrap().bind_to_core(0).expect("Error setting affinity");
         }
 
+        let control_address = SocketAddr::new(parameter.ip, self.control_port.unwrap_or(self.port));
+
+        match parameter.mode {
+            NPerfMode::Sender => self.exec_sender(&parameter, control_address, &core_affinity_manager),
+            NPerfMode::Receiver => self.exec_receiver(&parameter, control_address, &core_affinity_manager)
+        }
+    }
+
+    fn exec_sender(&self, parameter: &Parameter, control_address: SocketAddr, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Option<Statistic> {
+        if parameter.ip.is_multicast() {
+            return self.exec_multicast_sender(parameter, core_affinity_manager);
+        }
+
+        let mut control_channel = match ControlChannel::connect(control_address) {
+            Ok(x) => x,
+            Err(x) => {
+                error!("{}", x);
+                return None;
+            }
+        };
+
+        match self.run_sender_test(parameter, &mut control_channel, core_affinity_manager) {
+            Ok(statistic) => Some(statistic),
+            Err("Test interrupted") => {
+                warn!("Sender interrupted before the measurement started");
+                let _ = control_channel.abort("Sender interrupted");
+                None
+            },
+            Err(x) => {
+                error!("Error running test: {}", x);
+                None
+            }
+        }
+    }
+
+    // In multicast mode, the sender runs the test with every receiver of the group over its own control channel
+    fn exec_multicast_sender(&self, parameter: &Parameter, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Option<Statistic> {
+        let mut control_channels = Vec::new();
+        for receiver in self.multicast_receivers.iter() {
+            match net::resolve_address(receiver, self.control_port.unwrap_or(self.port)).and_then(ControlChannel::connect) {
+                Ok(x) => control_channels.push(x),
+                Err(x) => {
+                    error!("Failed to connect to multicast receiver {}: {}", receiver, x);
+                    return None;
+                }
+            }
+        }
+
+        match self.run_multicast_sender_test(parameter, &mut control_channels, core_affinity_manager) {
+            Ok(statistic) => Some(statistic),
+            Err("Test interrupted") => {
+                warn!("Sender interrupted before the measurement started");
+                control_channels.iter_mut().for_each(|control_channel| { let _ = control_channel.abort("Sender interrupted"); });
+                None
+            },
+            Err(x) => {
+                error!("Error running test: {}", x);
+                None
+            }
+        }
+    }
+
+    fn exec_receiver(&self, parameter: &Parameter, control_address: SocketAddr, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Option<Statistic> {
+        let listener = match TcpListener::bind(control_address) {
+            Ok(x) => x,
+            Err(x) => {
+                error!("Failed to bind control channel to {}: {}", control_address, x);
+                return None;
+            }
+        };
+
+        let session_manager = SessionManager::new(self.port, control_address.port());
+        if self.daemon {
+            self.exec_daemon(parameter, control_address, &listener, core_affinity_manager, &session_manager);
+            return None;
+        }
+
         loop {
-            #[allow(clippy::type_complexity)]
-            let mut fetch_handle: Vec<thread::JoinHandle<Result<(Statistic, Vec<Statistic>), &str>>> = Vec::new();
-    
-            // If socket sharing enabled, creating the socket and bind to port/connect must happen before the threads are spawned
-            let socket = self.create_socket(&parameter);
-
-            // If SQ_POLL and io_uring enabled, create io_uring fd here
-            let io_uring: Option<IoUringNormal> = if parameter.uring_parameter.sqpoll_shared {
-                IoUringNormal::new(parameter.clone(), None).ok()
-            } else {
+            info!("Waiting for a sender to connect to the control channel on {}...", control_address);
+            let statistic = match ControlChannel::accept(&listener) {
+                Ok(mut control_channel) => self.run_receiver_session(parameter, &mut control_channel, core_affinity_manager, &session_manager),
+                Err("Test interrupted") => {
+                    info!("Receiver interrupted while waiting for a sender");
+                    None
+                },
+                Err(x) => {
+                    error!("Error running test: {}", x);
+                    None
+                }
+            };
+
+            if !self.run_infinite || interrupt::interrupted() {
+                return statistic;
+            }
+        }
+    }
+
+    // A test interrupted during the measurement ends with the statistics collected so far, which are printed and returned to the sender.
+    // Before the measurement started there are none, so the sender is only told why the test ends.
+    fn run_receiver_session(&self, parameter: &Parameter, control_channel: &mut ControlChannel, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>, session_manager: &Arc<Mutex<SessionManager>>) -> Option<Statistic> {
+        match self.run_receiver_test(parameter, control_channel, core_affinity_manager, session_manager) {
+            Ok(statistic) => Some(statistic),
+            Err("Test interrupted") => {
+                warn!("Receiver interrupted before the sender started the measurement");
+                // The sender may have closed the control channel already
+                let _ = control_channel.abort("Receiver interrupted");
                 None
+            },
+            Err(x) => {
+                error!("Error running test: {}", x);
+                None
+            }
+        }
+    }
+
+    // The daemon runs every test in its own thread, so senders don't have to wait for each other.
+    // If it is interrupted, it waits for the running tests to finish with their statistics collected so far.
+    fn exec_daemon(&self, parameter: &Parameter, control_address: SocketAddr, listener: &TcpListener, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>, session_manager: &Arc<Mutex<SessionManager>>) {
+        info!("Running as daemon, waiting for senders to connect to the control channel on {}...", control_address);
+        let mut sessions: Vec<thread::JoinHandle<()>> = Vec::new();
+
+        while !interrupt::interrupted() {
+            sessions.retain(|session| !session.is_finished());
+            let Ok(mut control_channel) = ControlChannel::accept(listener) else {
+                continue;
             };
-            let io_uring_fd = io_uring.as_ref().map(|io_uring| io_uring.get_raw_fd()); 
 
+            let nperf = self.clone();
+            let parameter = parameter.clone();
+            let core_affinity_manager = Arc::clone(core_affinity_manager);
+            let session_manager = Arc::clone(session_manager);
 
-            for i in 0..parameter.amount_threads {
-                let receiver_port = if parameter.multiplex_port_receiver != MultiplexPort::Individual {
-                    info!("Receiver port is shared/sharded. Incrementing port number is disabled.");
-                    self.port
-                } else {
-                    self.port + i
-                };
+            sessions.push(thread::spawn(move || {
+                nperf.run_receiver_session(&parameter, &mut control_channel, &core_affinity_manager, &session_manager);
+            }));
+        }
 
-                // Get instance of core affinity manager
-                let core_affinity = Arc::clone(&core_affinity_manager);
-                // Use same test id for all threads if one connection is simulated
-                let test_id = if parameter.simulate_connection == SimulateConnection::Single { 0 } else { i as u64 };
-                let local_port_sender: Option<u16> = if parameter.multiplex_port == MultiplexPort::Sharding { Some(self.sender_port) } else { None };
-                let parameter_clone = parameter.clone();
-
-                fetch_handle.push(thread::spawn(move || Self::exec_thread(parameter_clone, socket, io_uring_fd, receiver_port, local_port_sender, test_id, core_affinity)));
-            }
-    
-            info!("Waiting for all threads to finish...");
-            let mut util = crate::util::cpu_util::CpuUtil::new();
-            util.get_relative_cpu_util();
-
-            let amount_interval_outputs = if parameter.output_interval == 0.0 { 0 } else { (parameter.test_runtime_length as f64 / parameter.output_interval).floor() as usize };
-            debug!("Amount of interval outputs: {}", amount_interval_outputs);
-
-
-            // Iter over join handle and sum up statistics
-            let mut final_statistics = Statistic::new(parameter.clone());
-            let mut final_interval_statistics: Vec<Statistic> = vec![Statistic::new(parameter.clone()); amount_interval_outputs];
-            for (interval_id, statistic) in final_interval_statistics.iter_mut().enumerate() {
-                statistic.interval_id = interval_id as u64 + 1;
-            }
-
-            for handle in fetch_handle {
-                match handle.join() {
-                    Ok(result) => {
-                        // TODO: Merge interval_statistics
-                        if let Ok((statistic, interval_statistics)) = result { 
-                            final_statistics = final_statistics + statistic;
-                            if amount_interval_outputs != 0 {
-                                for statistic in interval_statistics {
-                                    let interval_id = statistic.interval_id as usize - 1;
-                                    final_interval_statistics[interval_id] = final_interval_statistics[interval_id].clone() + statistic;
-                                }
-                            } 
-                        }
+        for session in sessions {
+            session.join().unwrap_or_else(|x| warn!("Error joining session thread: {:?}", x));
+        }
+    }
+
+    fn run_sender_test(&self, parameter: &Parameter, control_channel: &mut ControlChannel, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Result<Statistic, &'static str> {
+        control_channel.send_hello()?;
+        control_channel.send_parameter(parameter)?;
+        let port = control_channel.expect_session()?;
+        if parameter.reverse {
+            return self.run_reverse_sender_test(parameter, port, control_channel, core_affinity_manager);
+        } else if parameter.bidirectional {
+            return self.run_bidirectional_sender_test(parameter, port, control_channel, core_affinity_manager);
+        }
+
+        self.run_forward_sender_test(parameter, port, std::slice::from_mut(control_channel), core_affinity_manager)
+    }
+
+    fn run_multicast_sender_test(&self, parameter: &Parameter, control_channels: &mut [ControlChannel], core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Result<Statistic, &'static str> {
+        for control_channel in control_channels.iter_mut() {
+            control_channel.send_hello()?;
+            control_channel.send_parameter(parameter)?;
+
+            // All receivers have to listen on the ports the group traffic is sent to
+            if control_channel.expect_session()? != self.port {
+                control_channel.abort("Multicast receivers have to use the data ports of the group")?;
+                return Err("Multicast receiver assigned different data ports");
+            }
+        }
+
+        self.run_forward_sender_test(parameter, self.port, control_channels, core_affinity_manager)
+    }
+
+    // Every receiver returns its own statistic, which is combined with the statistic of the sender
+    fn run_forward_sender_test(&self, parameter: &Parameter, port: u16, control_channels: &mut [ControlChannel], core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Result<Statistic, &'static str> {
+        // The receivers answer as soon as all of their threads are ready to receive
+        control_channels.iter_mut().try_for_each(|control_channel| control_channel.expect(ControlMessage::Ready))?;
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut node_threads = self.spawn_node_threads(parameter, port, None, core_affinity_manager, &stop_flag)?;
+
+        // Start the measurement window on the receiver side at the same time the sender threads start sending
+        node_threads.start();
+        let stopped = control_channels.iter_mut().try_for_each(|control_channel| control_channel.send(&ControlMessage::Start))
+            .and_then(|_| Self::wait_for_node_threads(&node_threads, control_channels));
+
+        let mut final_statistic = Self::join_node_threads(parameter, node_threads);
+        let stopped = stopped?;
+
+        // Marks the end of the measurement window on the receivers, which didn't stop the test themselves
+        control_channels.iter_mut().zip(stopped).filter(|(_, stopped)| !stopped).try_for_each(|(control_channel, _)| control_channel.send(&ControlMessage::Stop))?;
+        let receiver_statistics = control_channels.iter_mut().map(|control_channel| control_channel.expect_statistic()).collect::<Result<Vec<_>, _>>()?;
+
+        Self::print_statistics(parameter, &mut final_statistic);
+        for receiver_statistic in receiver_statistics {
+            CombinedStatistic::new(parameter.clone(), final_statistic.clone(), receiver_statistic.clone()).print(parameter.output_format);
+            Self::print_receiver_connections(parameter, receiver_statistic);
+        }
+        Ok(final_statistic)
+    }
+
+    // In reverse mode, the sender measures with receiver threads, while the receiver transmits
+    fn run_reverse_sender_test(&self, parameter: &Parameter, port: u16, control_channel: &mut ControlChannel, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Result<Statistic, &'static str> {
+        let receiver_parameter = parameter.configure_receiver(parameter.clone())?;
+        // The receiver answers as soon as all of its threads wait for the hole punches
+        control_channel.expect(ControlMessage::Ready)?;
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut node_threads = self.spawn_node_threads(&receiver_parameter, port, None, core_affinity_manager, &stop_flag)?;
+
+        // All threads sent their hole punches, so the receiver is able to transmit
+        node_threads.start();
+        let measurement_window = Self::send_measurement_start(control_channel, &node_threads.timer);
+
+        stop_flag.store(true, Ordering::Relaxed);
+        let mut final_statistic = Self::join_node_threads(&receiver_parameter, node_threads);
+
+        let (start_timestamp, end_timestamp) = measurement_window?;
+        final_statistic.set_test_duration(Some(start_timestamp), Some(end_timestamp));
+        let sender_statistic = control_channel.expect_statistic()?;
+
+        Self::print_statistics(&receiver_parameter, &mut final_statistic);
+        CombinedStatistic::new(parameter.clone(), sender_statistic, final_statistic.clone()).print(parameter.output_format);
+        Ok(final_statistic)
+    }
+
+    // In bidirectional mode, the sender transmits and measures the traffic of the receiver at the same time.
+    // The reverse direction uses the ports following the ones of the forward direction.
+    fn run_bidirectional_sender_test(&self, parameter: &Parameter, port: u16, control_channel: &mut ControlChannel, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Result<Statistic, &'static str> {
+        let receiver_parameter = Parameter {
+            reverse: true,
+            ..parameter.configure_receiver(parameter.clone())?
+        };
+        // The receiver answers as soon as all of its threads are ready to receive
+        control_channel.expect(ControlMessage::Ready)?;
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut receiver_threads = self.spawn_node_threads(&receiver_parameter, port + parameter.amount_threads, None, core_affinity_manager, &stop_flag)?;
+        let mut sender_threads = match self.spawn_node_threads(parameter, port, None, core_affinity_manager, &stop_flag) {
+            Ok(x) => x,
+            Err(x) => {
+                Self::abort_node_threads(&receiver_parameter, receiver_threads, &stop_flag);
+                return Err(x);
+            }
+        };
+
+        // The hole punches of the reverse direction are sent, before the sender threads start sending
+        receiver_threads.start();
+        sender_threads.start();
+        let start_result = control_channel.send(&ControlMessage::Start);
+        let start_timestamp = Statistic::get_unix_timestamp();
+        receiver_threads.timer.start_measurement();
+        let stopped = start_result.and_then(|_| Self::wait_for_node_threads(&sender_threads, std::slice::from_mut(control_channel)));
+
+        let mut sender_statistic = Self::join_node_threads(parameter, sender_threads);
+
+        // Marks the end of the forward direction, while the receiver answers as soon as it finished transmitting.
+        // An interrupted receiver stopped the test itself, and answers after it finished transmitting as well.
+        let stop_result = stopped
+            .and_then(|stopped| if stopped[0] { Ok(()) } else { control_channel.send(&ControlMessage::Stop) })
+            .and_then(|_| control_channel.expect_stop());
+        let end_timestamp = Statistic::get_unix_timestamp();
+
+        stop_flag.store(true, Ordering::Relaxed);
+        let mut receiver_statistic = Self::join_node_threads(&receiver_parameter, receiver_threads);
+        receiver_statistic.set_test_duration(Some(start_timestamp), Some(end_timestamp));
+        stop_result?;
+
+        // The receiver returns the statistic of its receiving direction first
+        let remote_receiver_statistic = control_channel.expect_statistic()?;
+        let remote_sender_statistic = control_channel.expect_statistic()?;
+
+        Self::print_statistics(parameter, &mut sender_statistic);
+        Self::print_statistics(&receiver_parameter, &mut receiver_statistic);
+        CombinedStatistic::new(parameter.clone(), sender_statistic.clone(), remote_receiver_statistic.clone()).print(parameter.output_format);
+        Self::print_receiver_connections(parameter, remote_receiver_statistic);
+        CombinedStatistic::new(Parameter { reverse: true, ..parameter.clone() }, remote_sender_statistic, receiver_statistic).print(parameter.output_format);
+        Ok(sender_statistic)
+    }
+
+    // Watches the control channels, while the node threads are running. An interrupted remote host sends STOP, and the test ends early, as soon as all remote hosts stopped it.
+    // Returns which of the remote hosts stopped the test.
+    fn wait_for_node_threads(node_threads: &NodeThreads, control_channels: &mut [ControlChannel]) -> Result<Vec<bool>, &'static str> {
+        let mut stopped = vec![false; control_channels.len()];
+        while !node_threads.finished() {
+            for (control_channel, stopped) in control_channels.iter_mut().zip(stopped.iter_mut()).filter(|(_, stopped)| !**stopped) {
+                match control_channel.poll()? {
+                    Some(ControlMessage::Stop) => {
+                        warn!("Remote host stopped the test");
+                        *stopped = true;
                     },
-                    Err(x) => warn!("Error joining thread: {:?}", x),
+                    Some(message) => {
+                        error!("Expected no control message during the test, but received {:?}", message);
+                        return Err("Unexpected control message received");
+                    },
+                    None => {}
                 }
             }
 
-            for statistic in final_interval_statistics.iter_mut() {
-                // Fix interval CPU util: (statistics.cpu_user_time, statistics.cpu_system_time, statistics.cpu_total_time) = util.get_relative_cpu_util();
-                if statistic.amount_datagrams != 0 {
-                    statistic.print(parameter.output_format, true);
+            if stopped.iter().all(|&stopped| stopped) {
+                node_threads.timer.stop();
+                break;
+            }
+        }
+        Ok(stopped)
+    }
+
+    // Returns the start and end timestamp of the measurement window in reverse mode, which ends as soon as the receiver finished transmitting
+    fn send_measurement_start(control_channel: &mut ControlChannel, timer: &TestTimer) -> Result<(f64, f64), &'static str> {
+        control_channel.send(&ControlMessage::Start)?;
+        let start_timestamp = Statistic::get_unix_timestamp();
+        timer.start_measurement();
+
+        // An interrupted sender stops the transmission of the receiver, which answers as soon as it finished transmitting
+        match control_channel.expect(ControlMessage::Stop) {
+            Ok(_) => info!("Receiver finished transmitting"),
+            Err("Test interrupted") => {
+                warn!("Sender interrupted, stopping the measurement");
+                control_channel.send(&ControlMessage::Stop)?;
+                control_channel.expect_stop()?;
+            },
+            Err(x) => return Err(x)
+        }
+        let end_timestamp = Statistic::get_unix_timestamp();
+
+        Ok((start_timestamp, end_timestamp))
+    }
+
+    fn run_receiver_test(&self, parameter: &Parameter, control_channel: &mut ControlChannel, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>, session_manager: &Arc<Mutex<SessionManager>>) -> Result<Statistic, &'static str> {
+        control_channel.expect_hello()?;
+        let (parameter, reverse_parameter) = &Self::receive_parameter(parameter, control_channel)?;
+
+        // The session and its data ports are released, as soon as the test finished
+        let amount_ports = parameter.amount_ports() + reverse_parameter.as_ref().map_or(0, |x| x.amount_threads);
+        let session = match SessionManager::open_session(session_manager, amount_ports, parameter.transport == Transport::Tcp) {
+            Ok(x) => x,
+            Err(x) => {
+                error!("Receiver can't open a session for the sender: {}", x);
+                control_channel.abort(x)?;
+                return Err(x);
+            }
+        };
+        control_channel.send_session(session.id, session.port)?;
+        let port = session.port;
+
+        if let Some(reverse_parameter) = reverse_parameter {
+            return self.run_bidirectional_receiver_test(parameter, reverse_parameter, port, control_channel, core_affinity_manager);
+        } else if parameter.reverse {
+            return self.run_reverse_receiver_test(parameter, port, control_channel, core_affinity_manager);
+        }
+
+        // The XDP program redirects the frames of the test to the AF_XDP sockets of the threads, until it is detached after the test
+        let xdp_program = if parameter.io_model == IOModel::AfXdp {
+            match xdp::receive_interface(parameter.interface.as_deref(), parameter.ip).and_then(|interface| XdpProgram::new(interface, port, parameter.amount_ports(), parameter.amount_threads)) {
+                Ok(x) => Some(x),
+                Err(x) => {
+                    error!("Receiver can't load the XDP program: {}", x);
+                    control_channel.abort(x)?;
+                    return Err(x);
                 }
             }
+        } else {
+            None
+        };
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut node_threads = Self::abort_test(control_channel, self.spawn_node_threads(parameter, port, xdp_program.as_ref().map(|x| x.xsk_map()), core_affinity_manager, &stop_flag))?;
+
+        // Wait until all threads have bound their sockets, before the sender is allowed to start
+        node_threads.start();
+        let measurement_window = control_channel.send(&ControlMessage::Ready).and_then(|_| Self::receive_measurement_window(parameter, control_channel, &[&node_threads.timer]));
+
+        // The threads drain their sockets and return, as soon as the stop flag is set
+        stop_flag.store(true, Ordering::Relaxed);
+        let mut final_statistic = Self::join_node_threads(parameter, node_threads);
+
+        let (start_timestamp, end_timestamp) = measurement_window?;
+        final_statistic.set_test_duration(Some(start_timestamp), Some(end_timestamp));
+        final_statistic.calculate_statistics();
+        control_channel.send_statistic(&final_statistic)?;
+
+        Self::print_statistics(parameter, &mut final_statistic);
+        Ok(final_statistic)
+    }
 
-            // Update CPU spent time
-            (final_statistics.cpu_user_time, final_statistics.cpu_system_time, final_statistics.cpu_total_time) = util.get_absolut_cpu_util();
+    // In reverse mode, the receiver transmits with sender threads to the addresses the hole punches of the sender arrive from
+    fn run_reverse_receiver_test(&self, parameter: &Parameter, port: u16, control_channel: &mut ControlChannel, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Result<Statistic, &'static str> {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut node_threads = Self::abort_test(control_channel, self.spawn_node_threads(parameter, port, None, core_affinity_manager, &stop_flag))?;
 
-            if final_statistics.amount_datagrams != 0 {
-                final_statistics.print(parameter.output_format, false);
+        // The sender threads bound their sockets and wait for the hole punches, before they finish their setup and wait at the start barrier
+        node_threads.wait_bound();
+        let ready_result = control_channel.send(&ControlMessage::Ready);
+        node_threads.start();
+        // The sender threads start transmitting with START, and the sender stops the transmission early, if it is interrupted
+        let stopped = ready_result
+            .and_then(|_| control_channel.expect(ControlMessage::Start))
+            .inspect(|_| node_threads.timer.start_measurement())
+            .and_then(|_| Self::wait_for_node_threads(&node_threads, std::slice::from_mut(control_channel)));
+        // Without START, the sender threads would wait for it forever
+        if stopped.is_err() {
+            node_threads.timer.stop();
+        }
+
+        let mut final_statistic = Self::join_node_threads(parameter, node_threads);
+        stopped?;
+
+        control_channel.send(&ControlMessage::Stop)?;
+        control_channel.send_statistic(&final_statistic)?;
+
+        Self::print_statistics(parameter, &mut final_statistic);
+        Ok(final_statistic)
+    }
+
+    // In bidirectional mode, the receiver measures the traffic of the sender and transmits to the addresses the hole punches of the sender arrive from
+    fn run_bidirectional_receiver_test(&self, parameter: &Parameter, sender_parameter: &Parameter, port: u16, control_channel: &mut ControlChannel, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>) -> Result<Statistic, &'static str> {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut sender_threads = Self::abort_test(control_channel, self.spawn_node_threads(sender_parameter, port + parameter.amount_threads, None, core_affinity_manager, &stop_flag))?;
+        let mut receiver_threads = match Self::abort_test(control_channel, self.spawn_node_threads(parameter, port, None, core_affinity_manager, &stop_flag)) {
+            Ok(x) => x,
+            Err(x) => {
+                Self::abort_node_threads(sender_parameter, sender_threads, &stop_flag);
+                return Err(x);
             }
+        };
+
+        // Wait until all sockets are bound, before the sender is allowed to start and send its hole punches
+        receiver_threads.start();
+        sender_threads.wait_bound();
+        let ready_result = control_channel.send(&ControlMessage::Ready);
+        sender_threads.start();
+        let measurement_window = ready_result.and_then(|_| Self::receive_measurement_window(parameter, control_channel, &[&receiver_threads.timer, &sender_threads.timer]));
+
+        // The transmitting threads end with the measurement window, in case it was ended early by an interrupt
+        sender_threads.timer.stop();
+        stop_flag.store(true, Ordering::Relaxed);
+        let mut receiver_statistic = Self::join_node_threads(parameter, receiver_threads);
+        let mut sender_statistic = Self::join_node_threads(sender_parameter, sender_threads);
+
+        let (start_timestamp, end_timestamp) = measurement_window?;
+        receiver_statistic.set_test_duration(Some(start_timestamp), Some(end_timestamp));
+        receiver_statistic.calculate_statistics();
+
+        // Marks the end of the reverse direction on the sender side
+        control_channel.send(&ControlMessage::Stop)?;
+        control_channel.send_statistic(&receiver_statistic)?;
+        control_channel.send_statistic(&sender_statistic)?;
+
+        Self::print_statistics(parameter, &mut receiver_statistic);
+        Self::print_statistics(sender_parameter, &mut sender_statistic);
+        Ok(receiver_statistic)
+    }
 
-            info!("All threads finished!");
-            if let Some(socket) = socket {
-                socket.close().expect("Error closing socket");
+    // Tells the sender why the receiver can't set up the test, instead of leaving it waiting for READY
+    fn abort_test<T>(control_channel: &mut ControlChannel, result: Result<T, &'static str>) -> Result<T, &'static str> {
+        result.or_else(|x| {
+            error!("Receiver can't set up the test: {}", x);
+            control_channel.abort(x)?;
+            Err(x)
+        })
+    }
+
+    // The receiver runs the test with the parameter set of the sender, and tells the sender if it can't honor it.
+    // In bidirectional mode, the parameter set of the transmitting direction is returned as well.
+    fn receive_parameter(parameter: &Parameter, control_channel: &mut ControlChannel) -> Result<(Parameter, Option<Parameter>), &'static str> {
+        let sender_parameter = control_channel.expect_parameter()?;
+
+        let configured_parameter = if sender_parameter.reverse {
+            parameter.configure_reverse_sender(sender_parameter).map(|x| (x, None))
+        } else if sender_parameter.bidirectional {
+            parameter.configure_bidirectional_receiver(sender_parameter).map(|(receiver_parameter, sender_parameter)| (receiver_parameter, Some(sender_parameter)))
+        } else {
+            parameter.configure_receiver(sender_parameter).map(|x| (x, None))
+        };
+
+        match configured_parameter {
+            Ok(receiver_parameter) => {
+                info!("Configured receiver with the parameter set of the sender");
+                debug!("Running with Parameter: {:?}", receiver_parameter);
+                Ok(receiver_parameter)
+            },
+            Err(x) => {
+                error!("Receiver can't run the test requested by the sender: {}", x);
+                control_channel.abort(x)?;
+                Err(x)
             }
-    
-            if !(self.run_infinite && parameter.mode == NPerfMode::Receiver) {
-                return Some(final_statistics);
+        }
+    }
+
+    // Returns the start and end timestamp of the measurement window, as announced by the sender over the control channel, and opens it on the timers of all given threads.
+    // The receive timeouts bound the wait for START and STOP, so a receiver doesn't hang if the sender vanishes without closing the control channel.
+    fn receive_measurement_window(parameter: &Parameter, control_channel: &mut ControlChannel, timers: &[&TestTimer]) -> Result<(f64, f64), &'static str> {
+        control_channel.set_receive_timeout(parameter.receive_start_timeout);
+        control_channel.expect(ControlMessage::Start)?;
+        let start_timestamp = Statistic::get_unix_timestamp();
+        timers.iter().for_each(|timer| timer.start_measurement());
+        info!("Sender started the measurement");
+
+        // An interrupted receiver ends the measurement right away, tells the sender to stop, and still returns its statistic to the sender
+        let stop_timeout = if parameter.receive_idle_timeout == 0 { 0 } else { parameter.test_runtime_length * 1000 + parameter.receive_idle_timeout };
+        control_channel.set_receive_timeout(stop_timeout);
+        match control_channel.expect(ControlMessage::Stop) {
+            Ok(_) => info!("Sender stopped the measurement"),
+            Err("Test interrupted") => {
+                warn!("Receiver interrupted, stopping the measurement");
+                control_channel.send(&ControlMessage::Stop)?;
+            },
+            Err(x) => return Err(x)
+        }
+        let end_timestamp = Statistic::get_unix_timestamp();
+        control_channel.set_receive_timeout(0);
+
+        Ok((start_timestamp, end_timestamp))
+    }
+
+    fn spawn_node_threads(&self, parameter: &Parameter, port: u16, xsk_map: Option<RawFd>, core_affinity_manager: &Arc<Mutex<CoreAffinityManager>>, stop_flag: &Arc<AtomicBool>) -> Result<NodeThreads, &'static str> {
+        let mut handles: Vec<thread::JoinHandle<NodeResult>> = Vec::new();
+
+        // If socket sharing enabled, creating the socket and bind to port/connect must happen before the threads are spawned
+        let socket = self.create_socket(parameter, port)?;
+
+        // If SQ_POLL and io_uring enabled, create io_uring fd here
+        let io_uring: Option<IoUringNormal> = if parameter.uring_parameter.sqpoll_shared {
+            IoUringNormal::new(parameter.clone(), None).ok()
+        } else {
+            None
+        };
+        let io_uring_fd = io_uring.as_ref().map(|io_uring| io_uring.get_raw_fd());
+
+        let barrier = Arc::new(Barrier::new(parameter.amount_threads as usize + 1));
+        let bound_barrier = if parameter.mode == NPerfMode::Sender && parameter.reverse { Some(Arc::new(Barrier::new(parameter.amount_threads as usize + 1))) } else { None };
+        // Only the sending side ends the test after its duration
+        let runtime_length = if parameter.mode == NPerfMode::Sender { Some(parameter.test_runtime_length) } else { None };
+        // The sender opens the measurement window as soon as its threads are released, every other side counts its duration and intervals from START
+        let timer = TestTimer::new(runtime_length, parameter.mode == NPerfMode::Receiver || parameter.reverse, parameter.output_interval);
+
+        // Every thread counts into its own counters, which the interval reporter samples
+        let counters: Vec<Arc<ThreadCounters>> = (0..parameter.amount_threads).map(|i| Arc::new(ThreadCounters::new(i as u64, i as u64, Self::thread_port(parameter, port, i)))).collect();
+        let interval_reporter = if parameter.output_interval != 0.0 {
+            Some(IntervalReporter::new(parameter.clone(), Arc::clone(&timer), counters.clone()))
+        } else {
+            None
+        };
+
+        for i in 0..parameter.amount_threads {
+            let receiver_port = Self::thread_port(parameter, port, i);
+            if parameter.multiplex_port_receiver != MultiplexPort::Individual {
+                info!("Receiver port is shared/sharded. Incrementing port number is disabled.");
+            }
+
+            // Get instance of core affinity manager
+            let core_affinity = Arc::clone(core_affinity_manager);
+            // The test id identifies the thread, which has its own packet ID space in every connection it sends on
+            let test_id = i as u64;
+            let local_port_sender: Option<u16> = if parameter.multiplex_port == MultiplexPort::Sharding { Some(self.sender_port) } else { None };
+            let parameter_clone = parameter.clone();
+            let barrier = Arc::clone(&barrier);
+            let bound_barrier = bound_barrier.clone();
+            let timer = Arc::clone(&timer);
+            let counters = Arc::clone(&counters[i as usize]);
+            let stop_flag = Arc::clone(stop_flag);
+
+            handles.push(thread::spawn(move || Self::exec_thread(parameter_clone, socket, io_uring_fd, xsk_map, receiver_port, local_port_sender, test_id, core_affinity, barrier, bound_barrier, timer, counters, stop_flag)));
+        }
+
+        let mut cpu_util = CpuUtil::new();
+        cpu_util.get_relative_cpu_util();
+
+        Ok(NodeThreads {
+            handles,
+            socket,
+            _io_uring: io_uring,
+            cpu_util,
+            barrier,
+            bound_barrier,
+            timer,
+            timer_handle: None,
+            interval_reporter,
+            interval_reporter_handle: None
+        })
+    }
+
+    fn thread_port(parameter: &Parameter, port: u16, thread_index: u16) -> u16 {
+        if parameter.multiplex_port_receiver != MultiplexPort::Individual {
+            port
+        } else {
+            // Every socket of the thread has its own port, starting with this one
+            port + thread_index * parameter.sockets_per_thread
+        }
+    }
+
+    fn join_node_threads(parameter: &Parameter, mut node_threads: NodeThreads) -> Statistic {
+        info!("Waiting for all threads to finish...");
+
+        // Iter over join handle and sum up statistics
+        let mut final_statistics = Statistic::new(parameter.clone());
+
+        let amount_threads = node_threads.handles.len();
+        let mut released: Vec<Instant> = Vec::with_capacity(amount_threads);
+        for handle in node_threads.handles {
+            match handle.join() {
+                Ok(result) => {
+                    if let Ok((statistic, released_at)) = result {
+                        released.push(released_at);
+                        final_statistics = final_statistics + statistic.clone();
+                        // The statistic of every thread is kept next to the aggregate
+                        if amount_threads > 1 {
+                            final_statistics.threads.push(statistic);
+                        }
+                    }
+                },
+                Err(x) => warn!("Error joining thread: {:?}", x),
             }
         }
+
+        // The last interval is printed before the summary
+        if let (Some(interval_reporter), Some(handle)) = (node_threads.interval_reporter, node_threads.interval_reporter_handle) {
+            interval_reporter.finish(handle);
+        }
+        if let Some(timer_handle) = node_threads.timer_handle {
+            node_threads.timer.cancel(timer_handle);
+        }
+
+        // All threads are released by the same barrier, so the spread only shows how long the scheduler took to wake them up
+        if let (Some(first), Some(last)) = (released.iter().min(), released.iter().max()) {
+            final_statistics.thread_start_spread = last.duration_since(*first).as_secs_f64() * 1000.0;
+            debug!("Start spread of the threads: {:.3}ms", final_statistics.thread_start_spread);
+        }
+
+        // Update CPU spent time
+        (final_statistics.cpu_user_time, final_statistics.cpu_system_time, final_statistics.cpu_total_time) = node_threads.cpu_util.get_absolut_cpu_util();
+
+        info!("All threads finished!");
+        if let Some(socket) = node_threads.socket {
+            socket.close().unwrap_or_else(|x| warn!("Error closing socket: {}", x));
+        }
+
+        final_statistics
+    }
+
+    // Ends the threads of one direction, if the other direction can't be set up. They are released from their barriers and return without a measurement.
+    fn abort_node_threads(parameter: &Parameter, mut node_threads: NodeThreads, stop_flag: &AtomicBool) {
+        stop_flag.store(true, Ordering::Relaxed);
+        node_threads.timer.stop();
+        node_threads.wait_bound();
+        node_threads.start();
+        Self::join_node_threads(parameter, node_threads);
+    }
+
+    // Only the receiver accounts per connection, so the sender reports the connections of the receiver next to the combined statistic
+    fn print_receiver_connections(parameter: &Parameter, mut receiver_statistic: Statistic) {
+        receiver_statistic.parameter.output_file_path = parameter.output_file_path.clone();
+        receiver_statistic.print_connections(parameter.output_format);
+    }
+
+    fn print_statistics(parameter: &Parameter, final_statistic: &mut Statistic) {
+        if final_statistic.amount_datagrams != 0 {
+            final_statistic.print(parameter.output_format, false);
+        }
     }
 
     #[allow(clippy::too_many_arguments)]
-    fn exec_thread(parameter: Parameter, socket: Option<Socket>, io_uring: Option<RawFd>, receiver_port: u16, sender_port: Option<u16>, test_id: u64, core_affinity_manager: Arc<Mutex<CoreAffinityManager>>) -> Result<(Statistic, Vec<Statistic>), &'static str> {
-        let sock_address_receiver = SocketAddrV4::new(parameter.ip, receiver_port);
+    fn exec_thread(parameter: Parameter, socket: Option<Socket>, io_uring: Option<RawFd>, xsk_map: Option<RawFd>, receiver_port: u16, sender_port: Option<u16>, test_id: u64, core_affinity_manager: Arc<Mutex<CoreAffinityManager>>, barrier: Arc<Barrier>, bound_barrier: Option<Arc<Barrier>>, timer: Arc<TestTimer>, counters: Arc<ThreadCounters>, stop_flag: Arc<AtomicBool>) -> NodeResult {
+        let sock_address_receiver = SocketAddr::new(parameter.ip, receiver_port);
+        interrupt::block_in_thread();
+
+        // The node waits until all threads are set up, before it starts its measurement.
+        // A thread failing to set up its node drops the start barrier, which waits as well, so neither the other threads nor the main thread are blocked.
+        let mut start_barrier = StartBarrier::new(barrier, bound_barrier);
 
         if parameter.core_affinity {
-            core_affinity_manager.lock().unwrap().set_affinity().unwrap();
+            let core = core_affinity_manager.lock().unwrap().set_affinity().inspect_err(|x| error!("{:?}: Error setting affinity: {}", thread::current().id(), x))?;
+            counters.set_core(core);
         }
-        
-        let mut node: Box<dyn Node> = if parameter.mode == NPerfMode::Sender {
-            Box::new(Sender::new(test_id, sender_port, sock_address_receiver, socket, io_uring, parameter.clone()))
-        } else {
-            Box::new(Receiver::new(sock_address_receiver, socket, io_uring, parameter.clone()))
-        };
+        counters.register_thread();
+
+        let node = Self::create_node(&parameter, socket, io_uring, xsk_map, sock_address_receiver, sender_port, test_id, timer, Arc::clone(&counters), stop_flag, &mut start_barrier);
+        let mut node = node.inspect_err(|x| error!("{:?}: Error setting up node: {}", thread::current().id(), x))?;
 
-        match node.run(parameter.io_model) {
-            Ok(statistic) => { 
+        let result = node.run(parameter.io_model, &mut start_barrier);
+        start_barrier.wait();
+        counters.record_final_cpu_time();
+        match result {
+            Ok(mut statistic) => {
                 info!("{:?}: Finished measurement!", thread::current().id());
-                Ok(statistic)
+                counters.tag(&mut statistic);
+                // The CPU time of the thread is counted from the start of its measurement, while the aggregate gets the CPU time of the whole process
+                let cpu_time = ThreadCpuTime::current() - start_barrier.measurement_cpu_time();
+                (statistic.cpu_user_time, statistic.cpu_system_time, statistic.cpu_total_time) = cpu_time.utilization(statistic.test_duration);
+                Ok((statistic, start_barrier.released().unwrap_or_else(Instant::now)))
             },
             Err(x) => {
                 error!("{:?}: Error running app: {}", thread::current().id(), x);
@@ -144,29 +777,78 @@ impl nPerf {
     }
 
 
-    fn create_socket(&self, parameter: &Parameter) -> Option<Socket> {
+    // In reverse mode, the sockets are set up with hole punches from the measuring side, so the transmitting side can be reached behind NAT
+    #[allow(clippy::too_many_arguments)]
+    fn create_node(parameter: &Parameter, socket: Option<Socket>, io_uring: Option<RawFd>, xsk_map: Option<RawFd>, sock_address_receiver: SocketAddr, sender_port: Option<u16>, test_id: u64, timer: Arc<TestTimer>, counters: Arc<ThreadCounters>, stop_flag: Arc<AtomicBool>, start_barrier: &mut StartBarrier) -> Result<Box<dyn Node>, &'static str> {
+        match (parameter.mode, parameter.reverse) {
+            (NPerfMode::Sender, false) => Ok(Box::new(Sender::new(test_id, sender_port, sock_address_receiver, socket, io_uring, timer, counters, parameter.clone())?)),
+            (NPerfMode::Receiver, false) => {
+                let mut packet_buffer = Receiver::create_packet_buffer(parameter);
+                let frame_ring = Self::create_frame_ring(parameter, xsk_map, sock_address_receiver.port(), test_id as u32, &mut packet_buffer)?;
+                Ok(Box::new(Receiver::new(sock_address_receiver, socket, io_uring, frame_ring, packet_buffer, timer, counters, stop_flag, parameter.clone())?))
+            },
+            (NPerfMode::Sender, true) => {
+                let mut socket = Socket::new(parameter.ip, parameter.transport, parameter.socket_options, parameter.interface.as_deref()).ok_or("Error creating socket")?;
+                socket.bind(sock_address_receiver)?;
+                // The measuring side isn't told to send its hole punches, before all sockets are bound.
+                // The node waits at the start barrier after it accepted the hole punch and set up its buffers and io_uring instance.
+                start_barrier.wait_bound();
+                let sock_address_out = match socket.accept_hole_punch(crate::HOLE_PUNCH_TIMEOUT) {
+                    Ok(x) => x,
+                    Err("TIMEOUT") => return Err("No hole punch received from the sender"),
+                    Err(x) => return Err(x)
+                };
+                Ok(Box::new(Sender::new(test_id, None, sock_address_out, Some(socket), io_uring, timer, counters, parameter.clone())?))
+            },
+            (NPerfMode::Receiver, true) => {
+                let mut socket = Socket::new(parameter.ip, parameter.transport, parameter.socket_options, parameter.interface.as_deref()).ok_or("Error creating socket")?;
+                if let Some(source_ip) = parameter.source_ip {
+                    socket.bind(SocketAddr::new(source_ip, 0))?;
+                }
+                socket.connect(sock_address_receiver)?;
+                socket.send_hole_punch(test_id)?;
+                Ok(Box::new(Receiver::new(sock_address_receiver, Some(socket), io_uring, None, Receiver::create_packet_buffer(parameter), timer, counters, stop_flag, parameter.clone())?))
+            }
+        }
+    }
+
+    // The UDP sockets of the receiver stay bound next to the frame ring, so the kernel doesn't answer the frames with ICMP port unreachable
+    fn create_frame_ring(parameter: &Parameter, xsk_map: Option<RawFd>, port: u16, queue: u32, packet_buffer: &mut PacketBuffer) -> Result<Option<Box<dyn FrameRing>>, &'static str> {
+        match (parameter.io_model, xsk_map) {
+            (IOModel::PacketRing, _) => Ok(Some(Box::new(PacketRing::new_rx(parameter.interface.as_deref(), port, parameter.datagram_size as usize)?))),
+            // Every receiver thread gets the frames of the receive queue with its index
+            (IOModel::AfXdp, Some(xsk_map)) => {
+                let interface = xdp::receive_interface(parameter.interface.as_deref(), parameter.ip)?;
+                Ok(Some(Box::new(XdpSocket::new(interface, xsk_map, queue, packet_buffer.get_buffer_pointer_from_index(0)?)?)))
+            },
+            (IOModel::AfXdp, None) => Err("XDP program isn't loaded"),
+            _ => Ok(None)
+        }
+    }
+
+    fn create_socket(&self, parameter: &Parameter, port: u16) -> Result<Option<Socket>, &'static str> {
         if parameter.mode == NPerfMode::Sender && parameter.multiplex_port == MultiplexPort::Sharing {
             info!("Creating master socket for all sender threads to use, since socket sharing is enabled");
-            let mut socket = Socket::new(parameter.socket_options).expect("Error creating socket");
-            let sock_address_in = SocketAddrV4::new(crate::DEFAULT_SENDER_IP, self.sender_port);
+            let mut socket = Socket::new(parameter.ip, parameter.transport, parameter.socket_options, parameter.interface.as_deref()).ok_or("Error creating socket")?;
+            let sock_address_in = SocketAddr::new(parameter.source_ip.unwrap_or(crate::net::unspecified_address(&parameter.ip)), self.sender_port);
 
-            socket.bind(sock_address_in).expect("Error binding to local port");
+            socket.bind(sock_address_in)?;
 
             // connect (includes bind) to specific 4-tuple, since every thread sends to same port on the receiver side
             if parameter.multiplex_port_receiver == MultiplexPort::Sharding || parameter.multiplex_port_receiver == MultiplexPort::Sharing {
-                let sock_address_out = SocketAddrV4::new(parameter.ip, self.port);
-                socket.connect(sock_address_out).expect("Error connecting to remote host");
+                let sock_address_out = SocketAddr::new(parameter.ip, port);
+                socket.connect(sock_address_out)?;
             }
 
-            Some(socket)
+            Ok(Some(socket))
         } else if parameter.mode == NPerfMode::Receiver && parameter.multiplex_port_receiver == MultiplexPort::Sharing {
             info!("Creating master socket for all receiver threads to use, since socket sharing is enabled");
-            let sock_address_in = SocketAddrV4::new(parameter.ip, self.port);
-            let mut socket = Socket::new(parameter.socket_options).expect("Error creating socket");
-            socket.bind(sock_address_in).expect("Error binding to local port");
-            Some(socket)
+            let sock_address_in = SocketAddr::new(parameter.ip, port);
+            let mut socket = Socket::new(parameter.ip, parameter.transport, parameter.socket_options, parameter.interface.as_deref()).ok_or("Error creating socket")?;
+            socket.bind(sock_address_in)?;
+            Ok(Some(socket))
         } else {
-            None
+            Ok(None)
         }
     }
 }
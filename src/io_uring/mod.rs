@@ -6,13 +6,13 @@ pub mod send;
 use std::os::fd::RawFd;
 use io_uring::{buf_ring::BufRing, cqueue, opcode, types::{SubmitArgs, Timespec}, IoUring, Probe, Submitter};
 use log::{debug, error, info, warn};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use crate::{util::statistic::{Parameter, UringParameter}, Statistic};
 
 const URING_SQ_POLL_TIMEOUT: u32 = 2_000;
 pub const IORING_CQE_F_NOTIF: u32 = 8;
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum UringSqFillingMode {
     #[default]
     Topup,
@@ -20,7 +20,7 @@ pub enum UringSqFillingMode {
     Syscall 
 }
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum UringTaskWork {
     #[default]
     Default,
@@ -29,7 +29,7 @@ pub enum UringTaskWork {
     CoopDefer
 }
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum UringMode {
     #[default]
     Normal,
@@ -45,8 +45,6 @@ pub trait IoUringOperatingModes {
 
     fn get_statistic(&self) -> Statistic;
 
-    fn reset_statistic(&mut self, parameter: Parameter);
-
     // Return 1 if CQ is overflown (EBUSY error returned)
     fn io_uring_enter(submitter: &mut Submitter, timeout: u32, min_complete: usize) -> Result<u64, &'static str> {
         // Simulates https://man7.org/linux/man-pages/man3/io_uring_submit_and_wait_timeout.3.html
@@ -138,6 +136,18 @@ fn create_buf_ring(submitter: &mut Submitter, buffer_size: u16, mss: u32) -> Buf
 }
 
 
+// The upper bits of the user data identify the socket of a request, if a thread drives several sockets with one io_uring instance
+const USER_DATA_SOCKET_SHIFT: u32 = 48;
+
+pub fn encode_user_data(socket_index: usize, value: u64) -> u64 {
+    ((socket_index as u64) << USER_DATA_SOCKET_SHIFT) | value
+}
+
+// Returns the socket index and the packet ID or buffer index of a request
+pub fn decode_user_data(user_data: u64) -> (usize, u64) {
+    ((user_data >> USER_DATA_SOCKET_SHIFT) as usize, user_data & ((1 << USER_DATA_SOCKET_SHIFT) - 1))
+}
+
 // Check flags, if multishot request is still armed
 pub fn check_multishot_status(flags: u32) -> bool {
     if !cqueue::more(flags) {
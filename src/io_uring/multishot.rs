@@ -91,8 +91,4 @@ impl IoUringOperatingModes for IoUringMultishot {
     fn get_statistic(&self) -> Statistic {
         self.statistic.clone()
     }
-
-    fn reset_statistic(&mut self, parameter: Parameter) {
-        self.statistic = Statistic::new(parameter);
-    }
 }
\ No newline at end of file
@@ -1,8 +1,9 @@
 use io_uring::{cqueue::Entry, opcode, types, CompletionQueue, IoUring};
 use log::{debug, trace, warn};
+use std::collections::VecDeque;
 use std::os::{fd::RawFd, unix::io::AsRawFd};
 
-use crate::{util::{packet_buffer::PacketBuffer, statistic::{Parameter, UringParameter}}, Statistic};
+use crate::{net::socket_set::SocketSet, util::{packet_buffer::PacketBuffer, statistic::{Parameter, UringParameter}}, Statistic};
 
 use super::IoUringOperatingModes;
 pub struct IoUringNormal {
@@ -12,7 +13,9 @@ pub struct IoUringNormal {
 }
 
 impl IoUringNormal {
-    fn submit(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, socket_fd: i32) -> Result<u32, &'static str> {
+    // A consumed completion is replaced by a request on the socket it completed on, so every socket keeps its share of the inflight requests.
+    // Requests beyond the consumed completions, e.g. when the ring is filled first, are spread round robin over the sockets.
+    fn submit(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, sockets: &mut SocketSet, resubmit: &mut VecDeque<usize>) -> Result<u32, &'static str> {
         let mut submission_count = 0;
         let mut sq = self.ring.submission();
         debug!("BEGIN io_uring_submit: Current sq len: {}. Dropped messages: {}", sq.len(), sq.dropped());
@@ -20,13 +23,19 @@ impl IoUringNormal {
         for i in 0..amount_requests {
             let packet_buffer_index = packet_buffer.get_buffer_index()?;
             trace!("Message number {}/{}: Used buffer index {}", i, amount_requests, packet_buffer_index);
+            let socket_index = resubmit.front().copied().unwrap_or(sockets.index());
 
-            let sqe = opcode::RecvMsg::new(types::Fd(socket_fd), packet_buffer.get_msghdr_from_index(packet_buffer_index)?)
+            let sqe = opcode::RecvMsg::new(types::Fd(sockets.get(socket_index).get_socket_id()), packet_buffer.get_msghdr_from_index(packet_buffer_index)?)
             .build()
-            .user_data(packet_buffer_index as u64);
+            .user_data(super::encode_user_data(socket_index, packet_buffer_index as u64));
 
             match unsafe { sq.push(&sqe) } {
-                Ok(_) => submission_count += 1,
+                Ok(_) => {
+                    submission_count += 1;
+                    if resubmit.pop_front().is_none() {
+                        sockets.rotate();
+                    }
+                },
                 Err(err) => {
                     // When using submission queue polling, it can happen that the reported queue length is not the same as the actual queue length.
                     warn!("Error pushing io_uring sqe: {}. Stopping submit() after submitting {} entries", err, submission_count);
@@ -40,11 +49,11 @@ impl IoUringNormal {
         Ok(submission_count)
     }
 
-    pub fn fill_sq_and_submit(&mut self, amount_inflight: u32, packet_buffer: &mut PacketBuffer, socket_fd: i32) -> Result<u32, &'static str> {
+    pub fn fill_sq_and_submit(&mut self, amount_inflight: u32, packet_buffer: &mut PacketBuffer, sockets: &mut SocketSet, resubmit: &mut VecDeque<usize>) -> Result<u32, &'static str> {
         let (min_complete, amount_new_requests) = match super::calc_sq_fill_mode(amount_inflight, self.parameter, &mut self.ring) {
             (0,0) => return Ok(0),
             (to_submit, min_complete) => {
-                (min_complete, self.submit(to_submit, packet_buffer, socket_fd)?)
+                (min_complete, self.submit(to_submit, packet_buffer, sockets, resubmit)?)
             }
         };
 
@@ -90,8 +99,4 @@ impl IoUringOperatingModes for IoUringNormal {
     fn get_statistic(&self) -> Statistic {
         self.statistic.clone()
     }
-
-    fn reset_statistic(&mut self, parameter: Parameter) {
-        self.statistic = Statistic::new(parameter);
-    }
 }
\ No newline at end of file
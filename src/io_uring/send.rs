@@ -16,7 +16,7 @@ pub struct IoUringSend {
 }
 
 impl IoUringSend {
-    fn submit(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32) -> Result<usize, &'static str> {
+    fn submit(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32, socket_index: usize) -> Result<usize, &'static str> {
         let mut submission_count = 0;
         let mut sq = self.ring.submission();
         let packets_per_buffer = packet_buffer.packets_amount_per_msghdr();
@@ -31,7 +31,7 @@ impl IoUringSend {
 
             let sqe = opcode::SendMsg::new(types::Fd(socket_fd), packet_buffer.get_msghdr_from_index(i)?)
                 .build()
-                .user_data(packet_id);
+                .user_data(super::encode_user_data(socket_index, packet_id));
 
             match unsafe { sq.push(&sqe) } {
                 Ok(_) => submission_count += 1,
@@ -47,7 +47,7 @@ impl IoUringSend {
         Ok(submission_count)
     }
 
-    fn submit_zc(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32) -> Result<usize, &'static str> {
+    fn submit_zc(&mut self, amount_requests: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32, socket_index: usize) -> Result<usize, &'static str> {
         let mut submission_count = 0;
         let mut amount_datagrams = 0;
         let mut sq = self.ring.submission();
@@ -66,7 +66,7 @@ impl IoUringSend {
                 opcode::SendMsgZc::new(types::Fd(socket_fd), packet_buffer.get_msghdr_from_index(packet_buffer_index)?)
                 .ioprio(IORING_SEND_ZC_REPORT_USAGE)
                 .build()
-                .user_data(packet_buffer_index as u64);
+                .user_data(super::encode_user_data(socket_index, packet_buffer_index as u64));
 
             match unsafe { sq.push(&sqe) } {
                 Ok(_) => submission_count += 1,
@@ -82,16 +82,16 @@ impl IoUringSend {
         Ok(submission_count)
     }
 
-    pub fn fill_sq_and_submit(&mut self, amount_inflight: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32) -> Result<usize, &'static str> {
+    pub fn fill_sq_and_submit(&mut self, amount_inflight: usize, packet_buffer: &mut PacketBuffer, next_packet_id: u64, socket_fd: i32, socket_index: usize) -> Result<usize, &'static str> {
         let mut amount_new_requests = 0;
 
         let min_complete = match super::calc_sq_fill_mode(amount_inflight as u32, self.parameter, &mut self.ring) {
             (0,0) => return Ok(0),
             (to_submit, min_complete) => {
                 amount_new_requests += if self.zerocopy {
-                    self.submit_zc(to_submit, packet_buffer, next_packet_id, socket_fd)?
+                    self.submit_zc(to_submit, packet_buffer, next_packet_id, socket_fd, socket_index)?
                 } else {
-                    self.submit(to_submit, packet_buffer, next_packet_id, socket_fd)?
+                    self.submit(to_submit, packet_buffer, next_packet_id, socket_fd, socket_index)?
                 };
                 
                 min_complete
@@ -135,8 +135,4 @@ impl IoUringOperatingModes for IoUringSend {
     fn get_statistic(&self) -> Statistic {
         self.statistic.clone()
     }
-
-    fn reset_statistic(&mut self, parameter: Parameter) {
-        self.statistic = Statistic::new(parameter);
-    }
 }
\ No newline at end of file
@@ -5,28 +5,55 @@ mod command_parser;
 mod executor;
 mod io_uring;
 
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
 pub use util::statistic::Statistic;
 
 // const UDP_RATE: usize = (1024 * 1024) // /* 1 Mbps */
 const DEFAULT_MSS: u32= 1472;
 const DEFAULT_UDP_DATAGRAM_SIZE: u32 = 1472;
+// IPv6 headers are 20 bytes longer, so the defaults are reduced to fit into a 1500 byte MTU as well
+const DEFAULT_MSS_IPV6: u32 = 1452;
+const DEFAULT_UDP_DATAGRAM_SIZE_IPV6: u32 = 1452;
 const DEFAULT_GSO_BUFFER_SIZE: u32 = 64768 ; // 65507 is the possible maximum , but then the last packet is only have full -> 1472 * 44 = 64768
 //const MAX_SOCKET_BUFFER_SIZE: u32 = 2129920; // 2MB; // The buffer size will be doubled by the kernel to account for overhead. See man 7 socket
 const DEFAULT_SOCKET_BUFFER_SIZE: u32 = 212992; 
 const DEFAULT_DURATION: u64 = 10; // /* seconds */
 const DEFAULT_RECEIVER_PORT: u16 = 45001;
 const DEFAULT_SENDER_IP: Ipv4Addr = Ipv4Addr::new(0,0,0,0);
+const DEFAULT_SENDER_IPV6: Ipv6Addr = Ipv6Addr::UNSPECIFIED;
 const DEFAULT_SENDER_PORT: u16 = 46001;
 const DEFAULT_INTERVAL: f64 = 0.0; // /* seconds */
 const DEFAULT_BANDWIDTH: u64 = 0; // /* Megabits per second (Mbit/s) */
-const WAIT_CONTROL_MESSAGE: u64 = 400; // /* milliseconds */
+const DEFAULT_MULTICAST_TTL: u32 = 1;
+// A receiver thread ends without data for this long, so a lost sender doesn't block it forever. 0 waits until the sender stops the test.
+const DEFAULT_RECEIVE_START_TIMEOUT: u64 = 10_000; // in milliseconds
+const DEFAULT_RECEIVE_IDLE_TIMEOUT: u64 = 5_000; // in milliseconds
 const DEFAULT_FILE_NAME: &str = "nperf-output.csv";
 const MAX_TEST_ID: usize = 1024;
+// Simulated connections are numbered from 0. Receivers drop datagrams with connection IDs beyond the connections of the test.
+const MAX_CONNECTION_ID: usize = 4096;
+
+// The timer thread of a test updates its coarse clock this often, the interval boundaries and the end of the test are met exactly
+const TIMER_TICK: u64 = 10; // in milliseconds
+
+// The control channel returns from waiting this often, to check if the test was interrupted
+const CONTROL_CHANNEL_POLL_TIMEOUT: u64 = 100; // in milliseconds
+
+// Reverse mode: Hole punch datagrams are sent multiple times, in case one gets lost
+const AMOUNT_HOLE_PUNCH_DATAGRAMS: usize = 3;
+const HOLE_PUNCH_TIMEOUT: i32 = 5000; // in milliseconds
+
+// TCP mode: Every sender thread opens one connection to its receiver thread
+const TCP_CONNECT_TIMEOUT: i32 = 5000; // in milliseconds
+const TCP_LISTEN_BACKLOG: i32 = 1;
+
+const IPV4_HEADER_LENGTH: u32 = 20;
+const IPV6_HEADER_LENGTH: u32 = 40;
+const UDP_HEADER_LENGTH: u32 = 8;
 
 // Maximum datagram size UDP is (64K - 1) - IP and UDP header sizes 
-const MAX_UDP_DATAGRAM_SIZE: u32 = 65535 - 8 - 20;
+const MAX_UDP_DATAGRAM_SIZE: u32 = 65535 - UDP_HEADER_LENGTH - IPV4_HEADER_LENGTH;
 const DEFAULT_AMOUNT_MSG_WHEN_SENDMMSG: usize = 1;
 const LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER: usize = 24;
 
@@ -40,4 +67,14 @@ const URING_ADDITIONAL_BUFFER_LENGTH: i32 = 40;
 const URING_ENTER_TIMEOUT: u32 = 10_000_000;
 const URING_SQPOLL_CPU: u32 = 0;
 
+// packet ring defaults (AF_PACKET with TPACKET_V3)
+const PACKET_RING_BLOCK_SIZE: u32 = 1 << 18; // Grows to the frame size, if a frame doesn't fit into a block
+const PACKET_RING_AMOUNT_BLOCKS: u32 = 16;
+const PACKET_RING_BLOCK_TIMEOUT: u32 = 10; // in milliseconds, after which the kernel hands a partially filled block of the RX ring to the receiver
+
+// AF_XDP defaults (XDP generic mode)
+const XDP_AMOUNT_FRAMES: u32 = 4096; // Frames of the UMEM, which is also the size of the fill and RX ring
+const XDP_FRAME_SIZE: u32 = 4096; // Power of 2 between 2048 and the page size
+const XDP_PACKET_HEADROOM: u32 = 256; // The kernel copies the frame behind this headroom into the UMEM frame
+
 pub use command_parser::nPerf;
@@ -0,0 +1,261 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::os::fd::AsRawFd;
+use std::time::{Duration, Instant};
+
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::util::{interrupt, statistic::{ConnectionStatistic, Parameter}};
+use crate::Statistic;
+
+// Sender and receiver refuse to run a test, if the other side speaks a different version of the control protocol
+pub const CONTROL_PROTOCOL_VERSION: u32 = 2;
+
+// Messages exchanged over the TCP control channel. Every message is serialized as a single JSON line.
+// The sender connects and sends HELLO, followed by its parameter set with CONFIGURE. The receiver configures, opens a SESSION with the data ports the sender has to use,
+// sets up its threads and answers with READY.
+// START and STOP mark the boundaries of the measurement window on both sides. DONE confirms the teardown of the receiver threads and returns the final statistic of the receiver with its connections.
+// An interrupted side sends STOP right away, so the other side ends the test early, and the statistics are exchanged as usual.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ControlMessage {
+    Hello { version: u32 },
+    Configure { parameter: Box<Parameter> },
+    Session { id: u64, port: u16 },
+    Ready,
+    Start,
+    Stop,
+    // The connections of the receiver aren't part of the statistic record, so they are sent next to it
+    Done { statistic: Box<Statistic>, #[serde(default)] connections: Vec<ConnectionStatistic> },
+    Error { reason: String },
+}
+
+pub struct ControlChannel {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    // A partially received message is kept, until the rest of its line arrives
+    line: String,
+    // Bounds the wait for the next message, if set
+    receive_timeout: Option<Duration>,
+}
+
+impl ControlChannel {
+    pub fn connect(address: SocketAddr) -> Result<ControlChannel, &'static str> {
+        match TcpStream::connect(address) {
+            Ok(stream) => {
+                info!("Connected control channel to {}", address);
+                Self::new(stream)
+            },
+            Err(x) => {
+                error!("Failed to connect control channel to {}: {}", address, x);
+                Err("Start the receiver first! Abort measurement...")
+            }
+        }
+    }
+
+    pub fn accept(listener: &TcpListener) -> Result<ControlChannel, &'static str> {
+        // Waits in steps for the next sender, so an interrupted receiver stops waiting
+        let mut pollfd = libc::pollfd { fd: listener.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        while unsafe { libc::poll(&mut pollfd, 1, crate::CONTROL_CHANNEL_POLL_TIMEOUT as i32) } <= 0 {
+            if interrupt::interrupted() {
+                return Err("Test interrupted");
+            }
+        }
+
+        match listener.accept() {
+            Ok((stream, remote_address)) => {
+                info!("Accepted control channel from {}", remote_address);
+                Self::new(stream)
+            },
+            Err(x) => {
+                error!("Failed to accept control channel: {}", x);
+                Err("Failed to accept control channel")
+            }
+        }
+    }
+
+    fn new(stream: TcpStream) -> Result<ControlChannel, &'static str> {
+        // Control messages are tiny and mark the measurement window, so they shouldn't be delayed by Nagle's algorithm
+        stream.set_nodelay(true).map_err(|_| "Failed to set TCP_NODELAY on control channel")?;
+        // Receiving returns from time to time, to check if the test was interrupted or the receive timeout expired
+        stream.set_read_timeout(Some(Duration::from_millis(crate::CONTROL_CHANNEL_POLL_TIMEOUT))).map_err(|_| "Failed to set receive timeout on control channel")?;
+        let reader = BufReader::new(stream.try_clone().map_err(|_| "Failed to clone control channel stream")?);
+
+        Ok(ControlChannel {
+            stream,
+            reader,
+            line: String::new(),
+            receive_timeout: None
+        })
+    }
+
+    pub fn send(&mut self, message: &ControlMessage) -> Result<(), &'static str> {
+        debug!("Sending control message: {:?}", message);
+        let mut line = serde_json::to_string(message).map_err(|_| "Failed to serialize control message")?;
+        line.push('\n');
+
+        match self.stream.write_all(line.as_bytes()) {
+            Ok(_) => Ok(()),
+            Err(x) => {
+                error!("Failed to send control message: {}", x);
+                Err("Failed to send control message")
+            }
+        }
+    }
+
+    pub fn recv(&mut self) -> Result<ControlMessage, &'static str> {
+        self.recv_message(true)
+    }
+
+    // An interruptible wait returns as soon as the test is interrupted, while the statistics of the remote host are still awaited after it
+    fn recv_message(&mut self, interruptible: bool) -> Result<ControlMessage, &'static str> {
+        let wait_start = Instant::now();
+
+        loop {
+            if let Some(message) = self.poll()? {
+                return Ok(message);
+            }
+            if interruptible && interrupt::interrupted() {
+                return Err("Test interrupted");
+            }
+            if self.receive_timeout.is_some_and(|timeout| wait_start.elapsed() >= timeout) {
+                error!("Timeout waiting for a control message from the remote host");
+                return Err("Timeout waiting for a control message");
+            }
+        }
+    }
+
+    // Returns None, if no complete message arrived within the poll timeout of the control channel
+    pub fn poll(&mut self) -> Result<Option<ControlMessage>, &'static str> {
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => Err("Control channel closed by remote host"),
+            Ok(_) => {
+                let message = serde_json::from_str(&self.line).map_err(|_| "Failed to parse control message");
+                self.line.clear();
+                let message = message?;
+                debug!("Received control message: {:?}", message);
+                Ok(Some(message))
+            },
+            Err(x) if x.kind() == std::io::ErrorKind::WouldBlock || x.kind() == std::io::ErrorKind::TimedOut => Ok(None),
+            Err(x) => {
+                error!("Failed to receive control message: {}", x);
+                Err("Failed to receive control message")
+            }
+        }
+    }
+
+    // Bounds the time recv() waits for the next message, in milliseconds. 0 waits forever.
+    pub fn set_receive_timeout(&mut self, timeout: u64) {
+        self.receive_timeout = Some(Duration::from_millis(timeout)).filter(|timeout| !timeout.is_zero());
+    }
+
+    // Receives the next message and checks if it is the expected one
+    pub fn expect(&mut self, expected: ControlMessage) -> Result<(), &'static str> {
+        let message = self.recv()?;
+        Self::check_message(message, expected)
+    }
+
+    // Waits for STOP of the remote host even after an interrupt, since the statistics of the remote host follow it
+    pub fn expect_stop(&mut self) -> Result<(), &'static str> {
+        let message = self.recv_message(false)?;
+        Self::check_message(message, ControlMessage::Stop)
+    }
+
+    fn check_message(message: ControlMessage, expected: ControlMessage) -> Result<(), &'static str> {
+        match message {
+            message if std::mem::discriminant(&message) == std::mem::discriminant(&expected) => Ok(()),
+            ControlMessage::Error { reason } => {
+                error!("Remote host aborted the test: {}", reason);
+                Err("Remote host aborted the test")
+            },
+            message => {
+                error!("Expected control message {:?}, but received {:?}", expected, message);
+                Err("Unexpected control message received")
+            }
+        }
+    }
+
+    pub fn send_hello(&mut self) -> Result<(), &'static str> {
+        self.send(&ControlMessage::Hello { version: CONTROL_PROTOCOL_VERSION })
+    }
+
+    pub fn expect_hello(&mut self) -> Result<(), &'static str> {
+        match self.recv()? {
+            ControlMessage::Hello { version } if version == CONTROL_PROTOCOL_VERSION => Ok(()),
+            ControlMessage::Hello { version } => {
+                error!("Sender uses control protocol version {}, but receiver uses version {}!", version, CONTROL_PROTOCOL_VERSION);
+                self.send(&ControlMessage::Error { reason: format!("Unsupported control protocol version {}", version) })?;
+                Err("Control protocol version mismatch")
+            },
+            message => {
+                error!("Expected HELLO control message, but received {:?}", message);
+                Err("Unexpected control message received")
+            }
+        }
+    }
+
+    pub fn send_parameter(&mut self, parameter: &Parameter) -> Result<(), &'static str> {
+        self.send(&ControlMessage::Configure { parameter: Box::new(parameter.clone()) })
+    }
+
+    pub fn expect_parameter(&mut self) -> Result<Parameter, &'static str> {
+        match self.recv()? {
+            ControlMessage::Configure { parameter } => Ok(*parameter),
+            ControlMessage::Error { reason } => {
+                error!("Remote host aborted the test: {}", reason);
+                Err("Remote host aborted the test")
+            },
+            message => {
+                error!("Expected CONFIGURE control message, but received {:?}", message);
+                Err("Unexpected control message received")
+            }
+        }
+    }
+
+    pub fn send_session(&mut self, id: u64, port: u16) -> Result<(), &'static str> {
+        self.send(&ControlMessage::Session { id, port })
+    }
+
+    // Returns the first data port the receiver assigned to the session
+    pub fn expect_session(&mut self) -> Result<u16, &'static str> {
+        match self.recv()? {
+            ControlMessage::Session { id, port } => {
+                info!("Receiver opened session {} on data port {}", id, port);
+                Ok(port)
+            },
+            ControlMessage::Error { reason } => {
+                error!("Remote host aborted the test: {}", reason);
+                Err("Remote host aborted the test")
+            },
+            message => {
+                error!("Expected SESSION control message, but received {:?}", message);
+                Err("Unexpected control message received")
+            }
+        }
+    }
+
+    pub fn send_statistic(&mut self, statistic: &Statistic) -> Result<(), &'static str> {
+        self.send(&ControlMessage::Done { statistic: Box::new(statistic.clone()), connections: statistic.connections.clone() })
+    }
+
+    pub fn expect_statistic(&mut self) -> Result<Statistic, &'static str> {
+        match self.recv_message(false)? {
+            ControlMessage::Done { statistic, connections } => Ok(Statistic { connections, ..*statistic }),
+            // The STOP of an interrupted remote host crosses the own one, if both sides end the test at the same time
+            ControlMessage::Stop => self.expect_statistic(),
+            ControlMessage::Error { reason } => {
+                error!("Remote host aborted the test: {}", reason);
+                Err("Remote host aborted the test")
+            },
+            message => {
+                error!("Expected DONE control message, but received {:?}", message);
+                Err("Unexpected control message received")
+            }
+        }
+    }
+
+    // Tells the remote host why the test can't be run
+    pub fn abort(&mut self, reason: &str) -> Result<(), &'static str> {
+        self.send(&ControlMessage::Error { reason: reason.to_string() })
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::Error;
+use log::{debug, error, trace};
+
+use crate::util::EpollTrigger;
+
+// Every node registers its sockets with its own epoll instance. If the threads share a socket, EPOLLEXCLUSIVE wakes up only one of their instances.
+// The sockets are identified by their index in the events returned by wait().
+pub struct Epoll {
+    fd: i32,
+    events: Vec<libc::epoll_event>
+}
+
+impl Epoll {
+    pub fn new(sockets: &[i32], events: libc::c_int, trigger: EpollTrigger, exclusive: bool) -> Result<Epoll, &'static str> {
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd == -1 {
+            error!("Error creating epoll instance: {}", Error::last_os_error());
+            return Err("Error creating epoll instance");
+        }
+
+        let mut flags = events as u32;
+        if trigger == EpollTrigger::Edge {
+            flags |= libc::EPOLLET as u32;
+        }
+        if exclusive {
+            flags |= libc::EPOLLEXCLUSIVE as u32;
+        }
+
+        for (index, &socket) in sockets.iter().enumerate() {
+            let mut event = libc::epoll_event {
+                events: flags,
+                u64: index as u64
+            };
+
+            if unsafe { libc::epoll_ctl(fd, libc::EPOLL_CTL_ADD, socket, &mut event) } == -1 {
+                error!("Error adding socket {} to epoll instance: {}", socket, Error::last_os_error());
+                unsafe { libc::close(fd) };
+                return Err("Error adding socket to epoll instance");
+            }
+        }
+
+        debug!("Created epoll instance {} for sockets {:?} with events {:#x}", fd, sockets, flags);
+        Ok(Epoll {
+            fd,
+            events: vec![libc::epoll_event { events: 0, u64: 0 }; sockets.len()]
+        })
+    }
+
+    // timeout = -1: Block until a socket is ready. Returns the events of the ready sockets.
+    pub fn wait(&mut self, timeout: i32) -> Result<&[libc::epoll_event], &'static str> {
+        let result = unsafe {
+            libc::epoll_wait(
+                self.fd,
+                self.events.as_mut_ptr(),
+                self.events.len() as i32,
+                timeout
+            )
+        };
+
+        if result == -1 {
+            error!("Error occured executing epoll_wait(): {}", Error::last_os_error());
+            Err("Error occured executing epoll_wait()")
+        } else if result == 0 {
+            debug!("epoll_wait returned due to timeout");
+            Err("TIMEOUT")
+        } else {
+            trace!("epoll_wait returned with {} ready sockets", result);
+            Ok(&self.events[..result as usize])
+        }
+    }
+}
+
+impl Drop for Epoll {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
@@ -1,85 +1,175 @@
-use std::{net::Ipv4Addr, str::FromStr};
+use std::{net::{IpAddr, SocketAddr, ToSocketAddrs}, str::FromStr};
 
-use log::warn;
+use log::{error, info, warn};
 
 pub mod socket;
 pub mod socket_options;
+pub mod control_channel;
+pub mod epoll;
+pub mod socket_set;
+pub mod packet_ring;
+pub mod xdp;
 
-#[repr(u64)]
-#[derive(Debug)]
+// Identifies datagrams sent by nPerf ("nPRF" in ASCII)
+pub const HEADER_MAGIC: u32 = 0x6E50_5246;
+// Receivers drop datagrams with a different header version
+pub const HEADER_VERSION: u8 = 2;
+pub const LEN_HEADER: usize = 32;
+
+// Ring the kernel copies the received frames into, so the receiver parses the IPv4/UDP headers itself instead of reading from its UDP sockets
+pub trait FrameRing {
+    // Passes the UDP payload of every frame handed over by the kernel to process and returns the amount of frames, or EAGAIN if there are none
+    fn recv(&mut self, process: &mut dyn FnMut(&[u8])) -> Result<u64, &'static str>;
+    // Waits with poll() until the ring is ready for the given events
+    fn wait(&self, events: libc::c_short, timeout: i32) -> Result<(), &'static str>;
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum MessageType {
-    INIT,
-    MEASUREMENT,
-    LAST
+    MEASUREMENT = 1,
+    HOLEPUNCH = 2
 }
 
-const LEN_HEADER: usize = std::mem::size_of::<MessageHeader>();
+impl TryFrom<u8> for MessageType {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(MessageType::MEASUREMENT),
+            2 => Ok(MessageType::HOLEPUNCH),
+            _ => Err("Unknown message type")
+        }
+    }
+}
 
-#[derive(Debug)]
-#[repr(transparent)]
+#[derive(Debug, Clone, Copy)]
 pub struct MessageHeader {
-    header: [u64; 3]
+    pub mtype: MessageType,
+    pub test_id: u64,
+    pub packet_id: u64,
+    pub connection_id: u64
 }
-// First 8 bytes: MessageType
-// Second 8 bytes: Test ID
-// Third 8 bytes: Packet ID
+// All fields are encoded in network byte order
+// Bytes 0-3: Magic number
+// Byte 4: Header version
+// Byte 5: MessageType
+// Bytes 6-7: Reserved
+// Bytes 8-15: Test ID
+// Bytes 16-23: Packet ID
+// Bytes 24-31: Connection ID
 
 impl MessageHeader {
-    pub fn new(mtype: MessageType, test_id: u64, packet_id: u64) -> MessageHeader {
+    pub fn new(mtype: MessageType, test_id: u64, packet_id: u64, connection_id: u64) -> MessageHeader {
         MessageHeader {
-            header: [mtype as u64, test_id, packet_id]
+            mtype,
+            test_id,
+            packet_id,
+            connection_id
         }
     }
 
-    pub fn serialize(&self) -> &[u8] {
-        unsafe {
-            std::slice::from_raw_parts(self.header.as_ptr() as *const u8, LEN_HEADER)
+    pub fn serialize(&self) -> [u8; LEN_HEADER] {
+        let mut buffer = [0_u8; LEN_HEADER];
+        buffer[0..4].copy_from_slice(&HEADER_MAGIC.to_be_bytes());
+        buffer[4] = HEADER_VERSION;
+        buffer[5] = self.mtype as u8;
+        buffer[8..16].copy_from_slice(&self.test_id.to_be_bytes());
+        buffer[16..24].copy_from_slice(&self.packet_id.to_be_bytes());
+        buffer[24..32].copy_from_slice(&self.connection_id.to_be_bytes());
+        buffer
+    }
+
+    // Checks the datagram for a valid header, so that foreign or corrupt datagrams can be dropped
+    pub fn decode(buffer: &[u8]) -> Result<MessageHeader, &'static str> {
+        if buffer.len() < LEN_HEADER {
+            return Err("Datagram is too short for a header");
+        }
+
+        if u32::from_be_bytes(buffer[0..4].try_into().unwrap()) != HEADER_MAGIC {
+            return Err("Invalid magic number in header");
         }
+
+        if buffer[4] != HEADER_VERSION {
+            return Err("Unsupported header version");
+        }
+
+        Ok(MessageHeader {
+            mtype: MessageType::try_from(buffer[5])?,
+            test_id: u64::from_be_bytes(buffer[8..16].try_into().unwrap()),
+            packet_id: u64::from_be_bytes(buffer[16..24].try_into().unwrap()),
+            connection_id: u64::from_be_bytes(buffer[24..32].try_into().unwrap())
+        })
     }
 
     pub fn set_packet_id(&mut self, packet_id: u64) {
-        self.header[2] = packet_id;
+        self.packet_id = packet_id;
     }
 
-    pub fn get_packet_id(buffer: &[u8]) -> u64 {
-        unsafe {
-            let header = std::mem::transmute::<&[u8], &[u64]>(buffer);
-            header[2]
-        }
+    // Updates the packet ID and connection ID of an already serialized header
+    pub fn set_packet_id_raw(buffer: &mut [u8], packet_id: u64, connection_id: u64) {
+        buffer[16..24].copy_from_slice(&packet_id.to_be_bytes());
+        buffer[24..32].copy_from_slice(&connection_id.to_be_bytes());
     }
+}
 
-    pub fn set_packet_id_raw(buffer: &mut [u8], packet_id: u64) {
-        unsafe {
-            let header = std::mem::transmute::<&mut [u8], &mut [u64]>(buffer);
-            header[2] = packet_id;
-        }
+
+// Accepts IPv4 and IPv6 addresses. IPv6 addresses may be enclosed in brackets, e.g. [::1]
+pub fn parse_ip(adress: &str) -> Result<IpAddr, &'static str> {
+    let adress = adress.strip_prefix('[').and_then(|x| x.strip_suffix(']')).unwrap_or(adress);
+    match IpAddr::from_str(adress) {
+        Ok(x) => Ok(x),
+        Err(_) => Err("Invalid IP address!"),
     }
+}
 
-    pub fn get_test_id(buffer: &[u8]) -> u64 {
-        unsafe {
-            let header = std::mem::transmute::<&[u8], &[u64]>(buffer);
-            header[1]
-        }
+// Accepts literal IP addresses and hostnames. A hostname is resolved to the first address of the same family as the local address, e.g. the source IP.
+// Otherwise a hostname like localhost may resolve to ::1 first, which can't be reached from an IPv4 source address.
+pub fn resolve_ip(host: &str, local_ip: Option<IpAddr>) -> Result<IpAddr, &'static str> {
+    if let Ok(ip) = parse_ip(host) {
+        return Ok(ip);
     }
 
-    pub fn get_message_type(buffer: &[u8]) -> MessageType {
-        unsafe {
-            let header = std::mem::transmute::<&[u8], &[u64]>(buffer);
-            std::mem::transmute::<u64, MessageType>(header[0])
+    let same_family = |ip: &IpAddr| match local_ip {
+        Some(local_ip) => local_ip.is_ipv4() == ip.is_ipv4(),
+        None => true
+    };
+
+    match (host, 0).to_socket_addrs().map(|addresses| addresses.map(|address| address.ip()).find(same_family)) {
+        Ok(Some(ip)) => {
+            info!("Resolved hostname {} to {}", host, ip);
+            Ok(ip)
+        },
+        Ok(None) => Err("Hostname doesn't resolve to any address of the family of the source IP!"),
+        Err(x) => {
+            error!("Failed to resolve hostname {}: {}", host, x);
+            Err("Failed to resolve hostname!")
         }
     }
-        
-    pub fn len(&self) -> usize {
-        LEN_HEADER
-    }
 }
 
+// Accepts an IP address or hostname with an optional port, e.g. host:5001 or [::1]:5001. Uses the default port, if none is given.
+pub fn resolve_address(address: &str, default_port: u16) -> Result<SocketAddr, &'static str> {
+    if let Ok(address) = SocketAddr::from_str(address) {
+        return Ok(address);
+    }
 
-pub fn parse_ipv4(adress: &str) -> Result<Ipv4Addr, &'static str> {
-    match Ipv4Addr::from_str(adress) {
-        Ok(x) => Ok(x),
-        Err(_) => Err("Invalid IPv4 address!"),
+    match address.rsplit_once(':') {
+        // A bare IPv6 address also contains colons, so only a hostname is split at its last colon
+        Some((host, port)) if !host.contains(':') => {
+            let port = port.parse::<u16>().map_err(|_| "Invalid port!")?;
+            Ok(SocketAddr::new(resolve_ip(host, None)?, port))
+        },
+        _ => Ok(SocketAddr::new(resolve_ip(address, None)?, default_port))
+    }
+}
+
+// Wildcard address of the same address family, e.g. to bind the local side of a socket
+pub fn unspecified_address(ip: &IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => IpAddr::V4(crate::DEFAULT_SENDER_IP),
+        IpAddr::V6(_) => IpAddr::V6(crate::DEFAULT_SENDER_IPV6),
     }
 }
 
@@ -0,0 +1,365 @@
+use std::{ffi::CString, fs, io::Error, mem::MaybeUninit, net::{Ipv4Addr, SocketAddrV4}, ptr, sync::atomic::{fence, Ordering}};
+use log::{debug, error, info, trace};
+
+use super::{FrameRing, MessageHeader};
+
+const LEN_ETHERNET_HEADER: usize = 14;
+pub const LEN_FRAME_HEADERS: usize = LEN_ETHERNET_HEADER + crate::IPV4_HEADER_LENGTH as usize + crate::UDP_HEADER_LENGTH as usize;
+// The kernel expects the frame of a TX slot right after the aligned tpacket3_hdr
+const FRAME_OFFSET: usize = libc::TPACKET3_HDRLEN - std::mem::size_of::<libc::sockaddr_ll>();
+const IP_FLAG_DONT_FRAGMENT: u16 = 0x4000;
+pub(super) const IP_FRAGMENT_MASK: u32 = 0x3fff;
+const IP_DEFAULT_TTL: u8 = 64;
+
+// AF_PACKET socket with a memory mapped TPACKET_V3 ring (PACKET_MMAP), which is either used for sending or receiving.
+// The TX ring consists of fixed size slots, which are filled with prebuilt frames, so only the packet IDs are written before sending them.
+// The kernel packs the received frames of the RX ring into blocks, which are handed over to the receiver, once they are full or their timeout expired.
+pub struct PacketRing {
+    socket: i32,
+    ring: *mut u8,
+    block_size: usize,
+    amount_blocks: usize,
+    frame_size: usize,
+    amount_frames: usize,
+    // Next slot of the TX ring or next block of the RX ring
+    index: usize,
+    // TX ring: Interface and link layer address the frames are sent to
+    address: libc::sockaddr_ll,
+    frame_len: usize,
+}
+
+impl PacketRing {
+    // The frames carry the given datagram from the source to the destination address, so the source port should belong to a bound UDP socket
+    pub fn new_tx(interface: &str, source: SocketAddrV4, destination: SocketAddrV4, datagram: &[u8]) -> Result<PacketRing, &'static str> {
+        let interface_index = get_interface_index(interface)?;
+        let source_mac = get_interface_mac(interface)?;
+        // The loopback interface doesn't use link layer addresses
+        let destination_mac = if destination.ip().is_loopback() { [0; 6] } else { get_neighbour_mac(destination.ip(), interface)? };
+        let frame = build_udp_frame(source_mac, destination_mac, source, destination, datagram);
+
+        let mut packet_ring = Self::open(libc::PACKET_TX_RING, frame.len(), 0)?;
+        packet_ring.frame_len = frame.len();
+        packet_ring.address.sll_protocol = (libc::ETH_P_IP as u16).to_be();
+        packet_ring.address.sll_ifindex = interface_index;
+        packet_ring.address.sll_halen = libc::ETH_ALEN as u8;
+        packet_ring.address.sll_addr[..6].copy_from_slice(&destination_mac);
+
+        for index in 0..packet_ring.amount_frames {
+            let slot = packet_ring.slot(index);
+            unsafe {
+                ptr::copy_nonoverlapping(frame.as_ptr(), slot.add(FRAME_OFFSET), frame.len());
+                (*(slot as *mut libc::tpacket3_hdr)).tp_len = frame.len() as u32;
+            }
+        }
+
+        info!("Created TX ring with {} frames of {} bytes on interface {} from {} to {}", packet_ring.amount_frames, frame.len(), interface, source, destination);
+        Ok(packet_ring)
+    }
+
+    // Receives the IPv4/UDP frames to the given port on the interface, or on all interfaces
+    pub fn new_rx(interface: Option<&str>, port: u16, datagram_size: usize) -> Result<PacketRing, &'static str> {
+        let interface_index = interface.map(get_interface_index).transpose()?.unwrap_or(0);
+
+        // The socket is bound to the protocol after the filter is attached, so no other frames end up in the ring
+        let packet_ring = Self::open(libc::PACKET_RX_RING, LEN_FRAME_HEADERS + datagram_size, crate::PACKET_RING_BLOCK_TIMEOUT)?;
+        attach_port_filter(packet_ring.socket, port)?;
+
+        let mut address: libc::sockaddr_ll = unsafe { MaybeUninit::zeroed().assume_init() };
+        address.sll_family = libc::AF_PACKET as u16;
+        address.sll_protocol = (libc::ETH_P_IP as u16).to_be();
+        address.sll_ifindex = interface_index;
+        if unsafe { libc::bind(packet_ring.socket, &address as *const _ as *const libc::sockaddr, std::mem::size_of_val(&address) as libc::socklen_t) } == -1 {
+            error!("Error binding packet socket to interface {:?}: {}", interface, Error::last_os_error());
+            return Err("Failed to bind packet socket");
+        }
+
+        info!("Created RX ring with {} blocks of {} bytes on interface {} for port {}", packet_ring.amount_blocks, packet_ring.block_size, interface.unwrap_or("any"), port);
+        Ok(packet_ring)
+    }
+
+    // Protocol 0 doesn't register the socket for any received frames, until it is bound to a protocol
+    fn open(ring_type: libc::c_int, frame_len: usize, block_timeout: u32) -> Result<PacketRing, &'static str> {
+        let socket = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, 0) };
+        if socket == -1 {
+            error!("Error creating packet socket: {}", Error::last_os_error());
+            return Err("Failed to create packet socket, which needs CAP_NET_RAW");
+        }
+
+        // The packet ring closes the socket, if the setup fails from here on
+        let mut packet_ring = PacketRing {
+            socket,
+            ring: ptr::null_mut(),
+            block_size: 0,
+            amount_blocks: crate::PACKET_RING_AMOUNT_BLOCKS as usize,
+            frame_size: 0,
+            amount_frames: 0,
+            index: 0,
+            address: unsafe { MaybeUninit::zeroed().assume_init() },
+            frame_len: 0,
+        };
+        packet_ring.address.sll_family = libc::AF_PACKET as u16;
+
+        let version = libc::tpacket_versions::TPACKET_V3 as libc::c_int;
+        if unsafe { libc::setsockopt(socket, libc::SOL_PACKET, libc::PACKET_VERSION, &version as *const _ as _, std::mem::size_of_val(&version) as libc::socklen_t) } == -1 {
+            error!("Error setting TPACKET_V3 on packet socket: {}", Error::last_os_error());
+            return Err("Failed to set packet ring version");
+        }
+
+        // Blocks are a multiple of the page size and hold at least one frame
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        packet_ring.frame_size = (FRAME_OFFSET + frame_len).next_multiple_of(libc::TPACKET_ALIGNMENT);
+        packet_ring.block_size = (crate::PACKET_RING_BLOCK_SIZE as usize).max(packet_ring.frame_size.next_multiple_of(page_size));
+        packet_ring.amount_frames = packet_ring.block_size / packet_ring.frame_size * packet_ring.amount_blocks;
+
+        let request = libc::tpacket_req3 {
+            tp_block_size: packet_ring.block_size as u32,
+            tp_block_nr: packet_ring.amount_blocks as u32,
+            tp_frame_size: packet_ring.frame_size as u32,
+            tp_frame_nr: packet_ring.amount_frames as u32,
+            tp_retire_blk_tov: block_timeout,
+            tp_sizeof_priv: 0,
+            tp_feature_req_word: 0,
+        };
+        if unsafe { libc::setsockopt(socket, libc::SOL_PACKET, ring_type, &request as *const _ as _, std::mem::size_of_val(&request) as libc::socklen_t) } == -1 {
+            error!("Error setting up packet ring with {:?}: {}", request, Error::last_os_error());
+            return Err("Failed to set up packet ring");
+        }
+
+        let ring_size = packet_ring.block_size * packet_ring.amount_blocks;
+        let ring = unsafe { libc::mmap(ptr::null_mut(), ring_size, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED, socket, 0) };
+        if ring == libc::MAP_FAILED {
+            error!("Error mapping packet ring of {} bytes: {}", ring_size, Error::last_os_error());
+            return Err("Failed to map packet ring");
+        }
+        packet_ring.ring = ring as *mut u8;
+
+        debug!("Mapped packet ring of {} blocks with {} bytes and {} frames with {} bytes on socket {}", packet_ring.amount_blocks, packet_ring.block_size, packet_ring.amount_frames, packet_ring.frame_size, socket);
+        Ok(packet_ring)
+    }
+
+    // Slots don't span blocks, so the last bytes of a block may be unused
+    fn slot(&self, index: usize) -> *mut u8 {
+        let frames_per_block = self.block_size / self.frame_size;
+        unsafe { self.ring.add(index / frames_per_block * self.block_size + index % frames_per_block * self.frame_size) }
+    }
+
+    // Writes consecutive packet IDs into up to amount free slots and hands all filled slots to the kernel with one sendto() call.
+    // Returns the amount of filled slots and the amount of frames the kernel sent. Slots which aren't sent yet are sent with the next call.
+    pub fn send(&mut self, packet_id: u64, connection_id: u64, amount: usize) -> Result<(u64, u64), &'static str> {
+        let mut amount_filled_frames = 0;
+        while (amount_filled_frames as usize) < amount {
+            let slot = self.slot(self.index);
+            let header = slot as *mut libc::tpacket3_hdr;
+            if unsafe { ptr::read_volatile(&(*header).tp_status) } != libc::TP_STATUS_AVAILABLE {
+                break;
+            }
+
+            let datagram = unsafe { std::slice::from_raw_parts_mut(slot.add(FRAME_OFFSET + LEN_FRAME_HEADERS), self.frame_len - LEN_FRAME_HEADERS) };
+            MessageHeader::set_packet_id_raw(datagram, packet_id + amount_filled_frames, connection_id);
+            fence(Ordering::Release);
+            unsafe { ptr::write_volatile(&mut (*header).tp_status, libc::TP_STATUS_SEND_REQUEST) };
+
+            self.index = (self.index + 1) % self.amount_frames;
+            amount_filled_frames += 1;
+        }
+
+        let send_result = unsafe {
+            libc::sendto(
+                self.socket,
+                ptr::null(),
+                0,
+                libc::MSG_DONTWAIT,
+                &self.address as *const _ as *const libc::sockaddr,
+                std::mem::size_of_val(&self.address) as libc::socklen_t
+            )
+        };
+
+        if send_result == -1 {
+            let errno = Error::last_os_error();
+            return match errno.raw_os_error() {
+                Some(libc::EAGAIN) | Some(libc::ENOBUFS) if amount_filled_frames == 0 => Err("EAGAIN"),
+                Some(libc::EAGAIN) | Some(libc::ENOBUFS) => Ok((amount_filled_frames, 0)),
+                _ => {
+                    error!("Error sending frames of the TX ring: {}", errno);
+                    Err("Failed to send frames of the TX ring")
+                }
+            };
+        }
+
+        let amount_sent_frames = send_result as u64 / self.frame_len as u64;
+        trace!("Filled {} slots of the TX ring and sent {} frames", amount_filled_frames, amount_sent_frames);
+        if amount_filled_frames == 0 && amount_sent_frames == 0 {
+            // All slots are still in flight
+            return Err("EAGAIN");
+        }
+        Ok((amount_filled_frames, amount_sent_frames))
+    }
+}
+
+impl FrameRing for PacketRing {
+    // Passes the UDP payload of every frame in the next block to process and returns the block to the kernel.
+    // Returns the amount of frames in the block, or EAGAIN if the kernel didn't hand over the block yet.
+    fn recv(&mut self, process: &mut dyn FnMut(&[u8])) -> Result<u64, &'static str> {
+        let block = unsafe { self.ring.add(self.index * self.block_size) };
+        let block_header = unsafe { &mut (*(block as *mut libc::tpacket_block_desc)).hdr.bh1 };
+        if unsafe { ptr::read_volatile(&block_header.block_status) } & libc::TP_STATUS_USER == 0 {
+            return Err("EAGAIN");
+        }
+        fence(Ordering::Acquire);
+
+        let amount_frames = block_header.num_pkts;
+        let mut offset = block_header.offset_to_first_pkt as usize;
+        for _ in 0..amount_frames {
+            let header = unsafe { &*(block.add(offset) as *const libc::tpacket3_hdr) };
+            let frame = unsafe { std::slice::from_raw_parts(block.add(offset + header.tp_mac as usize), header.tp_snaplen as usize) };
+            match parse_udp_frame(frame) {
+                Some(datagram) => process(datagram),
+                None => debug!("Dropping frame of {} bytes without an UDP datagram", frame.len())
+            }
+            offset += header.tp_next_offset as usize;
+        }
+
+        fence(Ordering::Release);
+        unsafe { ptr::write_volatile(&mut block_header.block_status, libc::TP_STATUS_KERNEL) };
+        self.index = (self.index + 1) % self.amount_blocks;
+
+        trace!("Received block with {} frames from the RX ring", amount_frames);
+        Ok(amount_frames as u64)
+    }
+
+    // Waits until a slot of the TX ring got free (POLLOUT) or a block of the RX ring is handed over (POLLIN)
+    fn wait(&self, events: libc::c_short, timeout: i32) -> Result<(), &'static str> {
+        let mut pollfd = libc::pollfd { fd: self.socket, events, revents: 0 };
+        let poll_result = unsafe { libc::poll(&mut pollfd, 1, timeout) };
+
+        if poll_result == -1 {
+            error!("Error occured executing poll() on packet ring: {}", Error::last_os_error());
+            Err("Error occured executing poll()")
+        } else if poll_result == 0 {
+            debug!("Poll on packet ring returned due to timeout");
+            Err("TIMEOUT")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for PacketRing {
+    fn drop(&mut self) {
+        if !self.ring.is_null() {
+            unsafe { libc::munmap(self.ring as *mut libc::c_void, self.block_size * self.amount_blocks) };
+        }
+        unsafe { libc::close(self.socket) };
+    }
+}
+
+pub(super) fn get_interface_index(interface: &str) -> Result<libc::c_int, &'static str> {
+    let name = CString::new(interface).map_err(|_| "Invalid interface name")?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => {
+            error!("Error getting index of interface {}: {}", interface, Error::last_os_error());
+            Err("Failed to get interface index")
+        },
+        index => Ok(index as libc::c_int)
+    }
+}
+
+fn get_interface_mac(interface: &str) -> Result<[u8; 6], &'static str> {
+    fs::read_to_string(format!("/sys/class/net/{}/address", interface)).ok().and_then(|address| parse_mac(address.trim())).ok_or("Failed to get MAC address of interface")
+}
+
+// The receiver has to be on the same link, so its MAC address is in the ARP table of the interface after the control channel connected to it
+fn get_neighbour_mac(ip: &Ipv4Addr, interface: &str) -> Result<[u8; 6], &'static str> {
+    let arp_table = fs::read_to_string("/proc/net/arp").map_err(|_| "Failed to read ARP table")?;
+    // IP address, HW type, Flags, HW address, Mask, Device
+    for entry in arp_table.lines().skip(1) {
+        let fields: Vec<&str> = entry.split_whitespace().collect();
+        if fields.len() == 6 && fields[0] == ip.to_string() && fields[5] == interface && fields[2] != "0x0" {
+            return parse_mac(fields[3]).ok_or("Invalid MAC address in ARP table");
+        }
+    }
+
+    error!("No ARP entry for {} on interface {}! Packet rings only reach receivers on the same link", ip, interface);
+    Err("Failed to get MAC address of receiver")
+}
+
+fn parse_mac(address: &str) -> Option<[u8; 6]> {
+    let bytes: Vec<u8> = address.split(':').map(|byte| u8::from_str_radix(byte, 16)).collect::<Result<_, _>>().ok()?;
+    bytes.try_into().ok()
+}
+
+// Ethernet, IPv4 and UDP header in front of the datagram. The UDP checksum is optional for IPv4 and left out.
+fn build_udp_frame(source_mac: [u8; 6], destination_mac: [u8; 6], source: SocketAddrV4, destination: SocketAddrV4, datagram: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(LEN_FRAME_HEADERS + datagram.len());
+    frame.extend_from_slice(&destination_mac);
+    frame.extend_from_slice(&source_mac);
+    frame.extend_from_slice(&(libc::ETH_P_IP as u16).to_be_bytes());
+
+    let ip_header_start = frame.len();
+    frame.extend_from_slice(&[0x45, 0]); // Version 4, header length of 5 words, no TOS
+    frame.extend_from_slice(&((crate::IPV4_HEADER_LENGTH + crate::UDP_HEADER_LENGTH) as u16 + datagram.len() as u16).to_be_bytes());
+    frame.extend_from_slice(&[0, 0]); // ID isn't needed, since the datagram must not be fragmented
+    frame.extend_from_slice(&IP_FLAG_DONT_FRAGMENT.to_be_bytes());
+    frame.extend_from_slice(&[IP_DEFAULT_TTL, libc::IPPROTO_UDP as u8, 0, 0]);
+    frame.extend_from_slice(&source.ip().octets());
+    frame.extend_from_slice(&destination.ip().octets());
+    let checksum = ipv4_checksum(&frame[ip_header_start..]);
+    frame[ip_header_start + 10..ip_header_start + 12].copy_from_slice(&checksum.to_be_bytes());
+
+    frame.extend_from_slice(&source.port().to_be_bytes());
+    frame.extend_from_slice(&destination.port().to_be_bytes());
+    frame.extend_from_slice(&(crate::UDP_HEADER_LENGTH as u16 + datagram.len() as u16).to_be_bytes());
+    frame.extend_from_slice(&[0, 0]);
+    frame.extend_from_slice(datagram);
+    frame
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum = header.chunks(2).fold(0_u32, |sum, word| sum + u16::from_be_bytes([word[0], word[1]]) as u32);
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+// Returns the UDP payload of an Ethernet frame, which the socket filter or XDP program already checked to be an unfragmented IPv4/UDP frame
+pub(super) fn parse_udp_frame(frame: &[u8]) -> Option<&[u8]> {
+    let ip_header_length = (*frame.get(LEN_ETHERNET_HEADER)? & 0x0f) as usize * 4;
+    let udp_header_start = LEN_ETHERNET_HEADER + ip_header_length;
+    let udp_length = u16::from_be_bytes(frame.get(udp_header_start + 4..udp_header_start + 6)?.try_into().ok()?) as usize;
+    let datagram = frame.get(udp_header_start + crate::UDP_HEADER_LENGTH as usize..(udp_header_start + udp_length).min(frame.len()))?;
+    (!datagram.is_empty()).then_some(datagram)
+}
+
+// Classic BPF program, which only passes the incoming, unfragmented IPv4/UDP frames to the port of the receiver thread
+fn attach_port_filter(socket: i32, port: u16) -> Result<(), &'static str> {
+    let statement = |code: u32, jt: u8, jf: u8, k: u32| libc::sock_filter { code: code as u16, jt, jf, k };
+    let mut filter = [
+        // Frames sent by the host itself show up on packet sockets as well
+        statement(libc::BPF_LD | libc::BPF_B | libc::BPF_ABS, 0, 0, (libc::SKF_AD_OFF + libc::SKF_AD_PKTTYPE) as u32),
+        statement(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, 10, 0, libc::PACKET_OUTGOING as u32),
+        statement(libc::BPF_LD | libc::BPF_H | libc::BPF_ABS, 0, 0, 12),
+        statement(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, 0, 8, libc::ETH_P_IP as u32),
+        statement(libc::BPF_LD | libc::BPF_B | libc::BPF_ABS, 0, 0, 23),
+        statement(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, 0, 6, libc::IPPROTO_UDP as u32),
+        statement(libc::BPF_LD | libc::BPF_H | libc::BPF_ABS, 0, 0, 20),
+        statement(libc::BPF_JMP | libc::BPF_JSET | libc::BPF_K, 4, 0, IP_FRAGMENT_MASK),
+        // Loads the IP header length into X, to find the destination port of the UDP header
+        statement(libc::BPF_LDX | libc::BPF_B | libc::BPF_MSH, 0, 0, LEN_ETHERNET_HEADER as u32),
+        statement(libc::BPF_LD | libc::BPF_H | libc::BPF_IND, 0, 0, LEN_ETHERNET_HEADER as u32 + 2),
+        statement(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, 0, 1, port as u32),
+        statement(libc::BPF_RET | libc::BPF_K, 0, 0, u32::MAX),
+        statement(libc::BPF_RET | libc::BPF_K, 0, 0, 0),
+    ];
+
+    let program = libc::sock_fprog {
+        len: filter.len() as u16,
+        filter: filter.as_mut_ptr(),
+    };
+
+    if unsafe { libc::setsockopt(socket, libc::SOL_SOCKET, libc::SO_ATTACH_FILTER, &program as *const _ as _, std::mem::size_of_val(&program) as libc::socklen_t) } == -1 {
+        error!("Error attaching port filter to packet socket: {}", Error::last_os_error());
+        return Err("Failed to attach filter to packet socket");
+    }
+    Ok(())
+}
@@ -1,33 +1,61 @@
 
 use log::{debug, error, info, trace, warn};
-use std::{self, io::Error, mem::MaybeUninit, net::SocketAddrV4};
+use std::{self, io::Error, mem::MaybeUninit, net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6}};
 
 use super::socket_options::{self, SocketOptions};
+use crate::util::Transport;
+use super::{MessageHeader, MessageType, LEN_HEADER};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Socket {
-    sock_addr_in: Option<SocketAddrV4>,
-    sock_addr_out: Option<SocketAddrV4>,
+    sock_addr_in: Option<SocketAddr>,
+    sock_addr_out: Option<SocketAddr>,
     socket: i32,
+    ipv6: bool,
+    transport: Transport,
     //sendmmsg_econnrefused_counter: u16
 } 
 
 impl Socket {
-    pub fn new(mut socket_options: SocketOptions) -> Option<Socket> {
-        let socket = Self::create_socket()?; 
+    // The address family of the socket is taken from the IP address it is going to be used with, unless the unix transport is used
+    pub fn new(ip: IpAddr, transport: Transport, mut socket_options: SocketOptions, interface: Option<&str>) -> Option<Socket> {
+        let ipv6 = ip.is_ipv6();
+        let unix = transport == Transport::Unix;
+        let socket = Self::create_socket(ipv6, transport)?; 
+
+        if unix {
+            socket_options.set_unix_socket_options(socket).expect("Error updating socket options! Check your system configuration!");
+        } else {
+            socket_options.set_socket_options(socket, ipv6, interface).expect("Error updating socket options! Check your system configuration!");
+        }
 
-        socket_options.set_socket_options(socket).expect("Error updating socket options! Check your system configuration!");
+        // Lets the receiver listen on the port again, while connections of the last test are still in TIME_WAIT
+        if transport == Transport::Tcp {
+            socket_options::set_reuseaddr(socket).expect("Error updating socket options! Check your system configuration!");
+        }
+
+        // Unix domain sockets aren't bound to a network interface
+        if let Some(interface) = interface.filter(|_| !unix) {
+            if socket_options::set_bind_to_device(socket, interface).is_err() {
+                unsafe { libc::close(socket) };
+                return None;
+            }
+        }
 
         Some(Socket {
             sock_addr_in: None,
             sock_addr_out: None,
             socket,
+            ipv6,
+            transport,
             //sendmmsg_econnrefused_counter: 0
         })
     }
 
-    fn create_socket() -> Option<i32> {
-        let socket = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    fn create_socket(ipv6: bool, transport: Transport) -> Option<i32> {
+        let domain = if transport == Transport::Unix { libc::AF_UNIX } else if ipv6 { libc::AF_INET6 } else { libc::AF_INET };
+        let socket_type = if transport == Transport::Tcp { libc::SOCK_STREAM } else { libc::SOCK_DGRAM };
+        let socket = unsafe { libc::socket(domain, socket_type, 0) };
         if socket == -1 {
             error!("Failed to create socket");
             return None;
@@ -38,36 +66,89 @@ impl Socket {
     }
 
 
-    pub fn connect(&mut self, sock_address: SocketAddrV4) -> Result<(), &'static str> {
+    pub fn connect(&mut self, sock_address: SocketAddr) -> Result<(), &'static str> {
         self.sock_addr_out = Some(sock_address);
-        let sockaddr = Self::create_sockaddr(&self.sock_addr_out.expect("Outgoing socket address not set!"));
+        let (sockaddr, sockaddr_len) = self.sockaddr_for(&self.sock_addr_out.expect("Outgoing socket address not set!"));
  
         let connect_result = unsafe {
             libc::connect(
                 self.socket,
                 &sockaddr as *const _ as _,
-                std::mem::size_of_val(&sockaddr) as libc::socklen_t
+                sockaddr_len
             )
         };
         debug!("'Connected' to remote host with result: {:?}", connect_result);
     
         if connect_result == -1 {
+            // A nonblocking TCP socket finishes the handshake in the background
+            if Error::last_os_error().raw_os_error() == Some(libc::EINPROGRESS) {
+                return self.wait_for_connection();
+            }
+            error!("Errno when connecting to remote host: {}", Error::last_os_error());
             return Err("Failed to connect to remote host");
         }
     
         Ok(())
     }
 
-    pub fn bind(&mut self, sock_address: SocketAddrV4) -> Result<(), &'static str> {
+    fn wait_for_connection(&self) -> Result<(), &'static str> {
+        let mut pollfd = self.create_pollfd(libc::POLLOUT);
+        match self.poll(&mut pollfd, crate::TCP_CONNECT_TIMEOUT) {
+            Ok(_) => {},
+            Err("TIMEOUT") => return Err("Timeout connecting to remote host"),
+            Err(x) => return Err(x)
+        }
+
+        match socket_options::get_socket_error(self.socket)? {
+            0 => Ok(()),
+            errno => {
+                error!("Errno when connecting to remote host: {}", Error::from_raw_os_error(errno as i32));
+                Err("Failed to connect to remote host")
+            }
+        }
+    }
+
+    pub fn listen(&self) -> Result<(), &'static str> {
+        let listen_result = unsafe { libc::listen(self.socket, crate::TCP_LISTEN_BACKLOG) };
+
+        if listen_result == -1 {
+            error!("Errno when listening on socket: {}", Error::last_os_error());
+            return Err("Failed to listen on socket");
+        }
+
+        Ok(())
+    }
+
+    // The accepted socket doesn't inherit the nonblocking mode of the listening socket, so it is set with accept4()
+    pub fn accept(&self, nonblocking: bool) -> Result<Socket, &'static str> {
+        let flags = if nonblocking { libc::SOCK_NONBLOCK } else { 0 };
+        let socket = unsafe { libc::accept4(self.socket, std::ptr::null_mut(), std::ptr::null_mut(), flags) };
+
+        if socket == -1 {
+            error!("Errno when accepting connection: {}", Error::last_os_error());
+            return Err("Failed to accept connection");
+        }
+
+        info!("Accepted connection on socket {} with socket {}", self.socket, socket);
+        Ok(Socket {
+            sock_addr_in: self.sock_addr_in,
+            sock_addr_out: None,
+            socket,
+            ipv6: self.ipv6,
+            transport: self.transport,
+        })
+    }
+
+    pub fn bind(&mut self, sock_address: SocketAddr) -> Result<(), &'static str> {
         self.sock_addr_in = Some(sock_address);
-        let sockaddr = Self::create_sockaddr(&self.sock_addr_in.expect("Outgoing socket address not set!"));
-        debug!("Binding socket to {}:{}", sock_address, sockaddr.sin_port);
+        let (sockaddr, sockaddr_len) = self.sockaddr_for(&self.sock_addr_in.expect("Outgoing socket address not set!"));
+        debug!("Binding socket to {}", sock_address);
     
         let bind_result = unsafe {
             libc::bind(
                 self.socket,
                 &sockaddr as *const _ as _,
-                std::mem::size_of_val(&sockaddr) as libc::socklen_t
+                sockaddr_len
             )
         };
     
@@ -298,40 +379,161 @@ impl Socket {
         Ok(recv_result as usize)
     }
 
+    // In reverse mode, the measuring side sends these datagrams to open the path through NAT and firewalls for the transmitting side
+    pub fn send_hole_punch(&self, test_id: u64) -> Result<(), &'static str> {
+        let header = MessageHeader::new(MessageType::HOLEPUNCH, test_id, 0, 0).serialize();
+        for _ in 0..crate::AMOUNT_HOLE_PUNCH_DATAGRAMS {
+            self.send(&header, header.len())?;
+        }
+        Ok(())
+    }
+
+    // Waits for the first hole punch datagram and connects the socket to the address it was sent from
+    pub fn accept_hole_punch(&mut self, timeout: i32) -> Result<SocketAddr, &'static str> {
+        let mut pollfd = self.create_pollfd(libc::POLLIN);
+        self.poll(&mut pollfd, timeout)?;
+
+        let mut buffer = [0_u8; LEN_HEADER];
+        let mut sockaddr: libc::sockaddr_storage = unsafe { MaybeUninit::zeroed().assume_init() };
+        let mut sockaddr_len = std::mem::size_of_val(&sockaddr) as libc::socklen_t;
+
+        let recv_result = unsafe {
+            libc::recvfrom(
+                self.socket,
+                buffer.as_mut_ptr() as *mut _,
+                buffer.len(),
+                0,
+                &mut sockaddr as *mut _ as *mut libc::sockaddr,
+                &mut sockaddr_len
+            )
+        };
+
+        if recv_result == -1 {
+            error!("Errno when receiving hole punch datagram: {}", Error::last_os_error());
+            return Err("Failed to receive hole punch datagram");
+        }
+
+        match MessageHeader::decode(&buffer[..recv_result as usize]) {
+            Ok(header) if header.mtype == MessageType::HOLEPUNCH => {},
+            _ => return Err("Received datagram is not a hole punch")
+        }
+
+        let remote_address = Self::parse_sockaddr(&sockaddr).ok_or("Hole punch datagram has an unknown address family")?;
+        info!("Received hole punch from {}", remote_address);
+        self.connect(remote_address)?;
+        Ok(remote_address)
+    }
+
     pub fn get_mss(&self) -> Result<u32, &'static str> {
-        socket_options::get_mss(self.socket)
+        match self.transport {
+            Transport::Udp => socket_options::get_mss(self.socket, self.ipv6),
+            Transport::Tcp => socket_options::get_tcp_mss(self.socket),
+            Transport::Unix => Err("Unix domain sockets have no MSS")
+        }
+    }
+
+    pub fn set_receive_timeout(&self, timeout: i32) -> Result<(), &'static str> {
+        socket_options::set_receive_timeout(self.socket, timeout)
     }
 
     pub fn get_socket_id(&self) -> i32 {
         self.socket
     }
 
-    pub fn set_sock_addr_out(&mut self, sock_address: SocketAddrV4) {
-        if self.sock_addr_out.is_some() {
-            warn!("Overwriting existing socket address {} with {} on socket {}!", self.sock_addr_out.unwrap(), sock_address, self.socket);
+    pub fn set_sock_addr_out(&mut self, sock_address: SocketAddr) {
+        if let Some(sock_addr_out) = self.sock_addr_out.filter(|sock_addr_out| *sock_addr_out != sock_address) {
+            warn!("Overwriting existing socket address {} with {} on socket {}!", sock_addr_out, sock_address, self.socket);
         }
 
         self.sock_addr_out = Some(sock_address);
     }
 
-    fn create_sockaddr(sock_address: &SocketAddrV4) -> libc::sockaddr_in {
-        // Convert Ipv4Addr to libc::in_addr
-        let addr = sock_address.ip(); 
-        let addr_u32 = u32::from_le_bytes(addr.octets());
- 
-        #[cfg(target_os = "linux")]
-        libc::sockaddr_in {
-            sin_family: libc::AF_INET as u16,
-            sin_port: sock_address.port().to_be(), // Convert to big endian
-            sin_addr: libc::in_addr { s_addr: addr_u32 },
-            sin_zero: [0; 8]
+    // Converts the socket address into sockaddr_in or sockaddr_in6, stored in a sockaddr_storage which fits both. Returns the length of the used address type.
+    pub fn create_sockaddr(sock_address: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+        let mut sockaddr: libc::sockaddr_storage = unsafe { MaybeUninit::zeroed().assume_init() };
+
+        let sockaddr_len = match sock_address {
+            SocketAddr::V4(address) => {
+                let sockaddr_in = unsafe { &mut *(&mut sockaddr as *mut _ as *mut libc::sockaddr_in) };
+                sockaddr_in.sin_family = libc::AF_INET as libc::sa_family_t;
+                sockaddr_in.sin_port = address.port().to_be(); // Convert to big endian
+                sockaddr_in.sin_addr = libc::in_addr { s_addr: u32::from_le_bytes(address.ip().octets()) };
+                std::mem::size_of::<libc::sockaddr_in>()
+            },
+            SocketAddr::V6(address) => {
+                let sockaddr_in6 = unsafe { &mut *(&mut sockaddr as *mut _ as *mut libc::sockaddr_in6) };
+                sockaddr_in6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                sockaddr_in6.sin6_port = address.port().to_be();
+                sockaddr_in6.sin6_flowinfo = address.flowinfo();
+                sockaddr_in6.sin6_addr = libc::in6_addr { s6_addr: address.ip().octets() };
+                sockaddr_in6.sin6_scope_id = address.scope_id();
+                std::mem::size_of::<libc::sockaddr_in6>()
+            }
+        };
+
+        (sockaddr, sockaddr_len as libc::socklen_t)
+    }
+
+    // Unix domain sockets use an abstract address named after the port, e.g. "nperf-45001", so no socket files have to be cleaned up.
+    // Port 0 lets the kernel assign a unique abstract address (autobind), similar to an ephemeral UDP port.
+    fn create_unix_sockaddr(port: u16) -> (libc::sockaddr_storage, libc::socklen_t) {
+        let mut sockaddr: libc::sockaddr_storage = unsafe { MaybeUninit::zeroed().assume_init() };
+        let sockaddr_un = unsafe { &mut *(&mut sockaddr as *mut _ as *mut libc::sockaddr_un) };
+        sockaddr_un.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+        let family_len = std::mem::size_of::<libc::sa_family_t>();
+        if port == 0 {
+            return (sockaddr, family_len as libc::socklen_t);
+        }
+
+        // The leading null byte of sun_path marks the address as abstract
+        let name = format!("nperf-{}", port);
+        for (index, byte) in name.bytes().enumerate() {
+            sockaddr_un.sun_path[index + 1] = byte as libc::c_char;
+        }
+
+        (sockaddr, (family_len + 1 + name.len()) as libc::socklen_t)
+    }
+
+    fn sockaddr_for(&self, sock_address: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+        if self.transport == Transport::Unix {
+            Self::create_unix_sockaddr(sock_address.port())
+        } else {
+            Self::create_sockaddr(sock_address)
         }
     }
 
+    fn parse_sockaddr(sockaddr: &libc::sockaddr_storage) -> Option<SocketAddr> {
+        match sockaddr.ss_family as libc::c_int {
+            libc::AF_INET => {
+                let sockaddr_in = unsafe { &*(sockaddr as *const _ as *const libc::sockaddr_in) };
+                Some(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(sockaddr_in.sin_addr.s_addr.to_le_bytes()), u16::from_be(sockaddr_in.sin_port))))
+            },
+            libc::AF_INET6 => {
+                let sockaddr_in6 = unsafe { &*(sockaddr as *const _ as *const libc::sockaddr_in6) };
+                Some(SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::from(sockaddr_in6.sin6_addr.s6_addr), u16::from_be(sockaddr_in6.sin6_port), sockaddr_in6.sin6_flowinfo, sockaddr_in6.sin6_scope_id)))
+            },
+            _ => None
+        }
+    }
+
+    // Address the socket is bound to, e.g. the source address and port the kernel picked on connect()
+    pub fn get_local_address(&self) -> Result<SocketAddr, &'static str> {
+        let mut sockaddr: libc::sockaddr_storage = unsafe { MaybeUninit::zeroed().assume_init() };
+        let mut sockaddr_len = std::mem::size_of_val(&sockaddr) as libc::socklen_t;
+
+        if unsafe { libc::getsockname(self.socket, &mut sockaddr as *mut _ as *mut libc::sockaddr, &mut sockaddr_len) } == -1 {
+            error!("Errno when getting the local address of socket {}: {}", self.socket, Error::last_os_error());
+            return Err("Failed to get local address of socket");
+        }
+
+        Self::parse_sockaddr(&sockaddr).ok_or("Socket has an unknown address family")
+    }
+
     #[allow(clippy::manual_map)]
-    pub fn get_sockaddr_out(&self) -> Option<libc::sockaddr_in> {
+    pub fn get_sockaddr_out(&self) -> Option<(libc::sockaddr_storage, libc::socklen_t)> {
         if let Some(sock_addr) = &self.sock_addr_out {
-            Some(Self::create_sockaddr(sock_addr))
+            Some(self.sockaddr_for(sock_addr))
         } else {
             None
         }
@@ -367,7 +569,7 @@ impl Socket {
             Err("Error occured executing poll()")
         } else if poll_result == 0 {
             // Poll returned due to timeout
-            warn!("Poll returned due to timeout");
+            debug!("Poll returned due to timeout");
             Err("TIMEOUT")
         } else {
             trace!("Poll returned with result: {}", poll_result);
@@ -1,12 +1,12 @@
 use log::{error, info, debug};
-use serde::Serialize;
-use std::{fmt::Display, io::Error};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, io::Error, net::IpAddr};
 use crate::util::statistic::serialize_option_as_bool;
 
 
-#[derive(PartialEq, Debug, Clone, Copy, Serialize)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SocketOptions {
-    nonblocking: bool,
+    pub nonblocking: bool,
     ip_fragmentation: bool,
     reuseport: bool,
     #[serde(with = "serialize_option_as_bool")]
@@ -17,12 +17,15 @@ pub struct SocketOptions {
     recv_buffer_size: Option<u32>,
     #[serde(with = "serialize_option_as_bool")]
     send_buffer_size: Option<u32>,
-
+    #[serde(with = "serialize_option_as_bool")]
+    multicast_ttl: Option<u32>,
+    multicast_loop: bool,
+    pub multicast_group: Option<IpAddr>,
 }
 
 impl SocketOptions {
     #[allow(clippy::too_many_arguments)]
-    pub fn new(nonblocking: bool, ip_fragmentation: bool, reuseport: bool, gso: Option<u32>, gro: bool, socket_pacing_rate: u64, recv_buffer_size: Option<u32>, send_buffer_size: Option<u32>) -> Self {
+    pub fn new(nonblocking: bool, ip_fragmentation: bool, reuseport: bool, gso: Option<u32>, gro: bool, socket_pacing_rate: u64, recv_buffer_size: Option<u32>, send_buffer_size: Option<u32>, multicast_ttl: Option<u32>, multicast_loop: bool) -> Self {
         SocketOptions {
             nonblocking,
             ip_fragmentation,
@@ -32,10 +35,30 @@ impl SocketOptions {
             socket_pacing_rate,
             recv_buffer_size,
             send_buffer_size,
+            multicast_ttl,
+            multicast_loop,
+            multicast_group: None,
+        }
+    }
+
+    // Derives the socket options of the receiver from the ones of the sender. GRO mirrors GSO, while pacing, TTL and loopback only apply to sending.
+    // If the sender targets a multicast group, the receiver joins it.
+    pub fn for_receiver(&self, reuseport: bool, multicast_group: Option<IpAddr>) -> SocketOptions {
+        SocketOptions {
+            // Lets several receivers on the same host join the group on the same port
+            reuseport: reuseport || multicast_group.is_some(),
+            gso: None,
+            gro: self.gso.is_some(),
+            socket_pacing_rate: 0,
+            multicast_ttl: None,
+            multicast_loop: false,
+            multicast_group,
+            ..*self
         }
     }
 
-    pub fn set_socket_options(&mut self, socket: i32) -> Result<(), &'static str> {
+    // The interface is used to join a multicast group on a specific network interface
+    pub fn set_socket_options(&mut self, socket: i32, ipv6: bool, interface: Option<&str>) -> Result<(), &'static str> {
         debug!("Updating socket options with {:?}", self);
         set_reuseport(socket, self.reuseport)?;
 
@@ -43,7 +66,7 @@ impl SocketOptions {
             set_nonblocking(socket)?;
         } 
         if !self.ip_fragmentation {
-            set_ip_fragmentation_off(socket)?;
+            set_ip_fragmentation_off(socket, ipv6)?;
         } 
         if let Some(size) = self.gso {
             set_gso(socket, size)?;
@@ -53,8 +76,34 @@ impl SocketOptions {
             set_socket_pacing(socket, self.socket_pacing_rate)?;
         }
 
-        set_gro(socket, self.gro)?;
+        // UDP_GRO can't be set on TCP sockets, and it's disabled by default anyway
+        if self.gro {
+            set_gro(socket, self.gro)?;
+        }
+
+        if let Some(ttl) = self.multicast_ttl {
+            set_multicast_ttl(socket, ttl, ipv6)?;
+            set_multicast_loop(socket, self.multicast_loop, ipv6)?;
+        }
+
+        if let Some(group) = self.multicast_group {
+            join_multicast_group(socket, group, interface)?;
+        }
+
+        self.set_buffer_sizes(socket)
+    }
+
+    // Unix domain sockets only support the options on socket level
+    pub fn set_unix_socket_options(&mut self, socket: i32) -> Result<(), &'static str> {
+        debug!("Updating unix socket options with {:?}", self);
+        if self.nonblocking {
+            set_nonblocking(socket)?;
+        }
 
+        self.set_buffer_sizes(socket)
+    }
+
+    fn set_buffer_sizes(&mut self, socket: i32) -> Result<(), &'static str> {
         if let Some(size) = self.send_buffer_size { 
             set_buffer_size(socket, size, libc::SO_SNDBUF)?;
         } else {
@@ -154,7 +203,7 @@ pub fn set_buffer_size(socket: i32, size: u32, buffer_type: libc::c_int) -> Resu
 }
 
 fn set_gso(socket: i32, gso_size: u32) -> Result<(), &'static str> {
-    // gso_size should be equal to MSS = ETH_MSS - header(ipv4) - header(udp)
+    // gso_size should be equal to MSS = ETH_MSS - header(ipv4/ipv6) - header(udp)
     info!("Set socket option GSO to {}", gso_size);
     set_socket_option(socket, libc::SOL_UDP, libc::UDP_SEGMENT, gso_size)
 }
@@ -165,20 +214,60 @@ fn set_gro(socket: i32, status: bool) -> Result<(), &'static str> {
     set_socket_option(socket, libc::SOL_UDP, libc::UDP_GRO, value)
 }
 
-fn set_ip_fragmentation_off(socket: i32) -> Result<(), &'static str> {
+fn set_ip_fragmentation_off(socket: i32, ipv6: bool) -> Result<(), &'static str> {
     info!("Set socket to no IP fragmentation");
-    set_socket_option(socket, libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_DO)
+    if ipv6 {
+        set_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_MTU_DISCOVER, libc::IPV6_PMTUDISC_DO)
+    } else {
+        set_socket_option(socket, libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_DO)
+    }
 }
 
-pub fn get_mss(socket: i32) -> Result<u32, &'static str> {
-    // https://man7.org/linux/man-pages/man7/ip.7.html
+pub fn get_mss(socket: i32, ipv6: bool) -> Result<u32, &'static str> {
+    // https://man7.org/linux/man-pages/man7/ip.7.html and https://man7.org/linux/man-pages/man7/ipv6.7.html
     // MSS from TCP returned an error
-    match get_socket_option(socket, libc::IPPROTO_IP, libc::IP_MTU) {
-        Ok(mtu) => Ok(mtu - 20 - 8), // Return MSS instead of MTU
+    let (mtu, header_length) = if ipv6 {
+        (get_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_MTU), crate::IPV6_HEADER_LENGTH)
+    } else {
+        (get_socket_option(socket, libc::IPPROTO_IP, libc::IP_MTU), crate::IPV4_HEADER_LENGTH)
+    };
+
+    match mtu {
+        Ok(mtu) => Ok(mtu - header_length - crate::UDP_HEADER_LENGTH), // Return MSS instead of MTU
         Err(_) => Err("Failed to get MSS")
     }
 }
 
+pub fn get_tcp_mss(socket: i32) -> Result<u32, &'static str> {
+    get_socket_option(socket, libc::IPPROTO_TCP, libc::TCP_MAXSEG).map_err(|_| "Failed to get MSS")
+}
+
+pub fn set_receive_timeout(socket: i32, timeout: i32) -> Result<(), &'static str> {
+    // timeout is given in milliseconds
+    let timeval = libc::timeval {
+        tv_sec: timeout as i64 / 1000,
+        tv_usec: (timeout as i64 % 1000) * 1000,
+    };
+
+    let setsockopt_result = unsafe {
+        libc::setsockopt(
+            socket,
+            libc::SOL_SOCKET,
+            libc::SO_RCVTIMEO,
+            &timeval as *const _ as _,
+            std::mem::size_of::<libc::timeval>() as libc::socklen_t
+        )
+    };
+
+    if setsockopt_result == -1 {
+        error!("errno when setting receive timeout on socket: {}", Error::last_os_error());
+        return Err("Failed to set receive timeout");
+    }
+
+    info!("Set socket option receive timeout to {}ms", timeout);
+    Ok(())
+}
+
 pub fn set_socket_pacing(socket: i32, pacing_rate: u64) -> Result<(), &'static str> {
     info!("Set socket option pacing to for current socket to {}B/s", pacing_rate);
     set_socket_option(socket, libc::SOL_SOCKET, libc::SO_MAX_PACING_RATE, pacing_rate)
@@ -188,6 +277,96 @@ pub fn _get_gso_size(socket: i32) -> Result<u32, &'static str> {
     get_socket_option(socket, libc::SOL_UDP, libc::UDP_SEGMENT)
 }
 
+fn set_multicast_ttl(socket: i32, ttl: u32, ipv6: bool) -> Result<(), &'static str> {
+    info!("Set socket option multicast TTL to {}", ttl);
+    if ipv6 {
+        set_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_HOPS, ttl)
+    } else {
+        set_socket_option(socket, libc::IPPROTO_IP, libc::IP_MULTICAST_TTL, ttl)
+    }
+}
+
+fn set_multicast_loop(socket: i32, status: bool, ipv6: bool) -> Result<(), &'static str> {
+    let value: u32 = if status { 1 } else { 0 };
+    info!("Set socket option multicast loopback to {}", status);
+    if ipv6 {
+        set_socket_option(socket, libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_LOOP, value)
+    } else {
+        set_socket_option(socket, libc::IPPROTO_IP, libc::IP_MULTICAST_LOOP, value)
+    }
+}
+
+// Joins the multicast group on the given interface, or on the interface chosen by the kernel if none is given
+fn join_multicast_group(socket: i32, group: IpAddr, interface: Option<&str>) -> Result<(), &'static str> {
+    let interface_index = match interface {
+        Some(interface) => {
+            let name = std::ffi::CString::new(interface).map_err(|_| "Invalid interface name")?;
+            match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+                0 => return Err("Unknown interface to join multicast group on"),
+                index => index
+            }
+        },
+        None => 0
+    };
+
+    let setsockopt_result = match group {
+        IpAddr::V4(group) => {
+            let mreq = libc::ip_mreqn {
+                imr_multiaddr: libc::in_addr { s_addr: u32::from_ne_bytes(group.octets()) },
+                imr_address: libc::in_addr { s_addr: libc::INADDR_ANY },
+                imr_ifindex: interface_index as libc::c_int,
+            };
+            unsafe { libc::setsockopt(socket, libc::IPPROTO_IP, libc::IP_ADD_MEMBERSHIP, &mreq as *const _ as _, std::mem::size_of_val(&mreq) as libc::socklen_t) }
+        },
+        IpAddr::V6(group) => {
+            let mreq = libc::ipv6_mreq {
+                ipv6mr_multiaddr: libc::in6_addr { s6_addr: group.octets() },
+                ipv6mr_interface: interface_index,
+            };
+            unsafe { libc::setsockopt(socket, libc::IPPROTO_IPV6, libc::IPV6_ADD_MEMBERSHIP, &mreq as *const _ as _, std::mem::size_of_val(&mreq) as libc::socklen_t) }
+        }
+    };
+
+    if setsockopt_result == -1 {
+        error!("errno when joining multicast group {}: {}", group, Error::last_os_error());
+        return Err("Failed to join multicast group");
+    }
+
+    info!("Joined multicast group {}", group);
+    Ok(())
+}
+
+// Forces all traffic of the socket over the given network interface, e.g. a specific NIC or VLAN. Needs CAP_NET_RAW.
+pub fn set_bind_to_device(socket: i32, interface: &str) -> Result<(), &'static str> {
+    let setsockopt_result = unsafe {
+        libc::setsockopt(
+            socket,
+            libc::SOL_SOCKET,
+            libc::SO_BINDTODEVICE,
+            interface.as_ptr() as *const _,
+            interface.len() as libc::socklen_t
+        )
+    };
+
+    if setsockopt_result == -1 {
+        error!("errno when binding socket to interface {}: {}", interface, Error::last_os_error());
+        return Err("Failed to bind socket to interface");
+    }
+
+    info!("Set socket option BINDTODEVICE to {}", interface);
+    Ok(())
+}
+
+pub fn set_reuseaddr(socket: i32) -> Result<(), &'static str> {
+    info!("Set socket option REUSEADDR to true");
+    set_socket_option(socket, libc::SOL_SOCKET, libc::SO_REUSEADDR, 1_u32)
+}
+
+// Returns the pending error of the socket, e.g. the result of a nonblocking connect
+pub fn get_socket_error(socket: i32) -> Result<u32, &'static str> {
+    get_socket_option(socket, libc::SOL_SOCKET, libc::SO_ERROR)
+}
+
 pub fn set_reuseport(socket: i32, status: bool) -> Result<(), &'static str> {
     let value: u32 = if status { 1 } else { 0 };
     info!("Set socket option REUSEPORT to {}", status);
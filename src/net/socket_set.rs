@@ -0,0 +1,128 @@
+use log::trace;
+
+use crate::util::{EpollTrigger, IOModel};
+use super::{epoll::Epoll, socket::Socket};
+
+// All sockets of a node thread, driven like the event loop of a QUIC server handling many connections per worker.
+// A socket counts as ready until it returns EAGAIN, so the node only waits with its IO model once all of its sockets are drained.
+pub struct SocketSet {
+    sockets: Vec<Socket>,
+    ready: Vec<bool>,
+    index: usize,
+    // POLLIN or POLLOUT, which have the same values as EPOLLIN and EPOLLOUT
+    events: libc::c_short,
+    pollfds: Vec<libc::pollfd>,
+    epoll: Option<Epoll>,
+    epoll_trigger: EpollTrigger,
+    epoll_exclusive: bool,
+}
+
+impl SocketSet {
+    pub fn new(sockets: Vec<Socket>, events: libc::c_short, epoll_trigger: EpollTrigger, epoll_exclusive: bool) -> SocketSet {
+        let pollfds = sockets.iter().flat_map(|socket| socket.create_pollfd(events)).collect();
+
+        SocketSet {
+            ready: vec![true; sockets.len()],
+            sockets,
+            index: 0,
+            events,
+            pollfds,
+            epoll: None,
+            epoll_trigger,
+            epoll_exclusive,
+        }
+    }
+
+    pub fn amount_sockets(&self) -> usize {
+        self.sockets.len()
+    }
+
+    // Index of the socket the node exchanges messages on
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn current(&self) -> Socket {
+        self.sockets[self.index]
+    }
+
+    pub fn get(&self, index: usize) -> Socket {
+        self.sockets[index]
+    }
+
+    // Returns the index of the next ready socket after the current one, or the current one if no other socket is ready
+    fn next_ready(&self) -> Option<usize> {
+        (1..=self.sockets.len()).map(|offset| (self.index + offset) % self.sockets.len()).find(|&index| self.ready[index])
+    }
+
+    // Every ready socket gets its turn after an exchange call
+    pub fn rotate(&mut self) {
+        self.index = self.next_ready().unwrap_or(self.index);
+    }
+
+    // io_uring waits on the sockets itself, so every socket gets its turn
+    pub fn set_ready(&mut self) {
+        self.ready.fill(true);
+    }
+
+    // The current socket returned EAGAIN. Returns false, if none of the sockets is ready anymore.
+    pub fn set_blocked(&mut self) -> bool {
+        self.ready[self.index] = false;
+        match self.next_ready() {
+            Some(index) => {
+                self.index = index;
+                true
+            },
+            None => false
+        }
+    }
+
+    // Waits until at least one of the sockets is ready and switches to it
+    pub fn wait(&mut self, io_model: IOModel, timeout: i32) -> Result<(), &'static str> {
+        match io_model {
+            IOModel::Select => {
+                let mut fd_set: libc::fd_set = unsafe { self.sockets[0].create_fdset() };
+                for socket in self.sockets.iter().skip(1) {
+                    unsafe { libc::FD_SET(socket.get_socket_id(), &mut fd_set) };
+                }
+
+                // select() has to check all fds up to the highest one
+                let highest_socket = self.sockets.iter().max_by_key(|socket| socket.get_socket_id()).unwrap();
+                if self.events == libc::POLLIN {
+                    highest_socket.select(Some(&mut fd_set), None, timeout)?;
+                } else {
+                    highest_socket.select(None, Some(&mut fd_set), timeout)?;
+                }
+
+                for (ready, socket) in self.ready.iter_mut().zip(self.sockets.iter()) {
+                    *ready = unsafe { libc::FD_ISSET(socket.get_socket_id(), &fd_set) };
+                }
+            },
+            IOModel::Poll => {
+                self.sockets[0].poll(&mut self.pollfds, timeout)?;
+                for (ready, pollfd) in self.ready.iter_mut().zip(self.pollfds.iter()) {
+                    *ready = pollfd.revents != 0;
+                }
+            },
+            IOModel::Epoll => {
+                if self.epoll.is_none() {
+                    let sockets: Vec<i32> = self.sockets.iter().map(|socket| socket.get_socket_id()).collect();
+                    self.epoll = Some(Epoll::new(&sockets, self.events as libc::c_int, self.epoll_trigger, self.epoll_exclusive)?);
+                }
+                for event in self.epoll.as_mut().unwrap().wait(timeout)? {
+                    self.ready[event.u64 as usize] = true;
+                }
+            },
+            // Busy waiting tries all sockets again
+            _ => self.ready.fill(true)
+        }
+
+        self.index = self.next_ready().unwrap_or(self.index);
+        trace!("Socket {} of {} is ready", self.index, self.sockets.len());
+        Ok(())
+    }
+
+    pub fn close(&self) -> Result<(), &'static str> {
+        self.sockets.iter().try_for_each(|socket| socket.close())
+    }
+}
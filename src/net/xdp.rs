@@ -0,0 +1,446 @@
+use std::{fs, io::Error, mem::MaybeUninit, net::IpAddr, os::fd::RawFd, ptr, sync::atomic::{AtomicU32, Ordering}};
+use log::{debug, error, info, trace, warn};
+
+use super::{packet_ring, FrameRing};
+
+const LEN_ETHERNET_HEADER: i16 = 14;
+// eBPF opcodes, which don't exist in classic BPF
+const BPF_ALU64: u32 = 0x07;
+const BPF_DW: u32 = 0x18;
+const BPF_MOV: u32 = 0xb0;
+const BPF_END: u32 = 0xd0;
+const BPF_TO_BE: u32 = 0x08;
+const BPF_JNE: u32 = 0x50;
+const BPF_JLT: u32 = 0xa0;
+const BPF_CALL: u32 = 0x80;
+const BPF_EXIT: u32 = 0x90;
+const BPF_MAP_CREATE: libc::c_int = 0;
+const BPF_MAP_UPDATE_ELEM: libc::c_int = 2;
+const BPF_PROG_LOAD: libc::c_int = 5;
+const BPF_LINK_CREATE: libc::c_int = 28;
+const BPF_MAP_TYPE_XSKMAP: u32 = 17;
+const BPF_PROG_TYPE_XDP: u32 = 6;
+const BPF_XDP: u32 = 37;
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+const BPF_FUNC_REDIRECT_MAP: i32 = 51;
+const XDP_FLAGS_SKB_MODE: u32 = 1 << 1;
+const XDP_PASS: i32 = 2;
+const XDP_PROGRAM_NAME: &[u8] = b"nperf_redirect";
+const XDP_PROGRAM_LICENSE: &[u8] = b"Dual BSD/GPL\0";
+const XDP_VERIFIER_LOG_SIZE: usize = 1 << 16;
+
+#[repr(C)]
+struct BpfInstruction {
+    code: u8,
+    // Destination register in the lower, source register in the upper 4 bits
+    registers: u8,
+    offset: i16,
+    immediate: i32,
+}
+
+#[repr(C)]
+struct MapCreateAttributes {
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+}
+
+#[repr(C)]
+struct MapUpdateAttributes {
+    map_fd: u32,
+    padding: u32,
+    key: u64,
+    value: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+struct ProgramLoadAttributes {
+    program_type: u32,
+    amount_instructions: u32,
+    instructions: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buffer: u64,
+    kernel_version: u32,
+    program_flags: u32,
+    program_name: [u8; 16],
+    program_ifindex: u32,
+    expected_attach_type: u32,
+}
+
+#[repr(C)]
+struct LinkCreateAttributes {
+    program_fd: u32,
+    target_ifindex: u32,
+    attach_type: u32,
+    flags: u32,
+}
+
+// Minimal XDP program attached in generic (SKB) mode, which redirects the unfragmented IPv4/UDP frames to the data ports of a test into the XSKMAP.
+// The map holds the AF_XDP socket of every receive queue, all other frames like the control channel pass on to the network stack.
+// The program is detached from the interface, as soon as it is dropped.
+pub struct XdpProgram {
+    xsk_map: RawFd,
+    program: RawFd,
+    link: RawFd,
+}
+
+impl XdpProgram {
+    pub fn new(interface: &str, first_port: u16, amount_ports: u16, amount_threads: u16) -> Result<XdpProgram, &'static str> {
+        let interface_index = packet_ring::get_interface_index(interface)?;
+
+        // Every receiver thread binds its socket to the receive queue with its index. Frames on a queue without a socket would pass to the network stack uncounted.
+        let amount_queues = fs::read_dir(format!("/sys/class/net/{}/queues", interface)).map_err(|_| "Failed to get receive queues of interface")?
+            .filter(|entry| entry.as_ref().is_ok_and(|entry| entry.file_name().to_string_lossy().starts_with("rx-")))
+            .count();
+        if amount_queues != amount_threads as usize {
+            error!("Interface {} has {} receive queues, but AF_XDP needs exactly one for each of the {} receiver threads", interface, amount_queues, amount_threads);
+            return Err("Interface doesn't have a receive queue for every receiver thread");
+        }
+        // The interface spreads the frames over its queues by their flow, not by the sender thread they come from
+        if amount_threads > 1 {
+            warn!("AF_XDP receiver threads get the frames of their receive queue, so the per-thread statistics show the distribution over the queues, not over the sender threads");
+        }
+
+        let mut xdp_program = XdpProgram { xsk_map: -1, program: -1, link: -1 };
+
+        let map_attributes = MapCreateAttributes {
+            map_type: BPF_MAP_TYPE_XSKMAP,
+            key_size: 4,
+            value_size: 4,
+            max_entries: amount_threads as u32,
+        };
+        xdp_program.xsk_map = bpf(BPF_MAP_CREATE, &map_attributes).map_err(|x| {
+            error!("Error creating XSKMAP: {}", x);
+            "Failed to create XSKMAP, which needs CAP_BPF and CAP_NET_ADMIN"
+        })?;
+
+        let instructions = redirect_instructions(xdp_program.xsk_map, first_port, amount_ports);
+        let mut log = vec![0_u8; XDP_VERIFIER_LOG_SIZE];
+        let mut program_name = [0; 16];
+        program_name[..XDP_PROGRAM_NAME.len()].copy_from_slice(XDP_PROGRAM_NAME);
+        let program_attributes = ProgramLoadAttributes {
+            program_type: BPF_PROG_TYPE_XDP,
+            amount_instructions: instructions.len() as u32,
+            instructions: instructions.as_ptr() as u64,
+            license: XDP_PROGRAM_LICENSE.as_ptr() as u64,
+            log_level: 1,
+            log_size: log.len() as u32,
+            log_buffer: log.as_mut_ptr() as u64,
+            kernel_version: 0,
+            program_flags: 0,
+            program_name,
+            program_ifindex: 0,
+            expected_attach_type: BPF_XDP,
+        };
+        xdp_program.program = bpf(BPF_PROG_LOAD, &program_attributes).map_err(|x| {
+            error!("Error loading XDP program: {}\n{}", x, String::from_utf8_lossy(&log).trim_end_matches('\0'));
+            "Failed to load XDP program"
+        })?;
+
+        // Attaching the program with a BPF link detaches it, as soon as the link is closed
+        let link_attributes = LinkCreateAttributes {
+            program_fd: xdp_program.program as u32,
+            target_ifindex: interface_index as u32,
+            attach_type: BPF_XDP,
+            flags: XDP_FLAGS_SKB_MODE,
+        };
+        xdp_program.link = bpf(BPF_LINK_CREATE, &link_attributes).map_err(|x| {
+            error!("Error attaching XDP program to interface {}: {}", interface, x);
+            "Failed to attach XDP program, maybe another one is attached to the interface"
+        })?;
+
+        info!("Attached XDP program in generic mode to interface {}, redirecting ports {} to {}", interface, first_port, first_port as u32 + amount_ports as u32 - 1);
+        Ok(xdp_program)
+    }
+
+    pub fn xsk_map(&self) -> RawFd {
+        self.xsk_map
+    }
+}
+
+impl Drop for XdpProgram {
+    fn drop(&mut self) {
+        for fd in [self.link, self.program, self.xsk_map] {
+            if fd != -1 {
+                unsafe { libc::close(fd) };
+            }
+        }
+    }
+}
+
+// Without an interface, the receiver has to listen on a loopback address
+pub fn receive_interface(interface: Option<&str>, ip: IpAddr) -> Result<&str, &'static str> {
+    match interface {
+        Some(interface) => Ok(interface),
+        None if ip.is_loopback() => Ok("lo"),
+        None => Err("AF_XDP needs the interface to receive on, set with --interface on the receiver")
+    }
+}
+
+fn bpf<T>(command: libc::c_int, attributes: &T) -> Result<RawFd, Error> {
+    match unsafe { libc::syscall(libc::SYS_bpf, command, attributes as *const T, std::mem::size_of::<T>()) } {
+        -1 => Err(Error::last_os_error()),
+        fd => Ok(fd as RawFd)
+    }
+}
+
+// XDP_PASS for all frames, which aren't IPv4/UDP without IP options and fragmentation to one of the ports starting with first_port.
+// The key of the XSKMAP is the receive queue of the frame, and if no socket is bound to it, the frame passes as well.
+fn redirect_instructions(xsk_map: RawFd, first_port: u16, amount_ports: u16) -> Vec<BpfInstruction> {
+    let instruction = |code: u32, destination: u8, source: u8, offset: i16, immediate: i32| BpfInstruction {
+        code: code as u8,
+        registers: source << 4 | destination,
+        offset,
+        immediate
+    };
+    // Jump offset from the instruction with the given index to the last two instructions, which pass the frame
+    let pass = |index: i16| 23 - index - 1;
+
+    vec![
+        // r2 = ctx->data, r3 = ctx->data_end
+        instruction(libc::BPF_LDX | libc::BPF_MEM | libc::BPF_W, 2, 1, 0, 0),
+        instruction(libc::BPF_LDX | libc::BPF_MEM | libc::BPF_W, 3, 1, 4, 0),
+        // The verifier only allows reading the headers after checking the frame is long enough
+        instruction(BPF_ALU64 | BPF_MOV | libc::BPF_X, 4, 2, 0, 0),
+        instruction(BPF_ALU64 | libc::BPF_ADD | libc::BPF_K, 4, 0, 0, (LEN_ETHERNET_HEADER + 28) as i32),
+        instruction(libc::BPF_JMP | libc::BPF_JGT | libc::BPF_X, 4, 3, pass(4), 0),
+        // Loads are in network byte order, so the constants are swapped
+        instruction(libc::BPF_LDX | libc::BPF_MEM | libc::BPF_H, 4, 2, 12, 0),
+        instruction(libc::BPF_JMP | BPF_JNE | libc::BPF_K, 4, 0, pass(6), (libc::ETH_P_IP as u16).to_be() as i32),
+        instruction(libc::BPF_LDX | libc::BPF_MEM | libc::BPF_B, 4, 2, LEN_ETHERNET_HEADER, 0),
+        instruction(libc::BPF_JMP | BPF_JNE | libc::BPF_K, 4, 0, pass(8), 0x45),
+        instruction(libc::BPF_LDX | libc::BPF_MEM | libc::BPF_B, 4, 2, LEN_ETHERNET_HEADER + 9, 0),
+        instruction(libc::BPF_JMP | BPF_JNE | libc::BPF_K, 4, 0, pass(10), libc::IPPROTO_UDP),
+        instruction(libc::BPF_LDX | libc::BPF_MEM | libc::BPF_H, 4, 2, LEN_ETHERNET_HEADER + 6, 0),
+        instruction(libc::BPF_JMP | libc::BPF_JSET | libc::BPF_K, 4, 0, pass(12), (packet_ring::IP_FRAGMENT_MASK as u16).to_be() as i32),
+        instruction(libc::BPF_LDX | libc::BPF_MEM | libc::BPF_H, 4, 2, LEN_ETHERNET_HEADER + 22, 0),
+        instruction(libc::BPF_ALU | BPF_END | BPF_TO_BE, 4, 0, 0, 16),
+        instruction(libc::BPF_JMP | BPF_JLT | libc::BPF_K, 4, 0, pass(15), first_port as i32),
+        instruction(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, 4, 0, pass(16), first_port as i32 + amount_ports as i32),
+        // return bpf_redirect_map(&xsk_map, ctx->rx_queue_index, XDP_PASS)
+        instruction(libc::BPF_LDX | libc::BPF_MEM | libc::BPF_W, 2, 1, 16, 0),
+        instruction(libc::BPF_LD | BPF_DW | libc::BPF_IMM, 1, BPF_PSEUDO_MAP_FD, 0, xsk_map),
+        instruction(0, 0, 0, 0, 0),
+        instruction(BPF_ALU64 | BPF_MOV | libc::BPF_K, 3, 0, 0, XDP_PASS),
+        instruction(libc::BPF_JMP | BPF_CALL, 0, 0, 0, BPF_FUNC_REDIRECT_MAP),
+        instruction(libc::BPF_JMP | BPF_EXIT, 0, 0, 0, 0),
+        // return XDP_PASS
+        instruction(BPF_ALU64 | BPF_MOV | libc::BPF_K, 0, 0, 0, XDP_PASS),
+        instruction(libc::BPF_JMP | BPF_EXIT, 0, 0, 0, 0),
+    ]
+}
+
+// Memory mapped producer/consumer ring of an AF_XDP socket
+struct XdpRing {
+    map: *mut u8,
+    map_size: usize,
+    producer: *const AtomicU32,
+    consumer: *const AtomicU32,
+    descriptors: *mut u8,
+    mask: u32,
+}
+
+impl XdpRing {
+    fn new(socket: RawFd, offsets: &libc::xdp_ring_offset, page_offset: libc::off_t, amount_entries: u32, entry_size: usize) -> Result<XdpRing, &'static str> {
+        let map_size = offsets.desc as usize + amount_entries as usize * entry_size;
+        let map = unsafe { libc::mmap(ptr::null_mut(), map_size, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED | libc::MAP_POPULATE, socket, page_offset) };
+        if map == libc::MAP_FAILED {
+            error!("Error mapping AF_XDP ring with offset {:#x}: {}", page_offset, Error::last_os_error());
+            return Err("Failed to map AF_XDP ring");
+        }
+
+        let map = map as *mut u8;
+        Ok(XdpRing {
+            map,
+            map_size,
+            producer: unsafe { map.add(offsets.producer as usize) } as *const AtomicU32,
+            consumer: unsafe { map.add(offsets.consumer as usize) } as *const AtomicU32,
+            descriptors: unsafe { map.add(offsets.desc as usize) },
+            mask: amount_entries - 1,
+        })
+    }
+
+    fn producer(&self) -> &AtomicU32 {
+        unsafe { &*self.producer }
+    }
+
+    fn consumer(&self) -> &AtomicU32 {
+        unsafe { &*self.consumer }
+    }
+}
+
+impl Drop for XdpRing {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.map as *mut libc::c_void, self.map_size) };
+    }
+}
+
+// AF_XDP socket of a receiver thread, bound in copy mode to a receive queue of the interface.
+// The page aligned packet buffer of the receiver is registered as its UMEM: The kernel copies the redirected frames into the UMEM frames taken from the fill ring,
+// and hands them over with the RX ring. Every frame is returned to the fill ring, as soon as its datagram is processed.
+pub struct XdpSocket {
+    socket: RawFd,
+    // Owned by the packet buffer of the receiver, which has to outlive the socket
+    umem: *mut u8,
+    fill_ring: Option<XdpRing>,
+    rx_ring: Option<XdpRing>,
+}
+
+impl XdpSocket {
+    pub fn new(interface: &str, xsk_map: RawFd, queue: u32, umem: &mut [u8]) -> Result<XdpSocket, &'static str> {
+        let interface_index = packet_ring::get_interface_index(interface)?;
+
+        let socket = unsafe { libc::socket(libc::AF_XDP, libc::SOCK_RAW, 0) };
+        if socket == -1 {
+            error!("Error creating AF_XDP socket: {}", Error::last_os_error());
+            return Err("Failed to create AF_XDP socket");
+        }
+
+        // The socket is closed, if the setup fails from here on
+        let mut xdp_socket = XdpSocket { socket, umem: umem.as_mut_ptr(), fill_ring: None, rx_ring: None };
+        if umem.len() != crate::XDP_AMOUNT_FRAMES as usize * crate::XDP_FRAME_SIZE as usize {
+            return Err("Packet buffer doesn't fit the frames of the UMEM");
+        }
+
+        let umem_register = libc::xdp_umem_reg {
+            addr: xdp_socket.umem as u64,
+            len: umem.len() as u64,
+            chunk_size: crate::XDP_FRAME_SIZE,
+            headroom: 0,
+            flags: 0,
+            tx_metadata_len: 0,
+        };
+        xdp_socket.set_option(libc::XDP_UMEM_REG, &umem_register, "UMEM")?;
+        xdp_socket.set_option(libc::XDP_UMEM_FILL_RING, &crate::XDP_AMOUNT_FRAMES, "fill ring")?;
+        // The completion ring is only used for sending, but the socket can't be bound without it
+        xdp_socket.set_option(libc::XDP_UMEM_COMPLETION_RING, &crate::XDP_AMOUNT_FRAMES, "completion ring")?;
+        xdp_socket.set_option(libc::XDP_RX_RING, &crate::XDP_AMOUNT_FRAMES, "RX ring")?;
+
+        let mut offsets: libc::xdp_mmap_offsets = unsafe { MaybeUninit::zeroed().assume_init() };
+        let mut offsets_size = std::mem::size_of_val(&offsets) as libc::socklen_t;
+        if unsafe { libc::getsockopt(socket, libc::SOL_XDP, libc::XDP_MMAP_OFFSETS, &mut offsets as *mut _ as _, &mut offsets_size) } == -1 {
+            error!("Error getting AF_XDP ring offsets: {}", Error::last_os_error());
+            return Err("Failed to get AF_XDP ring offsets");
+        }
+
+        let fill_ring = XdpRing::new(socket, &offsets.fr, libc::XDP_UMEM_PGOFF_FILL_RING as libc::off_t, crate::XDP_AMOUNT_FRAMES, std::mem::size_of::<u64>())?;
+        let rx_ring = XdpRing::new(socket, &offsets.rx, libc::XDP_PGOFF_RX_RING, crate::XDP_AMOUNT_FRAMES, std::mem::size_of::<libc::xdp_desc>())?;
+
+        // Initially, all frames of the UMEM are handed to the kernel
+        for index in 0..crate::XDP_AMOUNT_FRAMES {
+            unsafe { *(fill_ring.descriptors as *mut u64).add(index as usize) = index as u64 * crate::XDP_FRAME_SIZE as u64 };
+        }
+        fill_ring.producer().store(crate::XDP_AMOUNT_FRAMES, Ordering::Release);
+        xdp_socket.fill_ring = Some(fill_ring);
+        xdp_socket.rx_ring = Some(rx_ring);
+
+        // Generic XDP only supports copy mode
+        let address = libc::sockaddr_xdp {
+            sxdp_family: libc::AF_XDP as u16,
+            sxdp_flags: libc::XDP_COPY,
+            sxdp_ifindex: interface_index as u32,
+            sxdp_queue_id: queue,
+            sxdp_shared_umem_fd: 0,
+        };
+        if unsafe { libc::bind(socket, &address as *const _ as *const libc::sockaddr, std::mem::size_of_val(&address) as libc::socklen_t) } == -1 {
+            error!("Error binding AF_XDP socket to queue {} of interface {}: {}", queue, interface, Error::last_os_error());
+            return Err("Failed to bind AF_XDP socket");
+        }
+
+        let map_attributes = MapUpdateAttributes {
+            map_fd: xsk_map as u32,
+            padding: 0,
+            key: &queue as *const u32 as u64,
+            value: &socket as *const RawFd as u64,
+            flags: 0,
+        };
+        bpf(BPF_MAP_UPDATE_ELEM, &map_attributes).map_err(|x| {
+            error!("Error inserting AF_XDP socket into XSKMAP: {}", x);
+            "Failed to insert AF_XDP socket into XSKMAP"
+        })?;
+
+        info!("Bound AF_XDP socket with UMEM of {} frames to queue {} of interface {}", crate::XDP_AMOUNT_FRAMES, queue, interface);
+        Ok(xdp_socket)
+    }
+
+    fn set_option<T>(&self, option: libc::c_int, value: &T, name: &str) -> Result<(), &'static str> {
+        if unsafe { libc::setsockopt(self.socket, libc::SOL_XDP, option, value as *const T as _, std::mem::size_of::<T>() as libc::socklen_t) } == -1 {
+            error!("Error setting up {} of AF_XDP socket: {}", name, Error::last_os_error());
+            return Err("Failed to set up AF_XDP socket");
+        }
+        Ok(())
+    }
+
+    // Frames the kernel couldn't hand over to the socket, since the RX ring was full or no frame of the UMEM was left
+    fn log_statistics(&self) {
+        let mut statistics: libc::xdp_statistics = unsafe { MaybeUninit::zeroed().assume_init() };
+        let mut statistics_size = std::mem::size_of_val(&statistics) as libc::socklen_t;
+        if unsafe { libc::getsockopt(self.socket, libc::SOL_XDP, libc::XDP_STATISTICS, &mut statistics as *mut _ as _, &mut statistics_size) } == 0 {
+            info!("AF_XDP socket dropped {} frames, RX ring was full {} times, fill ring was empty {} times", statistics.rx_dropped, statistics.rx_ring_full, statistics.rx_fill_ring_empty_descs);
+        }
+    }
+}
+
+impl FrameRing for XdpSocket {
+    fn recv(&mut self, process: &mut dyn FnMut(&[u8])) -> Result<u64, &'static str> {
+        let (Some(rx_ring), Some(fill_ring)) = (self.rx_ring.as_ref(), self.fill_ring.as_ref()) else {
+            return Err("AF_XDP socket isn't set up");
+        };
+
+        let consumer = rx_ring.consumer().load(Ordering::Relaxed);
+        let amount_frames = rx_ring.producer().load(Ordering::Acquire).wrapping_sub(consumer);
+        if amount_frames == 0 {
+            return Err("EAGAIN");
+        }
+
+        // The fill ring always has space for the returned frames, since it holds all frames of the UMEM
+        let fill_producer = fill_ring.producer().load(Ordering::Relaxed);
+        for index in 0..amount_frames {
+            let descriptor = unsafe { &*(rx_ring.descriptors as *const libc::xdp_desc).add((consumer.wrapping_add(index) & rx_ring.mask) as usize) };
+            let frame = unsafe { std::slice::from_raw_parts(self.umem.add(descriptor.addr as usize), descriptor.len as usize) };
+            match packet_ring::parse_udp_frame(frame) {
+                Some(datagram) => process(datagram),
+                None => debug!("Dropping frame of {} bytes without an UDP datagram", frame.len())
+            }
+            unsafe { *(fill_ring.descriptors as *mut u64).add((fill_producer.wrapping_add(index) & fill_ring.mask) as usize) = descriptor.addr };
+        }
+
+        rx_ring.consumer().store(consumer.wrapping_add(amount_frames), Ordering::Release);
+        fill_ring.producer().store(fill_producer.wrapping_add(amount_frames), Ordering::Release);
+
+        trace!("Received {} frames from the AF_XDP socket", amount_frames);
+        Ok(amount_frames as u64)
+    }
+
+    fn wait(&self, events: libc::c_short, timeout: i32) -> Result<(), &'static str> {
+        let mut pollfd = libc::pollfd { fd: self.socket, events, revents: 0 };
+        let poll_result = unsafe { libc::poll(&mut pollfd, 1, timeout) };
+
+        if poll_result == -1 {
+            error!("Error occured executing poll() on AF_XDP socket: {}", Error::last_os_error());
+            Err("Error occured executing poll()")
+        } else if poll_result == 0 {
+            debug!("Poll on AF_XDP socket returned due to timeout");
+            Err("TIMEOUT")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for XdpSocket {
+    fn drop(&mut self) {
+        if self.rx_ring.is_some() {
+            self.log_statistics();
+        }
+        // The rings have to be unmapped, before the socket is closed
+        self.rx_ring = None;
+        self.fill_ring = None;
+        unsafe { libc::close(self.socket) };
+    }
+}
+
@@ -1,9 +1,76 @@
-use crate::util::{statistic::Statistic, IOModel};
+use std::sync::{Arc, Barrier};
+use std::time::Instant;
+
+use crate::util::{cpu_util::ThreadCpuTime, statistic::Statistic, IOModel};
 
 pub mod sender;
 pub mod receiver;
 
+// Releases all node threads of a test at the same instant, after each of them finished its setup.
+// The nodes wait on it themselves after setting up their io_uring instance. A thread only waits once.
+// A thread, which fails or panics during its setup, waits when its start barrier is dropped, so it still releases the other threads and the main thread.
+pub struct StartBarrier {
+    barrier: Arc<Barrier>,
+    // The transmitting threads of a reverse test wait on it after binding their sockets, so the measuring side isn't told to send its hole punches before
+    bound_barrier: Option<Arc<Barrier>>,
+    bound: bool,
+    released: Option<Instant>,
+    // CPU time of the thread at the start of its measurement, so neither the setup nor the wait for the first datagram is accounted to it
+    measurement_cpu_time: ThreadCpuTime,
+}
+
+impl StartBarrier {
+    pub fn new(barrier: Arc<Barrier>, bound_barrier: Option<Arc<Barrier>>) -> StartBarrier {
+        StartBarrier {
+            barrier,
+            bound_barrier,
+            bound: false,
+            released: None,
+            measurement_cpu_time: ThreadCpuTime::default()
+        }
+    }
+
+    // Only the transmitting threads of a reverse test have a bound barrier, every other thread returns at once
+    pub fn wait_bound(&mut self) {
+        if !self.bound {
+            self.bound = true;
+            if let Some(bound_barrier) = &self.bound_barrier {
+                bound_barrier.wait();
+            }
+        }
+    }
+
+    pub fn wait(&mut self) {
+        self.wait_bound();
+        if self.released.is_none() {
+            self.barrier.wait();
+            self.released = Some(Instant::now());
+            self.measurement_cpu_time = ThreadCpuTime::current();
+        }
+    }
+
+    // Called by the node as soon as the test duration of its statistic starts, which is the first datagram on the receiving side
+    pub fn start_measurement(&mut self) {
+        self.measurement_cpu_time = ThreadCpuTime::current();
+    }
+
+    // The instant the thread was released from the barrier, to report the spread between the threads
+    pub fn released(&self) -> Option<Instant> {
+        self.released
+    }
+
+    pub fn measurement_cpu_time(&self) -> ThreadCpuTime {
+        self.measurement_cpu_time
+    }
+}
+
+impl Drop for StartBarrier {
+    fn drop(&mut self) {
+        self.wait();
+    }
+}
+
 pub trait Node {
-    fn run(&mut self, io_model: IOModel) -> Result<(Statistic, Vec<Statistic>), &'static str>;
+    fn run(&mut self, io_model: IOModel, start_barrier: &mut StartBarrier) -> Result<Statistic, &'static str>;
     fn io_wait(&mut self, io_model: IOModel) -> Result<(), &'static str>;
 }
@@ -1,93 +1,249 @@
-use std::net::SocketAddrV4;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
 use std::os::fd::RawFd;
-use std::thread::{self, sleep};
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
 use log::{debug, error, info, trace, warn};
 
 use io_uring::types::RecvMsgOut;
 use crate::io_uring::multishot::IoUringMultishot;
 use crate::io_uring::normal::IoUringNormal;
 use crate::io_uring::provided_buffer::IoUringProvidedBuffer;
-use crate::io_uring::{parse_received_bytes, IoUringOperatingModes, UringMode};
+use crate::io_uring::{decode_user_data, parse_received_bytes, IoUringOperatingModes, UringMode};
 use crate::util::msghdr_vec::MsghdrVec;
 use crate::util::packet_buffer::PacketBuffer;
-use crate::net::{socket::Socket, MessageHeader, MessageType};
-use crate::util::{self, statistic::*, ExchangeFunction, IOModel};
-use super::Node;
+use crate::util::counters::{CounterSnapshot, ThreadCounters};
+use crate::util::timer::TestTimer;
+use crate::net::{socket::Socket, socket_set::SocketSet, FrameRing};
+use crate::util::{self, interrupt, statistic::*, ExchangeFunction, IOModel, Transport};
+use super::{Node, StartBarrier};
 
-const INITIAL_POLL_TIMEOUT: i32 = 10000; // in milliseconds
 const IN_MEASUREMENT_POLL_TIMEOUT: i32 = 1000; // in milliseconds
 
+// Set up before the start barrier, so the setup of the rings and buffers isn't part of the measurement
+enum ReceiverUring {
+    Multishot(IoUringMultishot),
+    ProvidedBuffer(IoUringProvidedBuffer),
+    Normal(IoUringNormal),
+}
+
 pub struct Receiver {
+    // Dropped before the packet buffer, which holds the UMEM of an AF_XDP socket
+    frame_ring: Option<Box<dyn FrameRing>>,
     packet_buffer: PacketBuffer,
+    // The socket of the set, which is currently received on
     socket: Socket,
+    sockets: SocketSet,
+    // Only used, if the thread drives several sockets
+    socket_statistics: Vec<SocketStatistic>,
+    // Sockets of the io_uring completions consumed since the last submission, to resubmit to the same sockets
+    uring_resubmit: VecDeque<usize>,
     io_uring_sqpoll_fd: Option<RawFd>,
-    next_packet_id: u64,
     parameter: Parameter,
+    // One measurement per simulated connection, indexed by the connection id
     measurements: Vec<Measurement>,
-    statistic_interval: StatisticInterval,
-    exchange_function: ExchangeFunction
+    timer: Arc<TestTimer>,
+    counters: Arc<ThreadCounters>,
+    exchange_function: ExchangeFunction,
+    // Datagrams without a valid header can't be assigned to a measurement
+    amount_foreign_datagrams: u64,
+    // Bytes received over a TCP stream, to count the datagrams
+    stream_bytes: u64,
+    stream_closed: bool,
+    // Waiting returns at least this often, to check the stop flag and the receive timeouts
+    poll_timeout: i32,
+    // Start of the current period without a received datagram, on the clock of the timer
+    idle_since: Option<u64>,
+    termination_reason: TerminationReason,
+    stop_flag: Arc<AtomicBool>
 }
 
 impl Receiver {
-    pub fn new(sock_address_in: SocketAddrV4, socket: Option<Socket>, io_uring: Option<RawFd>, parameter: Parameter) -> Receiver {
-        let socket = if let Some(socket) = socket {
-            socket
+    // The frames of an AF_XDP socket are received into the packet buffer, so it's allocated page aligned as its UMEM
+    pub fn create_packet_buffer(parameter: &Parameter) -> PacketBuffer {
+        if parameter.io_model == IOModel::AfXdp {
+            PacketBuffer::new_page_aligned(crate::XDP_AMOUNT_FRAMES as usize * crate::XDP_FRAME_SIZE as usize, crate::XDP_FRAME_SIZE as usize)
+        } else {
+            PacketBuffer::new(MsghdrVec::new(parameter.packet_buffer_size, parameter.mss, parameter.datagram_size as usize).with_cmsg_buffer())
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(sock_address_in: SocketAddr, socket: Option<Socket>, io_uring: Option<RawFd>, frame_ring: Option<Box<dyn FrameRing>>, packet_buffer: PacketBuffer, timer: Arc<TestTimer>, counters: Arc<ThreadCounters>, stop_flag: Arc<AtomicBool>, parameter: Parameter) -> Result<Receiver, &'static str> {
+        let sockets: Vec<Socket> = if let Some(socket) = socket {
+            vec![socket]
         } else {
-            let mut socket: Socket = Socket::new(parameter.socket_options).expect("Error creating socket");
-            socket.bind(sock_address_in).expect("Error binding to local port");
-            socket
+            // Every socket of the thread listens on its own port
+            (0..parameter.sockets_per_thread).map(|index| {
+                let mut socket: Socket = Socket::new(sock_address_in.ip(), parameter.transport, parameter.socket_options, parameter.interface.as_deref()).ok_or("Error creating socket")?;
+                socket.bind(SocketAddr::new(sock_address_in.ip(), sock_address_in.port() + index))?;
+                if parameter.transport == Transport::Tcp {
+                    socket.listen()?;
+                }
+                Ok(socket)
+            }).collect::<Result<_, &'static str>>()?
         };
 
-        info!("Current mode 'receiver' listening on {}:{} with socketID {}", sock_address_in.ip(), sock_address_in.port(), socket.get_socket_id());
-        let packet_buffer = PacketBuffer::new(MsghdrVec::new(parameter.packet_buffer_size, parameter.mss, parameter.datagram_size as usize).with_cmsg_buffer());
+        let poll_timeout = [parameter.receive_start_timeout, parameter.receive_idle_timeout].into_iter().filter(|&timeout| timeout != 0).fold(IN_MEASUREMENT_POLL_TIMEOUT as u64, u64::min) as i32;
+        for (index, socket) in sockets.iter().enumerate() {
+            // A blocking socket has to return from time to time, to check if the sender stopped the test
+            if !parameter.socket_options.nonblocking {
+                socket.set_receive_timeout(poll_timeout)?;
+            }
+
+            info!("Current mode 'receiver' listening on {} with socketID {}", SocketAddr::new(sock_address_in.ip(), sock_address_in.port() + index as u16), socket.get_socket_id());
+        }
+        let socket_statistics = if sockets.len() > 1 {
+            (0..sockets.len()).map(|index| SocketStatistic { port: sock_address_in.port() + index as u16, ..Default::default() }).collect()
+        } else {
+            Vec::new()
+        };
 
-        Receiver {
+        Ok(Receiver {
+            frame_ring,
             packet_buffer,
-            socket,
+            socket: sockets[0],
+            sockets: SocketSet::new(sockets, libc::POLLIN, parameter.epoll_trigger, parameter.epoll_exclusive),
+            socket_statistics,
+            uring_resubmit: VecDeque::new(),
             io_uring_sqpoll_fd: io_uring,
-            next_packet_id: 0,
             parameter: parameter.clone(),
-            measurements: Vec::new(),
-            statistic_interval: StatisticInterval::new(Instant::now(), parameter.output_interval, parameter.test_runtime_length),
-            exchange_function: parameter.exchange_function
+            measurements: Measurement::for_connections(&parameter),
+            timer,
+            counters,
+            exchange_function: parameter.exchange_function,
+            amount_foreign_datagrams: 0,
+            stream_bytes: 0,
+            stream_closed: false,
+            poll_timeout,
+            idle_since: None,
+            termination_reason: TerminationReason::Stopped,
+            stop_flag
+        })
+    }
+
+    // A TCP connection is drained until the sender closes it. The io_uring loops don't wait for the sockets, so they check the idle timeout here.
+    // io_uring waits for an empty socket instead of returning EAGAIN, so the sockets are drained, as soon as an iteration received no more datagrams.
+    fn receiving_finished(&mut self, drained: bool) -> bool {
+        (self.stop_flag.load(Ordering::Relaxed) && if self.parameter.transport == Transport::Tcp { self.stream_closed } else { drained }) || self.idle_timeout_expired()
+    }
+
+    // Every received datagram ends the idle period, so the timeout only expires if nothing arrived for its whole length
+    fn idle_timeout_expired(&mut self) -> bool {
+        if self.parameter.receive_idle_timeout == 0 {
+            return false;
+        }
+
+        let elapsed_millis = self.timer.elapsed_millis();
+        let idle_since = *self.idle_since.get_or_insert(elapsed_millis);
+        if elapsed_millis - idle_since < self.parameter.receive_idle_timeout {
+            return false;
+        }
+
+        warn!("{:?}: No datagram received for {}ms, ending the measurement!", thread::current().id(), self.parameter.receive_idle_timeout);
+        self.termination_reason = TerminationReason::IdleTimeout;
+        true
+    }
+
+    // The executor stops the threads of an interrupted test with the stop flag, unless a receive timeout ended the thread before
+    fn final_termination_reason(&self) -> TerminationReason {
+        if self.termination_reason == TerminationReason::Stopped && interrupt::interrupted() {
+            TerminationReason::Interrupted
+        } else {
+            self.termination_reason
+        }
+    }
+
+    // The listening socket is replaced by the connection of the sender
+    fn accept_connection(&mut self) -> Result<(), &'static str> {
+        let listening_socket = self.socket;
+        self.socket = listening_socket.accept(self.parameter.socket_options.nonblocking)?;
+        listening_socket.close()?;
+
+        if !self.parameter.socket_options.nonblocking {
+            self.socket.set_receive_timeout(self.poll_timeout)?;
+        }
+        self.sockets = SocketSet::new(vec![self.socket], libc::POLLIN, self.parameter.epoll_trigger, self.parameter.epoll_exclusive);
+        Ok(())
+    }
+
+    // Accounts the datagrams received on a socket, if the thread drives several sockets
+    fn count_socket(&mut self, socket_index: usize, amount_datagrams: u64, amount_received_bytes: usize) {
+        if let Some(socket_statistic) = self.socket_statistics.get_mut(socket_index) {
+            socket_statistic.amount_datagrams += amount_datagrams;
+            socket_statistic.amount_data_bytes += amount_received_bytes;
+        }
+    }
+
+    // Every receive call uses the next readable socket of the thread
+    fn next_socket(&mut self) {
+        if self.sockets.amount_sockets() > 1 {
+            self.sockets.rotate();
+            self.socket = self.sockets.current();
         }
     }
 
     fn recv_messages(&mut self) -> Result<(), &'static str> {
         // The msg_controllen field should be the same for all messages, since it should only contain the GRO enabled control message.
-        // It is only reset after the first message, since the first message is the INIT message, which doesn't contain any control messages.
+        // It is only reset before the first message is received.
 
-        if self.parameter.socket_options.gro && self.next_packet_id == 0 {
+        if self.parameter.socket_options.gro && self.counters.datagrams().0 == 0 {
             self.packet_buffer.reset_msghdr_fields();
         }
 
+        if self.parameter.transport == Transport::Tcp {
+            return self.recv_stream();
+        }
+
         match self.exchange_function {
             ExchangeFunction::Normal => self.recv(),
             ExchangeFunction::Msg => self.recvmsg(),
             ExchangeFunction::Mmsg => self.recvmmsg(),
+        }?;
+
+        self.next_socket();
+        Ok(())
+    }
+
+    // A TCP stream has no datagram boundaries, so the headers can't be parsed. Returns EOF, as soon as the sender closed the connection.
+    fn recv_stream(&mut self) -> Result<(), &'static str> {
+        let amount_received_bytes = match self.exchange_function {
+            ExchangeFunction::Msg => self.socket.recvmsg(self.packet_buffer.get_msghdr_from_index(0).unwrap())?,
+            _ => self.socket.recv(self.packet_buffer.get_buffer_pointer_from_index(0).unwrap())?
+        };
+
+        if amount_received_bytes == 0 {
+            return Err("EOF");
         }
+
+        self.count_stream_bytes(amount_received_bytes);
+        Ok(())
+    }
+
+    fn count_stream_bytes(&mut self, amount_received_bytes: usize) {
+        let datagram_size = self.packet_buffer.datagram_size();
+        let amount_datagrams = util::count_stream_datagrams(&mut self.stream_bytes, amount_received_bytes, datagram_size);
+        let statistic = &mut Measurement::start(&mut self.measurements, 0).statistic;
+        statistic.amount_datagrams += amount_datagrams;
+        statistic.amount_data_bytes += amount_received_bytes;
+        self.counters.add_datagrams(amount_datagrams, amount_received_bytes);
     }
 
     #[inline(always)]
     fn recv(&mut self) -> Result<(), &'static str> {
+        let datagram_size = self.packet_buffer.datagram_size();
         // Only one buffer is used, so we can directly access the first element
         let buffer_pointer = self.packet_buffer.get_buffer_pointer_from_index(0).unwrap();
 
         match self.socket.recv(buffer_pointer) {
             Ok(amount_received_bytes) => {
-                let test_id = MessageHeader::get_test_id(buffer_pointer) as usize;
-                let mtype = MessageHeader::get_message_type(buffer_pointer);
-                debug!("Received packet with test id: {}", test_id);
-
-                Self::parse_message_type(mtype, test_id, &mut self.measurements, &self.parameter)?;
-
-                let statistic = &mut self.measurements.get_mut(test_id).expect("Error getting statistic: test id not found").statistic;
-                let datagram_size = self.packet_buffer.datagram_size();
-                let amount_received_packets = util::process_packet_buffer(self.packet_buffer.get_buffer_pointer_from_index(0).unwrap(), datagram_size, self.next_packet_id, statistic);
-                self.next_packet_id += amount_received_packets;
-                statistic.amount_datagrams += amount_received_packets;
-                statistic.amount_data_bytes += amount_received_bytes;
+                let mut batch = CounterSnapshot::default();
+                let (amount_received_packets, amount_foreign_packets) = util::process_packet_buffer(&buffer_pointer[..amount_received_bytes], datagram_size, &mut self.measurements, &mut batch, &self.parameter);
+                self.counters.add_batch(&batch);
+                self.amount_foreign_datagrams += amount_foreign_packets;
+                self.count_socket(self.sockets.index(), amount_received_packets, amount_received_bytes);
+                debug!("Received {} packets and total {} Bytes", amount_received_packets, amount_received_bytes);
                 Ok(())
             },
             Err(x) => Err(x)
@@ -101,26 +257,15 @@ impl Receiver {
 
         match self.socket.recvmsg(msghdr) {
             Ok(amount_received_bytes) => {
-                let buffer_pointer = self.packet_buffer.get_buffer_pointer_from_index(0).unwrap();
-                let test_id = MessageHeader::get_test_id(buffer_pointer) as usize;
-                let mtype = MessageHeader::get_message_type(buffer_pointer);
-        
-                Self::parse_message_type(mtype, test_id, &mut self.measurements, &self.parameter)?;
-        
                 let msghdr = self.packet_buffer.get_msghdr_from_index(0).unwrap();
-                let statistic = &mut self.measurements.get_mut(test_id).expect("Error getting statistic: test id not found").statistic;
-                let absolut_packets_received;
-                (self.next_packet_id, absolut_packets_received) = util::process_packet_msghdr(msghdr, amount_received_bytes, self.next_packet_id, statistic);
-                statistic.amount_datagrams += absolut_packets_received;
-                statistic.amount_data_bytes += amount_received_bytes;
-
-                // Reset msg_flags and msg_controllen fields
-                if self.parameter.socket_options.gro {
-                    msghdr.msg_flags = 0;
-                    msghdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
-                }
-
-                debug!("Received {} packets and total {} Bytes, and next packet id should be {}", absolut_packets_received, amount_received_bytes, self.next_packet_id);
+                let mut batch = CounterSnapshot::default();
+                let (absolut_packets_received, amount_foreign_packets) = util::process_packet_msghdr(msghdr, amount_received_bytes, &mut self.measurements, &mut batch, &self.parameter);
+                self.counters.add_batch(&batch);
+                self.amount_foreign_datagrams += amount_foreign_packets;
+                Self::reset_msghdr(msghdr, &self.parameter);
+                self.count_socket(self.sockets.index(), absolut_packets_received, amount_received_bytes);
+
+                debug!("Received {} packets and total {} Bytes", absolut_packets_received, amount_received_bytes);
                 Ok(())
             },
             Err(x) => Err(x)
@@ -136,35 +281,21 @@ impl Receiver {
                     return Ok(());
                 }
 
-                let test_id = MessageHeader::get_test_id(self.packet_buffer.get_buffer_pointer_from_index(0).unwrap()) as usize;
-                let mtype = MessageHeader::get_message_type(self.packet_buffer.get_buffer_pointer_from_index(0).unwrap());
-                let amount_received_bytes = util::get_total_bytes(&self.packet_buffer.mmsghdr_vec, amount_received_mmsghdr);
-
-                Self::parse_message_type(mtype, test_id, &mut self.measurements, &self.parameter)?;
-
-                let statistic = &mut self.measurements.get_mut(test_id).expect("Error getting statistic: test id not found").statistic;
-                let mut absolut_datagrams_received = 0;
-
-                // Check and calculate the amount of received packets and bytes
-                for (index, mmsghdr) in self.packet_buffer.mmsghdr_vec.iter_mut().enumerate() {
-                    if index >= amount_received_mmsghdr {
-                        break;
-                    }
-                    let msghdr = &mut mmsghdr.msg_hdr;
+                // Every datagram is assigned to the measurement of the connection in its own header
+                let (mut amount_received_packets, mut amount_received_bytes) = (0, 0);
+                let mut batch = CounterSnapshot::default();
+                for mmsghdr in self.packet_buffer.mmsghdr_vec.iter_mut().take(amount_received_mmsghdr) {
                     let msghdr_bytes = mmsghdr.msg_len as usize;
+                    let (absolut_packets_received, amount_foreign_packets) = util::process_packet_msghdr(&mut mmsghdr.msg_hdr, msghdr_bytes, &mut self.measurements, &mut batch, &self.parameter);
+                    self.amount_foreign_datagrams += amount_foreign_packets;
+                    amount_received_packets += absolut_packets_received;
+                    amount_received_bytes += msghdr_bytes;
 
-                    let datagrams_received;
-                    (self.next_packet_id, datagrams_received) = util::process_packet_msghdr(msghdr, msghdr_bytes, self.next_packet_id, statistic);
-                    absolut_datagrams_received += datagrams_received;
-                                    
-                    if self.parameter.socket_options.gro {
-                        msghdr.msg_flags = 0;
-                        msghdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
-                    }
+                    Self::reset_msghdr(&mut mmsghdr.msg_hdr, &self.parameter);
                 }
+                self.counters.add_batch(&batch);
+                self.count_socket(self.sockets.index(), amount_received_packets, amount_received_bytes);
 
-                statistic.amount_datagrams += absolut_datagrams_received;
-                statistic.amount_data_bytes += amount_received_bytes;
                 trace!("Sent {} msg_hdr to remote host", amount_received_mmsghdr);
                 Ok(())
             },
@@ -172,52 +303,17 @@ impl Receiver {
         }
     }
 
-    fn parse_message_type(mtype: MessageType, test_id: usize, measurements: &mut Vec<Measurement>, parameter: &Parameter) -> Result<(), &'static str> {
-        if test_id >= crate::MAX_TEST_ID {
-            error!("Received test id is greater than the maximum test id: {} > {}!", test_id, crate::MAX_TEST_ID);
-            return Err("Received test id is greater than the maximum test id")
-        }
-        match mtype {
-            MessageType::INIT => {
-                info!("{:?}: INIT packet received from test {}!", thread::current().id(), test_id);
-                // Resize the vector if neeeded, and create a new measurement struct
-                if measurements.len() <= test_id {
-                    measurements.resize(test_id + 1, Measurement::new(parameter.clone()));
-                }
-                Err("INIT_MESSAGE_RECEIVED")
-            },
-            MessageType::MEASUREMENT => { 
-                let measurement = if let Some(x) = measurements.get_mut(test_id) {
-                    x
-                } else {
-                    // No INIT message received before, so we need to resize and create/add a new measurement struct
-                    if measurements.len() <= test_id {
-                        measurements.resize(test_id + 1, Measurement::new(parameter.clone()));
-                    }
-                    measurements.get_mut(test_id).expect("Error getting statistic in measurement message: test id not found")
-                };
-                // Start measurement timer with receiving of the first MEASUREMENT message
-                if !measurement.first_packet_received {
-                    info!("{:?}: First packet received from test {}!", thread::current().id(), test_id);
-                    let start_time = Statistic::get_unix_timestamp();
-                    measurement.start_time = start_time;
-                    measurement.statistic.set_start_timestamp(Some(start_time));
-                    measurement.first_packet_received = true;
-                }
-                Ok(())
-            },
-            MessageType::LAST => {
-                info!("{:?}: LAST packet received from test {}!", thread::current().id(), test_id);
-                // Not checking for measurement length anymore, since we assume that the thread has received at least one MEASUREMENT message before
-                let measurement = measurements.get_mut(test_id).expect("Error getting statistic in last message: test id not found");
-                // Convert to from milliseconds to seconds
-                let end_time = Statistic::get_unix_timestamp() - (crate::WAIT_CONTROL_MESSAGE as f64 / 1000.0); // REMOVE THIS, if you remove the sleep in the sender, before sending last message, as well
-                measurement.last_packet_received = true;
-                measurement.statistic.set_test_duration(Some(measurement.start_time), Some(end_time));
-                measurement.statistic.calculate_statistics();
-                measurement.statistic.set_end_timestamp();
-                Err("LAST_MESSAGE_RECEIVED")
-            }
+    // Sums up the measurements of all connections, while keeping the statistic of every connection.
+    // The measurements exist for all connections of the test, but only the ones which received a datagram are summed up.
+    fn sum_measurements(&self, statistic: Statistic) -> Statistic {
+        self.measurements.iter().enumerate().filter(|(_, measurement)| measurement.first_packet_received).fold(statistic, |acc: Statistic, (connection_id, measurement)| acc + measurement.connection_statistic(connection_id))
+    }
+
+    // Reset msg_flags and msg_controllen fields, so the next GRO control message fits into the buffer
+    fn reset_msghdr(msghdr: &mut libc::msghdr, parameter: &Parameter) {
+        if parameter.socket_options.gro {
+            msghdr.msg_flags = 0;
+            msghdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
         }
     }
 
@@ -239,20 +335,16 @@ impl Receiver {
         // Drain completion queue events
         for cqe in cq {
             let amount_received_bytes = cqe.result();
-            let user_data = cqe.user_data();
+            let (socket_index, user_data) = decode_user_data(cqe.user_data());
             debug!("Received completion event with user_data: {}, and received bytes: {}", user_data, amount_received_bytes); 
 
             completion_count += parse_received_bytes(amount_received_bytes)?;
 
-            match self.handle_recvmsg_return(amount_received_bytes, None, user_data) {
-                Ok(_) => {},
-                Err("INIT_MESSAGE_RECEIVED") => { // Checking for INIT message, and returning the buffer index to the buffer ring
-                    index_pool.push(user_data as usize);
-                    continue;
-                },
-                Err(x) => return Err(x)
-            };
-
+            let amount_received_packets = self.handle_recvmsg_return(amount_received_bytes, None, user_data)?;
+            self.count_socket(socket_index, amount_received_packets, amount_received_bytes as usize);
+            if self.sockets.amount_sockets() > 1 {
+                self.uring_resubmit.push_back(socket_index);
+            }
             index_pool.push(user_data as usize);
         }
 
@@ -292,6 +384,12 @@ impl Receiver {
                 Err(x) => return Err(x)
             }
 
+            // The end of a TCP stream doesn't use a provided buffer
+            if self.parameter.transport == Transport::Tcp && amount_received_bytes == 0 {
+                self.stream_closed = true;
+                continue;
+            }
+
             // Create a msghdr from the provided buffer to better parse the received message
             let mut buf = unsafe {
                 bufs.get(cqe.flags(), usize::try_from(amount_received_bytes).unwrap())
@@ -343,6 +441,12 @@ impl Receiver {
 
             multishot_armed &= crate::io_uring::check_multishot_status(cqe.flags()); 
 
+            // The end of a TCP stream doesn't use a provided buffer
+            if self.parameter.transport == Transport::Tcp && amount_received_bytes == 0 {
+                self.stream_closed = true;
+                continue;
+            }
+
             // Get specific buffer from the buffer ring
             let buf = unsafe {
                 bufs.get(cqe.flags(), usize::try_from(amount_received_bytes).unwrap())
@@ -385,74 +489,60 @@ impl Receiver {
         Ok(multishot_armed)
     }
 
-    fn handle_recvmsg_return(&mut self, amount_received_bytes: i32,  msghdr: Option<&mut libc::msghdr>, msghdr_index: u64) -> Result<(), &'static str> {
+    // Returns the amount of received datagrams
+    fn handle_recvmsg_return(&mut self, amount_received_bytes: i32,  msghdr: Option<&mut libc::msghdr>, msghdr_index: u64) -> Result<u64, &'static str> {
+        self.idle_since = None;
+        if self.parameter.transport == Transport::Tcp {
+            self.stream_closed |= amount_received_bytes == 0;
+            self.count_stream_bytes(amount_received_bytes as usize);
+            return Ok(0);
+        }
+
         let msghdr = match msghdr {
             Some(msghdr) => msghdr,
             None => self.packet_buffer.get_msghdr_from_index(msghdr_index as usize).unwrap()
         };
 
-        let libc::iovec { iov_base, iov_len } = unsafe {*msghdr.msg_iov};
-        let buffer_pointer = unsafe {
-            // Get buffer from iov_base with type &[u8]
-            std::slice::from_raw_parts(iov_base as *const u8, iov_len )
-        };
-        
-        let test_id = MessageHeader::get_test_id(buffer_pointer) as usize;
-        let mtype = MessageHeader::get_message_type(buffer_pointer);
-
-        Self::parse_message_type(mtype, test_id, &mut self.measurements, &self.parameter)?;
+        let mut batch = CounterSnapshot::default();
+        let (absolut_packets_received, amount_foreign_packets) = util::process_packet_msghdr(msghdr, amount_received_bytes as usize, &mut self.measurements, &mut batch, &self.parameter);
+        self.counters.add_batch(&batch);
+        self.amount_foreign_datagrams += amount_foreign_packets;
+        Self::reset_msghdr(msghdr, &self.parameter);
 
-        let msghdr = match self.parameter.uring_parameter.uring_mode {
-            UringMode::Normal => self.packet_buffer.get_msghdr_from_index(msghdr_index as usize).unwrap(),
-            _ => msghdr
-        };
+        debug!("Received {} packets and total {} Bytes", absolut_packets_received, amount_received_bytes);
+        Ok(absolut_packets_received)
+    }
 
-        let statistic = &mut self.measurements.get_mut(test_id).expect("Error getting statistic: test id not found").statistic;
-        let absolut_packets_received;
-        (self.next_packet_id, absolut_packets_received) = util::process_packet_msghdr(msghdr, amount_received_bytes as usize, self.next_packet_id, statistic);
-        statistic.amount_datagrams += absolut_packets_received;
-        statistic.amount_data_bytes += amount_received_bytes as usize;
 
-        // Reset msg_flags and msg_controllen fields
-        if self.parameter.socket_options.gro {
-            msghdr.msg_flags = 0;
-            msghdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
+    fn create_io_uring(&self) -> Result<ReceiverUring, &'static str> {
+        match self.parameter.uring_parameter.uring_mode {
+            UringMode::Multishot => Ok(ReceiverUring::Multishot(IoUringMultishot::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?)),
+            UringMode::ProvidedBuffer => Ok(ReceiverUring::ProvidedBuffer(IoUringProvidedBuffer::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?)),
+            UringMode::Normal => Ok(ReceiverUring::Normal(IoUringNormal::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?)),
+            _ => {
+                error!("Invalid io_uring mode selected for receiver!");
+                Err("Invalid io_uring mode selected for receiver!")
+            }
         }
-
-        debug!("Received {} packets and total {} Bytes, and next packet id should be {}", absolut_packets_received, amount_received_bytes, self.next_packet_id);
-        Ok(())
     }
 
-
-    fn io_uring_loop(&mut self) -> Result<Statistic, &'static str> {
+    fn io_uring_loop(&mut self, io_uring: ReceiverUring) -> Result<Statistic, &'static str> {
+        // Provided buffers and multishot only support one socket per thread
         let socket_fd = self.socket.get_socket_id();
         let mut statistic = Statistic::new(self.parameter.clone());
         let mut amount_inflight = 0;
 
-        match self.parameter.uring_parameter.uring_mode {
-            UringMode::Multishot => {
-                let mut io_uring_instance = crate::io_uring::multishot::IoUringMultishot::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?;
+        match io_uring {
+            ReceiverUring::Multishot(mut io_uring_instance) => {
                 // Indicator if multishot request is still armed
                 let mut armed = false;
 
-                loop {
-                    statistic.amount_io_model_calls += 1;
+                let mut drained = false;
+                while !self.receiving_finished(drained) {
+                    let amount_datagrams = self.counters.datagrams().0;
+                    self.counters.add_io_model_calls(1);
                     io_uring_instance.fill_sq_and_submit(armed, socket_fd)?;
 
-                    // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
-                    if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
-                        let mut statistic_new = self.measurements.iter().fold(statistic.clone(), |acc: Statistic, measurement| acc + measurement.statistic.clone());
-                        statistic_new = statistic_new + io_uring_instance.get_statistic();
-                        self.statistic_interval.calculate_interval(statistic_new);
-
-                        // Reset measurements statistics
-                        for measurement in &mut self.measurements {
-                            measurement.statistic = Statistic::new(self.parameter.clone()); 
-                        }
-                        statistic = Statistic::new(self.parameter.clone());
-                        io_uring_instance.reset_statistic(self.parameter.clone());
-                    }
-
                     match self.io_uring_complete_multishot(&mut io_uring_instance) {
                         Ok(multishot_armed) => {
                             if !multishot_armed {
@@ -460,42 +550,27 @@ impl Receiver {
                             }
                             armed = multishot_armed
                         },
-                        Err("INIT_MESSAGE_RECEIVED") => {},
-                        Err("LAST_MESSAGE_RECEIVED") => {
-                            if self.all_measurements_finished() { return Ok(statistic + io_uring_instance.get_statistic()) }
-                        },
                         Err("EAGAIN") => {
-                            statistic.amount_eagain += 1;
+                            self.counters.add_eagain(1);
                         },
                         Err(x) => {
                             error!("Error completing io_uring sqe: {}", x);
                             return Err(x);
                         }
                     };
+                    drained = self.counters.datagrams().0 == amount_datagrams;
                 }
+                Ok(statistic + io_uring_instance.get_statistic())
             },
-            UringMode::ProvidedBuffer => {
-                let mut io_uring_instance: IoUringProvidedBuffer = crate::io_uring::provided_buffer::IoUringProvidedBuffer::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?;
+            ReceiverUring::ProvidedBuffer(mut io_uring_instance) => {
 
-                loop {
+                let mut drained = false;
+                while !self.receiving_finished(drained) {
+                    let amount_datagrams = self.counters.datagrams().0;
                     if let Some(ref mut array) = statistic.uring_inflight_utilization {
                         array[amount_inflight as usize] += 1;
                     }
-                    statistic.amount_io_model_calls += 1;
-
-                    // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
-                    if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
-                        let mut statistic_new = self.measurements.iter().fold(statistic.clone(), |acc: Statistic, measurement| acc + measurement.statistic.clone());
-                        statistic_new = statistic_new + io_uring_instance.get_statistic();
-                        self.statistic_interval.calculate_interval(statistic_new);
-
-                        // Reset measurements statistics
-                        for measurement in &mut self.measurements {
-                            measurement.statistic = Statistic::new(self.parameter.clone()); 
-                        }
-                        statistic = Statistic::new(self.parameter.clone());
-                        io_uring_instance.reset_statistic(self.parameter.clone());
-                    }
+                    self.counters.add_io_model_calls(1);
 
                     amount_inflight += io_uring_instance.fill_sq_and_submit(amount_inflight, socket_fd)?;
 
@@ -503,147 +578,197 @@ impl Receiver {
                         Ok(completed) => {
                             amount_inflight -= completed
                         },
-                        Err("INIT_MESSAGE_RECEIVED") => {},
-                        Err("LAST_MESSAGE_RECEIVED") => {
-                            if self.all_measurements_finished() { return Ok(statistic + io_uring_instance.get_statistic()) }
-                        },
                         Err("EAGAIN") => {
-                            statistic.amount_eagain += 1;
+                            self.counters.add_eagain(1);
                         },
                         Err(x) => {
                             error!("Error completing io_uring sqe: {}", x);
                             return Err(x);
                         }
                     };
+                    drained = self.counters.datagrams().0 == amount_datagrams;
                 }
+                Ok(statistic + io_uring_instance.get_statistic())
             },
-            UringMode::Normal => {
-                let mut io_uring_instance = crate::io_uring::normal::IoUringNormal::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?;
+            ReceiverUring::Normal(mut io_uring_instance) => {
+                self.sockets.set_ready();
 
-                loop {
+                let mut drained = false;
+                while !self.receiving_finished(drained) {
+                    let amount_datagrams = self.counters.datagrams().0;
                     if let Some(ref mut array) = statistic.uring_inflight_utilization {
                         array[amount_inflight as usize] += 1;
                     }
-                    statistic.amount_io_model_calls += 1;
-
-                    // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
-                    if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
-                        let mut statistic_new = self.measurements.iter().fold(statistic.clone(), |acc: Statistic, measurement| acc + measurement.statistic.clone());
-                        statistic_new = statistic_new + io_uring_instance.get_statistic();
-                        self.statistic_interval.calculate_interval(statistic_new);
-
-                        // Reset measurements statistics
-                        for measurement in &mut self.measurements {
-                            measurement.statistic = Statistic::new(self.parameter.clone()); 
-                        }
-                        statistic = Statistic::new(self.parameter.clone());
-                        io_uring_instance.reset_statistic(self.parameter.clone());
-                    }
+                    self.counters.add_io_model_calls(1);
 
-                    amount_inflight += io_uring_instance.fill_sq_and_submit(self.packet_buffer.get_pool_inflight() as u32, &mut self.packet_buffer, socket_fd)?;
+                    // All sockets of the thread share the io_uring instance, each completion is resubmitted to its socket
+                    amount_inflight += io_uring_instance.fill_sq_and_submit(self.packet_buffer.get_pool_inflight() as u32, &mut self.packet_buffer, &mut self.sockets, &mut self.uring_resubmit)?;
 
                     match self.io_uring_complete_normal(&mut io_uring_instance) {
                         Ok(completed) => {
                             amount_inflight -= completed
                         },
-                        Err("INIT_MESSAGE_RECEIVED") => {},
-                        Err("LAST_MESSAGE_RECEIVED") => {
-                            if self.all_measurements_finished() { return Ok(statistic + io_uring_instance.get_statistic()) } 
-                        },
                         Err("EAGAIN") => {
-                            statistic.amount_eagain += 1;
+                            self.counters.add_eagain(1);
                         },
                         Err(x) => {
                             error!("Error completing io_uring sqe: {}", x);
                             return Err(x);
                         }
                     };
+                    drained = self.counters.datagrams().0 == amount_datagrams;
                 }
-            },
-            _ => {
-                error!("Invalid io_uring mode selected for receiver!");
-                Err("Invalid io_uring mode selected for receiver!")
+                Ok(statistic + io_uring_instance.get_statistic())
             }
         }
     }
 
-    fn all_measurements_finished(&self) -> bool {
-        for measurement in self.measurements.iter() {
-            if !measurement.last_packet_received && measurement.first_packet_received {
-                debug!("{:?}: Last message received, but not all measurements are finished!", thread::current().id());
-                return false;
+    // The kernel copies the frames into the packet ring or the UMEM of the AF_XDP socket, so the receiver only parses the frames it hands over and waits with poll() for the next ones
+    fn frame_ring_loop(&mut self, statistic: Statistic) -> Result<Statistic, &'static str> {
+        let mut frame_ring = self.frame_ring.take().ok_or("Frame ring isn't set up")?;
+
+        loop {
+            let mut batch = CounterSnapshot::default();
+            let (measurements, parameter, amount_foreign_datagrams) = (&mut self.measurements, &self.parameter, &mut self.amount_foreign_datagrams);
+            let recv_result = frame_ring.recv(&mut |datagram| *amount_foreign_datagrams += util::process_packet_buffer(datagram, datagram.len(), measurements, &mut batch, parameter).1);
+            self.counters.add_batch(&batch);
+            match recv_result {
+                Ok(amount_frames) => {
+                    self.idle_since = None;
+                    debug!("Received {} frames", amount_frames);
+                },
+                Err("EAGAIN") => {
+                    // A partially filled block of a packet ring is handed over after its timeout, so the last datagrams arrive at most one timeout after the sender stopped
+                    let stopped = self.stop_flag.load(Ordering::Relaxed);
+                    if !stopped && self.idle_timeout_expired() {
+                        break;
+                    }
+                    let timeout = if stopped { 2 * crate::PACKET_RING_BLOCK_TIMEOUT as i32 } else { self.poll_timeout };
+                    self.counters.add_io_model_calls(1);
+                    self.counters.add_eagain(1);
+                    match frame_ring.wait(libc::POLLIN, timeout) {
+                        Ok(_) => {},
+                        Err("TIMEOUT") if stopped => break,
+                        Err("TIMEOUT") => {
+                            debug!("{:?}: Timeout waiting for a subsequent packet from the sender!", thread::current().id());
+                        },
+                        Err(x) => return Err(x)
+                    }
+                },
+                Err(x) => {
+                    error!("Error receiving frames! Aborting measurement...");
+                    return Err(x)
+                }
             }
         }
-        info!("{:?}: Last message received and all measurements are finished!", thread::current().id());
-        true
+        Ok(statistic)
     }
 
+    // Returns false, if the sender stopped the test or the start timeout expired before the first packet arrived on one of the sockets.
+    // The start timeout runs from the start of the measurement, so the handshake on the control channel isn't part of it.
+    fn wait_for_first_packet(&mut self) -> Result<bool, &'static str> {
+        loop {
+            // The IP stack drops frames with a local source address on the loopback interface after they were copied into a packet ring, and frames redirected by XDP never reach it
+            let wait_result = match self.frame_ring.as_ref() {
+                Some(frame_ring) => frame_ring.wait(libc::POLLIN, self.poll_timeout),
+                None => self.sockets.wait(IOModel::Poll, self.poll_timeout)
+            };
+            match wait_result {
+                Ok(_) => {
+                    self.socket = self.sockets.current();
+                    return Ok(true);
+                },
+                Err("TIMEOUT") => {
+                    if self.stop_flag.load(Ordering::Relaxed) {
+                        return Ok(false);
+                    }
+                    if self.parameter.receive_start_timeout != 0 && self.timer.measurement_elapsed_millis().is_some_and(|elapsed_millis| elapsed_millis >= self.parameter.receive_start_timeout) {
+                        self.termination_reason = TerminationReason::StartTimeout;
+                        return Ok(false);
+                    }
+                },
+                Err(x) => return Err(x)
+            }
+        }
+    }
 }
 
 
 impl Node for Receiver { 
-    fn run(&mut self, io_model: IOModel) -> Result<(Statistic, Vec<Statistic>), &'static str> {
+    fn run(&mut self, io_model: IOModel, start_barrier: &mut StartBarrier) -> Result<Statistic, &'static str> {
+        let io_uring = if io_model == IOModel::IoUring { Some(self.create_io_uring()?) } else { None };
+        start_barrier.wait();
+
         info!("Start receiver loop...");
         let mut statistic = Statistic::new(self.parameter.clone());
 
-        // Timeout waiting for first message 
-        // With communication channel in future, the measure thread is only started if the sender starts a measurement. Then timeout can be further reduced to 1-2s.
-        let mut pollfd = self.socket.create_pollfd(libc::POLLIN);
-        match self.socket.poll(&mut pollfd, INITIAL_POLL_TIMEOUT) {
-            Ok(_) => {},
-            Err("TIMEOUT") => {
-                // If port sharding is used, not every receiver thread gets packets due to the load balancing of REUSEPORT.
-                // To avoid that the thread waits forever, we need to return here.
-                warn!("{:?}: Timeout waiting for sender to send first packet!", thread::current().id());
-                return Ok((statistic, Vec::new()));
-            },
-            Err(x) => {
-                return Err(x);
+        // If port sharding is used, not every receiver thread gets packets due to the load balancing of REUSEPORT.
+        // Such a thread waits until the sender stops the test over the control channel.
+        if !self.wait_for_first_packet()? {
+            if self.termination_reason == TerminationReason::StartTimeout {
+                warn!("{:?}: No datagram received within {}ms after the start!", thread::current().id(), self.parameter.receive_start_timeout);
+            } else {
+                warn!("{:?}: Test stopped before the sender sent the first packet!", thread::current().id());
             }
-        };
-        statistic.start_timestamp = self.statistic_interval.last_send_timestamp;
-        self.statistic_interval.last_send_instant = Instant::now() + std::time::Duration::from_millis(crate::WAIT_CONTROL_MESSAGE);
-        self.statistic_interval.last_send_timestamp = Statistic::get_unix_timestamp() + (crate::WAIT_CONTROL_MESSAGE as f64 / 1000.0);
+            statistic.termination_reason = self.final_termination_reason();
+            return Ok(statistic);
+        }
 
-        if io_model == IOModel::IoUring {
-            statistic = self.io_uring_loop()?;
+        if self.parameter.transport == Transport::Tcp {
+            self.accept_connection()?;
+        }
+        start_barrier.start_measurement();
+        let start_timestamp = Statistic::get_unix_timestamp();
+
+        if let Some(io_uring) = io_uring {
+            statistic = self.io_uring_loop(io_uring)?;
+        } else if io_model == IOModel::PacketRing || io_model == IOModel::AfXdp {
+            statistic = self.frame_ring_loop(statistic)?;
         } else {
             loop {
-                statistic.amount_syscalls += 1;
-
-                // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
-                if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
-                    let statistic_new = self.measurements.iter().fold(statistic.clone(), |acc: Statistic, measurement| acc + measurement.statistic.clone());
-                    self.statistic_interval.calculate_interval(statistic_new);
-                    // Reset measurements statistics
-                    for measurement in &mut self.measurements {
-                        measurement.statistic = Statistic::new(self.parameter.clone()); 
-                    }
-                    statistic = Statistic::new(self.parameter.clone());
-                }
+                self.counters.add_syscalls(1);
 
                 match self.recv_messages() {
-                    Ok(_) => {},
+                    Ok(_) => self.idle_since = None,
+                    Err("EOF") => {
+                        debug!("{:?}: Sender closed the connection", thread::current().id());
+                        break;
+                    },
                     Err("EAGAIN") => {
-                        statistic.amount_io_model_calls += 1;
-                        statistic.amount_eagain += 1;
+                        if let Some(socket_statistic) = self.socket_statistics.get_mut(self.sockets.index()) {
+                            socket_statistic.amount_eagain += 1;
+                        }
+
+                        // Another socket of the thread may still have datagrams queued
+                        if self.sockets.set_blocked() {
+                            self.counters.add_eagain(1);
+                            self.socket = self.sockets.current();
+                            continue;
+                        }
+
+                        // All sockets are drained, so the thread can finish if the sender already stopped the test.
+                        // A TCP connection is drained until the sender closes it, or no more data arrives.
+                        let stopped = self.stop_flag.load(Ordering::Relaxed);
+                        if stopped && self.parameter.transport != Transport::Tcp {
+                            break;
+                        }
+                        if !stopped && self.idle_timeout_expired() {
+                            break;
+                        }
+                        self.counters.add_io_model_calls(1);
+                        self.counters.add_eagain(1);
                         match self.io_wait(io_model) {
+                            Ok(_) if self.sockets.amount_sockets() > 1 => self.socket = self.sockets.current(),
                             Ok(_) => {},
+                            Err("TIMEOUT") if stopped => break,
                             Err("TIMEOUT") => {
-                                // If port sharing is used, or single connection not every thread receives the LAST message. 
-                                // To avoid that the thread waits forever, we need to return here.
-                                warn!("{:?}: Timeout waiting for a subsequent packet from the sender!", thread::current().id());
-                                break;
+                                debug!("{:?}: Timeout waiting for a subsequent packet from the sender!", thread::current().id());
                             },
                             Err(x) => {
                                 return Err(x);
                             }
                         }
                     },
-                    Err("INIT_MESSAGE_RECEIVED") => {},
-                    Err("LAST_MESSAGE_RECEIVED") => {
-                        if self.all_measurements_finished() { break }
-                    },
                     Err(x) => {
                         error!("Error receiving message! Aborting measurement...");
                         return Err(x)
@@ -652,48 +777,31 @@ impl Node for Receiver {
             }
         }
 
+        // All threads stop at the same time after the sender stopped the test, so the hash mapping of SO_REUSEPORT doesn't change during the measurement
         if self.parameter.multiplex_port_receiver != MultiplexPort::Sharing {
-            // If a thread finishes (closes the socket) before the others, the hash mapping of SO_REUSEPORT changes. 
-            // Then all threads would receive packets from other connections (test_ids).
-            // Therefore, we need to wait a bit, until a thread closes its socket.
-            if self.parameter.multiplex_port_receiver == MultiplexPort::Sharding {
-                sleep(std::time::Duration::from_millis(crate::WAIT_CONTROL_MESSAGE * 2));
-            }
-            self.socket.close()?;
+            self.sockets.close()?;
         }
 
         debug!("{:?}: Finished receiving data from remote host", thread::current().id());
 
-        let mut final_statistic = Statistic::new(self.parameter.clone());
-        if self.statistic_interval.statistics.is_empty() {
-            final_statistic = self.measurements.iter().fold(statistic, |acc: Statistic, measurement| acc + measurement.statistic.clone());
-            final_statistic.set_test_duration(Some(self.statistic_interval.last_send_timestamp), Some(Statistic::get_unix_timestamp() - (crate::WAIT_CONTROL_MESSAGE as f64 / 1000.0)));
-        } else {
-            for statistic in self.statistic_interval.statistics.iter() {
-                final_statistic = final_statistic + statistic.clone();
-            }
-        }
+        // The measurements keep the statistics of the connections, while the counters hold the totals of the thread
+        let mut final_statistic = self.sum_measurements(statistic);
+        self.counters.sample().write_to(&mut final_statistic);
+        final_statistic.set_test_duration(Some(start_timestamp), Some(Statistic::get_unix_timestamp()));
 
-        final_statistic.set_test_duration(None, None);
+        if self.amount_foreign_datagrams > 0 {
+            warn!("{:?}: Dropped {} foreign datagrams without a valid header", thread::current().id(), self.amount_foreign_datagrams);
+        }
+        final_statistic.amount_foreign_datagrams += self.amount_foreign_datagrams;
+        final_statistic.sockets = self.socket_statistics.clone();
+        final_statistic.termination_reason = self.final_termination_reason();
         final_statistic.calculate_statistics();
 
-        Ok((final_statistic, self.statistic_interval.statistics.clone()))
+        Ok(final_statistic)
     }
 
     fn io_wait(&mut self, io_model: IOModel) -> Result<(), &'static str> {
-        // Normally we would need to iterate over FDs and check which socket is ready
-        // Since we only have one socket, we directly call recv_messages after io_wait returns
-        match io_model {
-            IOModel::Select => {
-                let mut read_fds: libc::fd_set = unsafe { self.socket.create_fdset() };
-                self.socket.select(Some(&mut read_fds), None, IN_MEASUREMENT_POLL_TIMEOUT)
-
-            },
-            IOModel::Poll => {
-                let mut pollfd = self.socket.create_pollfd(libc::POLLIN);
-                self.socket.poll(&mut pollfd, IN_MEASUREMENT_POLL_TIMEOUT)
-            },
-            _ => Ok(())
-        }
+        // Waits until one of the sockets of the thread is readable, which is received on next
+        self.sockets.wait(io_model, self.poll_timeout)
     }
 }
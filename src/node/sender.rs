@@ -1,92 +1,160 @@
-use std::net::SocketAddrV4;
+use std::net::{SocketAddr, SocketAddrV4};
 use std::os::fd::RawFd;
-use std::{thread::sleep, time::Instant};
+use std::sync::Arc;
 use log::{debug, trace, info, warn, error};
 
 use crate::io_uring::send::IoUringSend;
 use crate::io_uring::{check_multishot_status, IoUringOperatingModes, UringMode};
-use crate::net::{MessageHeader, MessageType, socket::Socket};
+use crate::net::{packet_ring::PacketRing, socket::Socket, socket_set::SocketSet, FrameRing};
+use crate::util::counters::ThreadCounters;
 use crate::util::msghdr_vec::MsghdrVec;
 use crate::util::packet_buffer::PacketBuffer;
-use crate::util::{self, ExchangeFunction, IOModel, statistic::*, msghdr::WrapperMsghdr};
-use super::Node;
+use crate::util::timer::TestTimer;
+use crate::util::{self, interrupt, ExchangeFunction, IOModel, Transport, statistic::*};
+use super::{Node, StartBarrier};
 
 pub struct Sender {
-    test_id: u64,
     packet_buffer: PacketBuffer,
+    // The socket of the set, which is currently sent on
     socket: Socket,
+    sockets: SocketSet,
     parameter: Parameter,
     io_uring_sqpoll_fd: Option<RawFd>,
+    packet_ring: Option<PacketRing>,
+    // Collects the statistics besides the counters, like the io_uring utilization
     statistic: Statistic,
-    statistic_interval: StatisticInterval,
-    run_time_length: u64,
+    counters: Arc<ThreadCounters>,
+    // Only used, if the thread drives several sockets
+    socket_statistics: Vec<SocketStatistic>,
+    // Ends the test after its duration, or if it was interrupted
+    timer: Arc<TestTimer>,
     next_packet_id: u64,
+    // Simulated connections of this thread with the next packet ID of each. The packet buffer is filled for the connection at connection_index.
+    connections: Vec<(u64, u64)>,
+    connection_index: usize,
+    // Bytes sent over a TCP stream, to count the datagrams
+    stream_bytes: u64,
     exchange_function: ExchangeFunction,
 }
 
 impl Sender {
-    pub fn new(test_id: u64, local_port: Option<u16>, sock_address_out: SocketAddrV4, socket: Option<Socket>, io_uring: Option<RawFd>, parameter: Parameter) -> Self {
-        let socket = if socket.is_none() {
-            let mut socket: Socket = Socket::new(parameter.socket_options).expect("Error creating socket");
-            if let Some(port) = local_port {
-                socket.bind(SocketAddrV4::new(crate::DEFAULT_SENDER_IP, port)).expect("Error binding socket");
-            }
-            socket.connect(sock_address_out).expect("Error connecting to remote host");
-            socket
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(test_id: u64, local_port: Option<u16>, sock_address_out: SocketAddr, socket: Option<Socket>, io_uring: Option<RawFd>, timer: Arc<TestTimer>, counters: Arc<ThreadCounters>, parameter: Parameter) -> Result<Self, &'static str> {
+        let parameter = parameter.configure_sending();
+        let local_ip = parameter.source_ip.unwrap_or(crate::net::unspecified_address(&sock_address_out.ip()));
+        let sockets: Vec<Socket> = if socket.is_none() {
+            // Every socket of the thread sends to its own port of the receiver
+            (0..parameter.sockets_per_thread).map(|index| {
+                let mut socket: Socket = Socket::new(sock_address_out.ip(), parameter.transport, parameter.socket_options, parameter.interface.as_deref()).ok_or("Error creating socket")?;
+                if local_port.is_some() || parameter.source_ip.is_some() {
+                    socket.bind(SocketAddr::new(local_ip, local_port.unwrap_or(0)))?;
+                }
+                socket.connect(SocketAddr::new(sock_address_out.ip(), sock_address_out.port() + index))?;
+                Ok(socket)
+            }).collect::<Result<_, &'static str>>()?
         } else {
             let mut socket = socket.unwrap();
             socket.set_sock_addr_out(sock_address_out); // Set socket address out for the remote host
-            socket
+            vec![socket]
         };
 
-        info!("Current mode 'sender' sending to remote host {}:{} from {}:{} with test ID {} on socketID {}", sock_address_out.ip(), sock_address_out.port(), crate::DEFAULT_SENDER_IP, local_port.unwrap_or(0), test_id, socket.get_socket_id());
+        for (index, socket) in sockets.iter().enumerate() {
+            info!("Current mode 'sender' sending to remote host {} from {} with test ID {} on socketID {}", SocketAddr::new(sock_address_out.ip(), sock_address_out.port() + index as u16), SocketAddr::new(local_ip, local_port.unwrap_or(0)), test_id, socket.get_socket_id());
+        }
+        let socket_statistics = if sockets.len() > 1 {
+            (0..sockets.len()).map(|index| SocketStatistic { port: sock_address_out.port() + index as u16, ..Default::default() }).collect()
+        } else {
+            Vec::new()
+        };
+        let socket = sockets[0];
 
-        let packet_buffer = Self::create_packet_buffer(&parameter, test_id, &socket); 
+        let connections: Vec<(u64, u64)> = util::thread_connections(test_id, parameter.amount_threads, parameter.amount_connections).into_iter().map(|connection_id| (connection_id, 0)).collect();
+        debug!("Thread with test ID {} sends on connections {:?}", test_id, connections.iter().map(|(connection_id, _)| connection_id).collect::<Vec<_>>());
+
+        let mut packet_buffer = Self::create_packet_buffer(&parameter, test_id, &socket); 
+        packet_buffer.set_connection_id(connections[0].0);
+
+        let packet_ring = if parameter.io_model == IOModel::PacketRing {
+            Some(Self::create_packet_ring(&parameter, &socket, sock_address_out, &mut packet_buffer)?)
+        } else {
+            None
+        };
 
-        Sender {
-            test_id,
+        Ok(Sender {
             packet_buffer,
             socket,
+            sockets: SocketSet::new(sockets, libc::POLLOUT, parameter.epoll_trigger, parameter.epoll_exclusive),
             parameter: parameter.clone(),
             io_uring_sqpoll_fd: io_uring,
+            packet_ring,
             statistic: Statistic::new(parameter.clone()),
-            statistic_interval: StatisticInterval::new(Instant::now(), parameter.output_interval, parameter.test_runtime_length),
-            run_time_length: parameter.test_runtime_length,
+            counters,
+            socket_statistics,
+            timer,
             next_packet_id: 0,
+            connections,
+            connection_index: 0,
+            stream_bytes: 0,
             exchange_function: parameter.exchange_function
-        }
+        })
     }
 
-    fn send_control_message(&mut self, mtype: MessageType) -> Result<(), &'static str> {
-        let header = MessageHeader::new(mtype, self.test_id, 0);
-        debug!("Coordination message: {:?}", header);
-
-        let packet_buffer = WrapperMsghdr::new(header.len() as u32, header.len() as u32);
-
-        if let Some(mut packet_buffer) = packet_buffer {
-            packet_buffer.copy_buffer(header.serialize());
-            let sockaddr = self.socket.get_sockaddr_out().unwrap();
-            packet_buffer.set_address(sockaddr);
-            let msghdr = packet_buffer.get_msghdr();
-
-            match self.socket.sendmsg(msghdr) {
-                Ok(_) => { Ok(()) },
-                Err("ECONNREFUSED") => Err("Start the receiver first! Abort measurement..."),
-                Err(x) => Err(x)
-            }
+    // TCP may accept only a part of the buffer, so its datagrams are derived from the byte stream
+    fn count_sent_bytes(&mut self, amount_datagrams: u64, amount_sent_bytes: usize) {
+        let amount_datagrams = if self.parameter.transport == Transport::Tcp {
+            util::count_stream_datagrams(&mut self.stream_bytes, amount_sent_bytes, self.packet_buffer.datagram_size())
         } else {
-            Err("Error creating buffer")
+            amount_datagrams
+        };
+        self.counters.add_datagrams(amount_datagrams, amount_sent_bytes);
+    }
+
+    // Accounts the datagrams sent on a socket, if the thread drives several sockets
+    fn count_socket(&mut self, socket_index: usize, amount_datagrams: u64, amount_sent_bytes: usize) {
+        if let Some(socket_statistic) = self.socket_statistics.get_mut(socket_index) {
+            socket_statistic.amount_datagrams += amount_datagrams;
+            socket_statistic.amount_data_bytes += amount_sent_bytes;
         }
     }
 
     fn send_messages(&mut self) -> Result<(), &'static str> {
+        let (amount_datagrams, amount_sent_bytes) = self.counters.datagrams();
         match self.exchange_function {
             ExchangeFunction::Normal => self.send(),
             ExchangeFunction::Msg => self.sendmsg(),
             ExchangeFunction::Mmsg => self.sendmmsg(),
+        }?;
+
+        if !self.socket_statistics.is_empty() {
+            let (amount_datagrams_now, amount_sent_bytes_now) = self.counters.datagrams();
+            self.count_socket(self.sockets.index(), amount_datagrams_now - amount_datagrams, (amount_sent_bytes_now - amount_sent_bytes) as usize);
+        }
+        self.next_connection();
+        self.next_socket();
+        Ok(())
+    }
+
+    // Every send call uses the next writable socket of the thread
+    fn next_socket(&mut self) {
+        if self.sockets.amount_sockets() > 1 {
+            self.sockets.rotate();
+            self.socket = self.sockets.current();
         }
     }
 
+    // Every send call uses the next connection of the thread, like a QUIC server serving several connections over one socket
+    fn next_connection(&mut self) {
+        if self.connections.len() <= 1 {
+            return;
+        }
+
+        self.connections[self.connection_index].1 = self.next_packet_id;
+        self.connection_index = (self.connection_index + 1) % self.connections.len();
+        let connection_id;
+        (connection_id, self.next_packet_id) = self.connections[self.connection_index];
+        self.packet_buffer.set_connection_id(connection_id);
+    }
+
     fn send(&mut self) -> Result<(), &'static str> {
         let amount_datagrams = self.packet_buffer.add_packet_ids(self.next_packet_id, None)?;
         self.next_packet_id += amount_datagrams;
@@ -98,8 +166,7 @@ impl Sender {
         match self.socket.send(buffer_pointer , buffer_length) {
             Ok(amount_send_bytes) => {
                 // For UDP, either the whole datagram is sent or nothing (due to an error e.g. full buffer). So we can assume that the whole datagram was sent.
-                self.statistic.amount_datagrams += amount_datagrams;
-                self.statistic.amount_data_bytes += amount_send_bytes;
+                self.count_sent_bytes(amount_datagrams, amount_send_bytes);
                 trace!("Sent datagram to remote host");
                 Ok(())
             },
@@ -123,8 +190,7 @@ impl Sender {
         match self.socket.sendmsg(msghdr) {
             Ok(amount_sent_bytes) => {
                 // Since we are using UDP, we can assume that the whole datagram was sent like in send().
-                self.statistic.amount_datagrams += amount_datagrams;
-                self.statistic.amount_data_bytes += amount_sent_bytes;
+                self.count_sent_bytes(amount_datagrams, amount_sent_bytes);
                 trace!("Sent datagram to remote host");
                 Ok(())
             },
@@ -153,8 +219,7 @@ impl Sender {
                     let amount_not_sent_packets = (self.packet_buffer.mmsghdr_vec.len() - amount_sent_mmsghdr) * amount_packets_per_msghdr;
                     self.next_packet_id -= amount_not_sent_packets as u64;
                 }
-                self.statistic.amount_datagrams += (amount_sent_mmsghdr * amount_packets_per_msghdr) as u64;
-                self.statistic.amount_data_bytes += util::get_total_bytes(&self.packet_buffer.mmsghdr_vec, amount_sent_mmsghdr);
+                self.counters.add_datagrams((amount_sent_mmsghdr * amount_packets_per_msghdr) as u64, util::get_total_bytes(&self.packet_buffer.mmsghdr_vec, amount_sent_mmsghdr));
                 trace!("Sent {} msg_hdr to remote host", amount_sent_mmsghdr);
                 Ok(())
             },
@@ -172,14 +237,31 @@ impl Sender {
         let mut packet_buffer = MsghdrVec::new(parameter.packet_buffer_size, parameter.mss, parameter.datagram_size as usize).with_random_payload().with_message_header(test_id);
 
         if parameter.multiplex_port == MultiplexPort::Sharing && parameter.multiplex_port_receiver == MultiplexPort::Individual {
-            if let Some(sockaddr) = socket.get_sockaddr_out() {
-                packet_buffer = packet_buffer.with_target_address(sockaddr);
+            if let Some((sockaddr, sockaddr_len)) = socket.get_sockaddr_out() {
+                packet_buffer = packet_buffer.with_target_address(sockaddr, sockaddr_len);
             } 
         }
 
         PacketBuffer::new(packet_buffer)
     }
 
+    // The frames are sent from the address of the connected UDP socket, which keeps the source port reserved
+    fn create_packet_ring(parameter: &Parameter, socket: &Socket, sock_address_out: SocketAddr, packet_buffer: &mut PacketBuffer) -> Result<PacketRing, &'static str> {
+        let (SocketAddr::V4(source), SocketAddr::V4(destination)) = (socket.get_local_address()?, sock_address_out) else {
+            return Err("Packet rings only support IPv4");
+        };
+        // Like connect(), the frames address the local host instead of the unspecified address
+        let destination = if destination.ip().is_unspecified() { SocketAddrV4::new(*source.ip(), destination.port()) } else { destination };
+        let interface = match parameter.interface.as_deref() {
+            Some(interface) => interface,
+            None if destination.ip().is_loopback() => "lo",
+            None => return Err("Packet rings need the interface to send on, set it with --interface")
+        };
+
+        let datagram_size = packet_buffer.datagram_size();
+        PacketRing::new_tx(interface, source, destination, &packet_buffer.get_buffer_pointer_from_index(0)?[..datagram_size])
+    }
+
     fn io_uring_complete_send(&mut self, io_uring_instance: &mut IoUringSend) -> Result<usize, &'static str> {
         let mut completion_count = 0;
         let amount_datagrams = self.packet_buffer.packets_amount_per_msghdr() as u64;
@@ -203,7 +285,7 @@ impl Sender {
                     // If no messages are available at the socket, the receive calls wait for a message to arrive, unless the socket is nonblocking (see fcntl(2)), in which case the value -11 is returned and the external variable errno is set to EAGAIN or EWOULDBLOCK.
                     // From: https://linux.die.net/man/2/recvmsg
                     debug!("EAGAIN: No messages can be send at the socket!"); // This should not happen in io_uring with FAST_POLL
-                    self.statistic.amount_omitted_datagrams += amount_datagrams as i64; // Currently we don't resend the packets
+                    self.counters.add_omitted(amount_datagrams as i64); // Currently we don't resend the packets
                 },
                 -111 => { // libc::ECONNREFUSED == 111
                     return Err("Start the receiver first! Abort measurement...");
@@ -213,8 +295,8 @@ impl Sender {
                     return Err("Failed to receive data!")
                 },
                 _ => { // Positive amount of bytes received
-                    self.statistic.amount_datagrams += amount_datagrams;
-                    self.statistic.amount_data_bytes += amount_bytes as usize;
+                    self.count_sent_bytes(amount_datagrams, amount_bytes as usize);
+                    self.count_socket(crate::io_uring::decode_user_data(user_data).0, amount_datagrams, amount_bytes as usize);
                     completion_count += 1;
                     trace!("Sent datagram to remote host");
                 }
@@ -241,7 +323,7 @@ impl Sender {
         // Drain completion queue events
         for cqe in cq {
             let amount_bytes = cqe.result();
-            let user_data = cqe.user_data();
+            let (socket_index, user_data) = crate::io_uring::decode_user_data(cqe.user_data());
             debug!("Received completion event with user_data: {}, and received bytes: {}", user_data, amount_bytes); 
 
             match amount_bytes {
@@ -250,7 +332,7 @@ impl Sender {
                     // From: https://linux.die.net/man/2/recvmsg
                     debug!("EAGAIN: No messages can be send at the socket!"); // This should not happen in io_uring with FAST_POLL
                     index_pool.push(user_data as usize);
-                    self.statistic.amount_omitted_datagrams += amount_datagrams as i64; // Currently we don't resend the packets
+                    self.counters.add_omitted(amount_datagrams as i64); // Currently we don't resend the packets
                 },
                 -111 => { // libc::ECONNREFUSED == 111
                     return Err("Start the receiver first! Abort measurement...");
@@ -279,8 +361,8 @@ impl Sender {
                     }
                 },
                 _ => { // Positive amount of bytes received
-                    self.statistic.amount_datagrams += amount_datagrams;
-                    self.statistic.amount_data_bytes += amount_bytes as usize;
+                    self.count_sent_bytes(amount_datagrams, amount_bytes as usize);
+                    self.count_socket(socket_index, amount_datagrams, amount_bytes as usize);
                     trace!("Sent datagram to remote host");
                 }
             }
@@ -294,37 +376,80 @@ impl Sender {
     }
 
 
-    fn io_uring_loop(&mut self, start_time: Instant) -> Result<(), &'static str> {
-        let socket_fd = self.socket.get_socket_id();
+    // The frames are prebuilt in the TX ring, so every round only writes the packet IDs into the free slots and hands them to the kernel with one sendto() call
+    fn packet_ring_loop(&mut self) -> Result<(), &'static str> {
+        let mut packet_ring = self.packet_ring.take().ok_or("Packet ring isn't set up")?;
+        let datagram_size = self.packet_buffer.datagram_size();
+
+        while !self.timer.expired() {
+            // Like sendmmsg, the packet buffer size sets how many frames are handed to the kernel at once
+            match packet_ring.send(self.next_packet_id, self.connections[self.connection_index].0, self.parameter.packet_buffer_size) {
+                Ok((amount_filled_frames, amount_sent_frames)) => {
+                    self.next_packet_id += amount_filled_frames;
+                    self.count_sent_bytes(amount_sent_frames, amount_sent_frames as usize * datagram_size);
+                    self.next_connection();
+                },
+                Err("EAGAIN") => {
+                    // The kernel frees the slots after the frames left the interface
+                    self.counters.add_eagain(1);
+                    self.counters.add_io_model_calls(1);
+                    match packet_ring.wait(libc::POLLOUT, crate::PACKET_RING_BLOCK_TIMEOUT as i32) {
+                        Ok(_) | Err("TIMEOUT") => {},
+                        Err(x) => return Err(x)
+                    }
+                },
+                Err(x) => {
+                    error!("Error sending frames! Aborting measurement...");
+                    return Err(x)
+                }
+            }
+            self.counters.add_syscalls(1);
+        }
+        Ok(())
+    }
+
+    // Waits for the other threads of the test, so all threads start sending at the same instant.
+    // In reverse and bidirectional mode, the threads are set up before the measuring side opens the measurement window, so they wait for it as well.
+    fn start_measurement(&mut self, start_barrier: &mut StartBarrier) {
+        start_barrier.wait();
+        if self.parameter.reverse {
+            self.timer.wait_for_measurement();
+        }
+        info!("Start measurement...");
+        start_barrier.start_measurement();
+        self.statistic.start_timestamp = Statistic::get_unix_timestamp();
+    }
+
+    fn io_uring_loop(&mut self, start_barrier: &mut StartBarrier) -> Result<(), &'static str> {
         let uring_mode = self.parameter.uring_parameter.uring_mode;
         let mut amount_inflight: usize = 0;
 
         match uring_mode {
             UringMode::Normal | UringMode::Zerocopy => {
                 let mut io_uring_instance = crate::io_uring::send::IoUringSend::new(self.parameter.clone(), self.io_uring_sqpoll_fd)?;
+                self.start_measurement(start_barrier);
 
-                while start_time.elapsed().as_secs() < self.run_time_length {
+                while !self.timer.expired() {
                     if let Some(ref mut array) = self.statistic.uring_inflight_utilization {
                         array[amount_inflight] += 1;
                     }
-                    self.statistic.amount_io_model_calls += 1;
-
-                    // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
-                    if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval  {
-                        self.statistic_interval.calculate_interval(self.statistic.clone());
-                        self.statistic = Statistic::new(self.parameter.clone());
-                    }
+                    self.counters.add_io_model_calls(1);
 
-                    let submitted = io_uring_instance.fill_sq_and_submit(self.packet_buffer.get_pool_inflight(), &mut self.packet_buffer, self.next_packet_id, socket_fd)?;
+                    // All sockets of the thread share the io_uring instance, each submission goes to the next socket
+                    let submitted = io_uring_instance.fill_sq_and_submit(self.packet_buffer.get_pool_inflight(), &mut self.packet_buffer, self.next_packet_id, self.socket.get_socket_id(), self.sockets.index())?;
                     amount_inflight += submitted;
                     self.next_packet_id += (submitted * self.packet_buffer.packets_amount_per_msghdr()) as u64;
+                    if submitted > 0 {
+                        self.next_connection();
+                        self.next_socket();
+                    }
 
                     match if uring_mode == UringMode::Zerocopy { self.io_uring_complete_send_zc(&mut io_uring_instance) } else { self.io_uring_complete_send(&mut io_uring_instance) } {
                         Ok(completed) => {
                             amount_inflight -= completed
                         },
                         Err("EAGAIN") => {
-                            self.statistic.amount_eagain += 1;
+                            self.counters.add_eagain(1);
                         },
                         Err(x) => {
                             error!("Error completing io_uring sqe: {}", x);
@@ -341,89 +466,69 @@ impl Sender {
 
 
 impl Node for Sender {
-    fn run(&mut self, io_model: IOModel) -> Result<(Statistic, Vec<Statistic>), &'static str> {
+    fn run(&mut self, io_model: IOModel, start_barrier: &mut StartBarrier) -> Result<Statistic, &'static str> {
         if self.parameter.multiplex_port != MultiplexPort::Sharing {
             if let Ok(mss) = self.socket.get_mss() {
                 info!("On the current socket the MSS is {}", mss);
             }
         }
-        
-        self.send_control_message(MessageType::INIT)?;
-        // Wait some time to ensure the receiver is ready to receive messages
-        sleep(std::time::Duration::from_millis(crate::WAIT_CONTROL_MESSAGE));
-
-        info!("Start measurement...");
-        let start_time = Instant::now();
-        self.statistic_interval.start(None);
 
         if io_model == IOModel::IoUring {
-            self.io_uring_loop(start_time)?;
+            self.io_uring_loop(start_barrier)?;
+        } else if io_model == IOModel::PacketRing {
+            self.start_measurement(start_barrier);
+            self.packet_ring_loop()?;
         } else {
+            self.start_measurement(start_barrier);
 
-            while start_time.elapsed().as_secs() < self.run_time_length {
-                // Check if the time elapsed since the last send operation is greater than or equal to self.parameters.interval seconds
-                if self.statistic_interval.output_interval != 0.0 && self.statistic_interval.last_send_instant.elapsed().as_secs_f64() >= self.statistic_interval.output_interval {
-                    self.statistic_interval.calculate_interval(self.statistic.clone());
-                    self.statistic = Statistic::new(self.parameter.clone());
-                }
-
+            while !self.timer.expired() {
                 match self.send_messages() {
                     Ok(_) => {},
                     Err("EAGAIN") => {
-                        self.statistic.amount_io_model_calls += 1;
-                        self.statistic.amount_eagain += 1;
-                        self.io_wait(io_model)?;
+                        self.counters.add_eagain(1);
+                        if let Some(socket_statistic) = self.socket_statistics.get_mut(self.sockets.index()) {
+                            socket_statistic.amount_eagain += 1;
+                        }
+
+                        // Another socket of the thread may still be writable
+                        if !self.sockets.set_blocked() {
+                            self.counters.add_io_model_calls(1);
+                            self.io_wait(io_model)?;
+                        }
+                        if self.sockets.amount_sockets() > 1 {
+                            self.socket = self.sockets.current();
+                        }
                     },
                     Err(x) => {
                         error!("Error sending message! Aborting measurement...");
                         return Err(x)
                     }
                 }
-                self.statistic.amount_syscalls += 1;
+                self.counters.add_syscalls(1);
             }
         }
 
-        // Print last interval
-        if self.statistic_interval.output_interval != 0.0 && !self.statistic_interval.finished() {
-            self.statistic_interval.calculate_interval(self.statistic.clone());
+        // A shared socket is closed by the executor after all threads finished
+        if self.parameter.multiplex_port != MultiplexPort::Sharing {
+            self.sockets.close()?;
         }
 
-        // Fold over all interval statistics to get the final statistic
-        let mut final_statistic = Statistic::new(self.parameter.clone());
-
-        if self.statistic_interval.statistics.is_empty() {
-            final_statistic = self.statistic.clone();
-            final_statistic.set_test_duration(Some(self.statistic_interval.last_send_timestamp), Some(Statistic::get_unix_timestamp()));
-        } else {
-            for statistic in self.statistic_interval.statistics.iter() {
-                final_statistic = final_statistic + statistic.clone();
-            }
+        let mut final_statistic = self.statistic.clone();
+        self.counters.sample().write_to(&mut final_statistic);
+        final_statistic.set_test_duration(None, Some(Statistic::get_unix_timestamp()));
+        final_statistic.sockets = self.socket_statistics.clone();
+        if interrupt::interrupted() {
+            final_statistic.termination_reason = TerminationReason::Interrupted;
         }
-
-        // Ensures that the buffers are empty again, so that the last message actually arrives at the receiver
-        sleep(std::time::Duration::from_millis(crate::WAIT_CONTROL_MESSAGE));
-        self.send_control_message(MessageType::LAST)?;
-
-        final_statistic.set_test_duration(None, None);
         final_statistic.calculate_statistics();
 
-        Ok((final_statistic, self.statistic_interval.statistics.clone()))
+        Ok(final_statistic)
     }
 
     fn io_wait(&mut self, io_model: IOModel) -> Result<(), &'static str> {
-        // Normally we would need to iterate over FDs and check which socket is ready
-        // Since we only have one socket, we directly call send_messages after io_wait returns
-        match io_model {
-            IOModel::Select => {
-                let mut write_fds: libc::fd_set = unsafe { self.socket.create_fdset() };
-                self.socket.select(None, Some(&mut write_fds), -1)
-
-            },
-            IOModel::Poll => {
-                let mut pollfd = self.socket.create_pollfd(libc::POLLOUT);
-                self.socket.poll(&mut pollfd, -1)
-            }
-            _ => Ok(())
-        }
+        // AF_XDP only exists on the receiving side, so the sender waits with poll() for its sockets
+        let io_model = if io_model == IOModel::AfXdp { IOModel::Poll } else { io_model };
+        // Waits until one of the sockets of the thread is writable, which is sent on next
+        self.sockets.wait(io_model, -1)
     }
 }
\ No newline at end of file
@@ -62,7 +62,8 @@ impl CoreAffinityManager {
         }
     }
 
-    pub fn set_affinity(&mut self) -> Result<(), &'static str> {
+    // Returns the core the thread was bound to
+    pub fn set_affinity(&mut self) -> Result<usize, &'static str> {
         let mut core_id = self.get_core_id();
 
         core_id = if self.numa_affinity {
@@ -87,7 +88,7 @@ impl CoreAffinityManager {
         info!("Binding thread {:?} to core ID: {}", thread::current().id(), core_id);
         let mut core_cpuset = CpuSet::new();
         core_cpuset.set(core_id);
-        self.bind_to_cpuset(core_cpuset)
+        self.bind_to_cpuset(core_cpuset).map(|_| core_id)
     }
 
     fn get_core_id(&mut self) -> usize {
@@ -0,0 +1,233 @@
+use std::ops::{Add, Sub};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::cpu_util::ThreadCpuTime;
+use super::statistic::Statistic;
+
+// Hot counters of a node thread, which the interval reporter samples while the test is running.
+// Only the node thread writes them, so adding is a plain load and store without a locked instruction.
+// Every thread has its counters on its own cache line, so the threads don't slow each other down.
+// The counters also carry the tags of the thread, so its interval and final statistics can be told apart from the other threads.
+#[repr(align(64))]
+#[derive(Debug, Default)]
+pub struct ThreadCounters {
+    thread_index: u64,
+    test_id: u64,
+    port: u16,
+    // Set once by the thread, after it was bound to its core
+    core: OnceLock<usize>,
+    // getrusage() only measures the calling thread, so the node thread publishes its CPU time, when the interval reporter requests it.
+    // Every loop of the nodes counts its syscalls or IO model calls with each iteration, which answers a pending request.
+    cpu_time: Mutex<ThreadCpuTime>,
+    cpu_time_requested: AtomicBool,
+    // Set, after the thread published its final CPU time
+    finished: AtomicBool,
+    amount_datagrams: AtomicU64,
+    amount_data_bytes: AtomicU64,
+    amount_syscalls: AtomicU64,
+    amount_io_model_calls: AtomicU64,
+    amount_eagain: AtomicU64,
+    amount_omitted_datagrams: AtomicI64,
+    amount_reordered_datagrams: AtomicU64,
+    amount_duplicated_datagrams: AtomicU64,
+}
+
+// Values of the counters at one point in time
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CounterSnapshot {
+    pub amount_datagrams: u64,
+    pub amount_data_bytes: u64,
+    pub amount_syscalls: u64,
+    pub amount_io_model_calls: u64,
+    pub amount_eagain: u64,
+    pub amount_omitted_datagrams: i64,
+    pub amount_reordered_datagrams: u64,
+    pub amount_duplicated_datagrams: u64,
+}
+
+#[inline(always)]
+fn add(counter: &AtomicU64, value: u64) {
+    counter.store(counter.load(Ordering::Relaxed) + value, Ordering::Relaxed);
+}
+
+impl ThreadCounters {
+    pub fn new(thread_index: u64, test_id: u64, port: u16) -> ThreadCounters {
+        ThreadCounters {
+            thread_index,
+            test_id,
+            port,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_core(&self, core: usize) {
+        let _ = self.core.set(core);
+    }
+
+    // Called by the node thread itself before it's released, so the interval reporter has a first sample of its CPU time
+    pub fn register_thread(&self) {
+        self.publish_cpu_time();
+    }
+
+    // Called by the node thread after its measurement, so the last interval gets the CPU time until the thread ended
+    pub fn record_final_cpu_time(&self) {
+        self.publish_cpu_time();
+        self.finished.store(true, Ordering::Release);
+    }
+
+    fn publish_cpu_time(&self) {
+        *self.cpu_time.lock().unwrap() = ThreadCpuTime::current();
+        self.cpu_time_requested.store(false, Ordering::Release);
+    }
+
+    #[inline(always)]
+    fn answer_cpu_time_request(&self) {
+        if self.cpu_time_requested.load(Ordering::Relaxed) {
+            self.publish_cpu_time();
+        }
+    }
+
+    // Called by the interval reporter at an interval boundary
+    pub fn request_cpu_time(&self) {
+        if !self.finished.load(Ordering::Acquire) {
+            self.cpu_time_requested.store(true, Ordering::Release);
+        }
+    }
+
+    // Waits until the thread answered the request or the deadline passed. A thread blocked in its IO model uses hardly any CPU time,
+    // so its last published CPU time is taken instead, and the rest is counted in the next interval.
+    pub fn cpu_time(&self, deadline: Instant) -> ThreadCpuTime {
+        while self.cpu_time_requested.load(Ordering::Acquire) && !self.finished.load(Ordering::Acquire) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_micros(100));
+        }
+        *self.cpu_time.lock().unwrap()
+    }
+
+    // Marks the statistic as the one of this thread
+    pub fn tag(&self, statistic: &mut Statistic) {
+        statistic.thread_index = Some(self.thread_index);
+        statistic.test_id = Some(self.test_id);
+        statistic.port = Some(self.port);
+        statistic.core = self.core.get().copied();
+    }
+
+    #[inline(always)]
+    pub fn add_datagrams(&self, amount_datagrams: u64, amount_data_bytes: usize) {
+        add(&self.amount_datagrams, amount_datagrams);
+        add(&self.amount_data_bytes, amount_data_bytes as u64);
+    }
+
+    #[inline(always)]
+    pub fn add_syscalls(&self, amount_syscalls: u64) {
+        add(&self.amount_syscalls, amount_syscalls);
+        self.answer_cpu_time_request();
+    }
+
+    #[inline(always)]
+    pub fn add_io_model_calls(&self, amount_io_model_calls: u64) {
+        add(&self.amount_io_model_calls, amount_io_model_calls);
+        self.answer_cpu_time_request();
+    }
+
+    #[inline(always)]
+    pub fn add_eagain(&self, amount_eagain: u64) {
+        add(&self.amount_eagain, amount_eagain);
+    }
+
+    // A reordered datagram was counted as omitted before, so the amount of omitted datagrams shrinks again
+    #[inline(always)]
+    pub fn add_omitted(&self, amount_omitted_datagrams: i64) {
+        self.amount_omitted_datagrams.store(self.amount_omitted_datagrams.load(Ordering::Relaxed) + amount_omitted_datagrams, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn add_reordered(&self, amount_reordered_datagrams: u64) {
+        add(&self.amount_reordered_datagrams, amount_reordered_datagrams);
+    }
+
+    #[inline(always)]
+    pub fn add_duplicated(&self, amount_duplicated_datagrams: u64) {
+        add(&self.amount_duplicated_datagrams, amount_duplicated_datagrams);
+    }
+
+    // The datagrams of a receive call are counted for their connections and in a local batch first, so the counters are written once per call
+    #[inline(always)]
+    pub fn add_batch(&self, batch: &CounterSnapshot) {
+        self.add_datagrams(batch.amount_datagrams, batch.amount_data_bytes as usize);
+        self.add_omitted(batch.amount_omitted_datagrams);
+        self.add_reordered(batch.amount_reordered_datagrams);
+        self.add_duplicated(batch.amount_duplicated_datagrams);
+        self.answer_cpu_time_request();
+    }
+
+    // Amount of datagrams and bytes counted so far
+    #[inline(always)]
+    pub fn datagrams(&self) -> (u64, u64) {
+        (self.amount_datagrams.load(Ordering::Relaxed), self.amount_data_bytes.load(Ordering::Relaxed))
+    }
+
+    // The counters are sampled one by one, so a sample taken during the test may be off by the last send or receive call between them
+    pub fn sample(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            amount_datagrams: self.amount_datagrams.load(Ordering::Relaxed),
+            amount_data_bytes: self.amount_data_bytes.load(Ordering::Relaxed),
+            amount_syscalls: self.amount_syscalls.load(Ordering::Relaxed),
+            amount_io_model_calls: self.amount_io_model_calls.load(Ordering::Relaxed),
+            amount_eagain: self.amount_eagain.load(Ordering::Relaxed),
+            amount_omitted_datagrams: self.amount_omitted_datagrams.load(Ordering::Relaxed),
+            amount_reordered_datagrams: self.amount_reordered_datagrams.load(Ordering::Relaxed),
+            amount_duplicated_datagrams: self.amount_duplicated_datagrams.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl CounterSnapshot {
+    // The counters hold the totals of the thread, so they replace the values of the statistic
+    pub fn write_to(&self, statistic: &mut Statistic) {
+        statistic.amount_datagrams = self.amount_datagrams;
+        statistic.amount_data_bytes = self.amount_data_bytes as usize;
+        statistic.amount_syscalls = self.amount_syscalls;
+        statistic.amount_io_model_calls = self.amount_io_model_calls;
+        statistic.amount_eagain = self.amount_eagain;
+        statistic.amount_omitted_datagrams = self.amount_omitted_datagrams;
+        statistic.amount_reordered_datagrams = self.amount_reordered_datagrams;
+        statistic.amount_duplicated_datagrams = self.amount_duplicated_datagrams;
+    }
+}
+
+impl Add for CounterSnapshot {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        CounterSnapshot {
+            amount_datagrams: self.amount_datagrams + other.amount_datagrams,
+            amount_data_bytes: self.amount_data_bytes + other.amount_data_bytes,
+            amount_syscalls: self.amount_syscalls + other.amount_syscalls,
+            amount_io_model_calls: self.amount_io_model_calls + other.amount_io_model_calls,
+            amount_eagain: self.amount_eagain + other.amount_eagain,
+            amount_omitted_datagrams: self.amount_omitted_datagrams + other.amount_omitted_datagrams,
+            amount_reordered_datagrams: self.amount_reordered_datagrams + other.amount_reordered_datagrams,
+            amount_duplicated_datagrams: self.amount_duplicated_datagrams + other.amount_duplicated_datagrams,
+        }
+    }
+}
+
+// Counts between an earlier snapshot and this one
+impl Sub for CounterSnapshot {
+    type Output = Self;
+
+    fn sub(self, earlier: Self) -> Self {
+        CounterSnapshot {
+            amount_datagrams: self.amount_datagrams - earlier.amount_datagrams,
+            amount_data_bytes: self.amount_data_bytes - earlier.amount_data_bytes,
+            amount_syscalls: self.amount_syscalls - earlier.amount_syscalls,
+            amount_io_model_calls: self.amount_io_model_calls - earlier.amount_io_model_calls,
+            amount_eagain: self.amount_eagain - earlier.amount_eagain,
+            amount_omitted_datagrams: self.amount_omitted_datagrams - earlier.amount_omitted_datagrams,
+            amount_reordered_datagrams: self.amount_reordered_datagrams - earlier.amount_reordered_datagrams,
+            amount_duplicated_datagrams: self.amount_duplicated_datagrams - earlier.amount_duplicated_datagrams,
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use libc::{getrusage, rusage, RUSAGE_SELF};
+use libc::{getrusage, rusage, RUSAGE_SELF, RUSAGE_THREAD};
+use std::ops::Sub;
 use std::time::Instant;
 
 pub struct CpuUtil {
@@ -57,4 +58,46 @@ impl CpuUtil {
     pub fn get_absolut_cpu_util(&mut self) -> (f64, f64, f64) {
         self.get_cpu_util(self.first_usage, self.first_instant)
     }
-}
\ No newline at end of file
+}
+
+
+// User and system CPU time a single thread spent, in seconds
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ThreadCpuTime {
+    pub user: f64,
+    pub system: f64,
+}
+
+impl ThreadCpuTime {
+    // getrusage() only measures the calling thread, but with a resolution of microseconds
+    pub fn current() -> ThreadCpuTime {
+        let mut usage: rusage = unsafe { std::mem::zeroed() };
+        unsafe {
+            getrusage(RUSAGE_THREAD, &mut usage);
+        }
+        ThreadCpuTime {
+            user: usage.ru_utime.tv_sec as f64 + usage.ru_utime.tv_usec as f64 / 1_000_000.0,
+            system: usage.ru_stime.tv_sec as f64 + usage.ru_stime.tv_usec as f64 / 1_000_000.0,
+        }
+    }
+
+    // User, system and total CPU utilization over the given duration, like CpuUtil
+    pub fn utilization(&self, duration: f64) -> (f64, f64, f64) {
+        if duration <= 0.0 {
+            return (0.0, 0.0, 0.0);
+        }
+        ((self.user / duration) * 100.0, (self.system / duration) * 100.0, ((self.user + self.system) / duration) * 100.0)
+    }
+}
+
+// CPU time spent between an earlier measurement and this one
+impl Sub for ThreadCpuTime {
+    type Output = Self;
+
+    fn sub(self, earlier: Self) -> Self {
+        ThreadCpuTime {
+            user: (self.user - earlier.user).max(0.0),
+            system: (self.system - earlier.system).max(0.0),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+use super::counters::{CounterSnapshot, ThreadCounters};
+use super::cpu_util::ThreadCpuTime;
+use super::statistic::{Parameter, Statistic};
+use super::timer::TestTimer;
+
+// Samples the counters of all node threads of a test at the interval boundaries of the timer, and prints the merged interval statistic.
+// The node threads only count, so their loops never build, clone or send statistics during the test.
+pub struct IntervalReporter {
+    parameter: Parameter,
+    timer: Arc<TestTimer>,
+    counters: Vec<Arc<ThreadCounters>>,
+    total_intervals: u64,
+    // Set, after all node threads finished
+    finished: AtomicBool,
+}
+
+impl IntervalReporter {
+    pub fn new(parameter: Parameter, timer: Arc<TestTimer>, counters: Vec<Arc<ThreadCounters>>) -> Arc<IntervalReporter> {
+        let total_intervals = if parameter.output_interval == 0.0 { 0 } else { (parameter.test_runtime_length as f64 / parameter.output_interval).floor() as u64 };
+        Arc::new(IntervalReporter {
+            parameter,
+            timer,
+            counters,
+            total_intervals,
+            finished: AtomicBool::new(false),
+        })
+    }
+
+    // Started together with the timer, while the first interval begins with the measurement window
+    pub fn start(reporter: &Arc<IntervalReporter>) -> thread::JoinHandle<()> {
+        let reporter = Arc::clone(reporter);
+        thread::spawn(move || reporter.run())
+    }
+
+    fn run(&self) {
+        let mut last_samples = vec![CounterSnapshot::default(); self.counters.len()];
+        // The threads registered themselves before they were released, so their CPU time is counted from the start of the test
+        let mut last_cpu_times: Vec<ThreadCpuTime> = self.counters.iter().map(|counters| counters.cpu_time(Instant::now())).collect();
+        // The intervals are counted from the start of the measurement window, which the receiving side only knows after START arrived
+        while self.timer.measurement_elapsed_millis().is_none() && !self.finished.load(Ordering::Relaxed) {
+            thread::park_timeout(Duration::from_millis(crate::TIMER_TICK));
+        }
+        let mut last_timestamp = Statistic::get_unix_timestamp() - self.timer.measurement_elapsed_millis().unwrap_or(0) as f64 / 1000.0;
+        let mut interval_id = 1;
+
+        while interval_id <= self.total_intervals {
+            let finished = self.finished.load(Ordering::Relaxed);
+            // The last interval takes everything counted until the threads finished, and an interrupted test prints the interval it ended in
+            let boundary_passed = self.timer.amount_intervals() >= interval_id && interval_id < self.total_intervals;
+            if !boundary_passed && !finished {
+                thread::park_timeout(Duration::from_millis(crate::TIMER_TICK));
+                continue;
+            }
+
+            let timestamp = Statistic::get_unix_timestamp();
+            let mut statistic = Statistic::new(self.parameter.clone());
+            let mut interval_counts = CounterSnapshot::default();
+            let mut interval_cpu_time = ThreadCpuTime::default();
+            // All threads are asked for their CPU time first, so they answer at about the same time
+            self.counters.iter().for_each(|counters| counters.request_cpu_time());
+            let cpu_time_deadline = Instant::now() + Duration::from_millis(crate::TIMER_TICK);
+            for ((counters, last_sample), last_cpu_time) in self.counters.iter().zip(last_samples.iter_mut()).zip(last_cpu_times.iter_mut()) {
+                let sample = counters.sample();
+                let thread_counts = sample - *last_sample;
+                interval_counts = interval_counts + thread_counts;
+                *last_sample = sample;
+
+                let cpu_time = counters.cpu_time(cpu_time_deadline);
+                let thread_cpu_time = cpu_time - *last_cpu_time;
+                interval_cpu_time.user += thread_cpu_time.user;
+                interval_cpu_time.system += thread_cpu_time.system;
+                *last_cpu_time = cpu_time;
+
+                // Every thread is reported next to the aggregate, if there are several
+                if self.counters.len() > 1 {
+                    let mut thread_statistic = Statistic::new(self.parameter.clone());
+                    thread_counts.write_to(&mut thread_statistic);
+                    counters.tag(&mut thread_statistic);
+                    thread_statistic.interval_id = interval_id;
+                    thread_statistic.set_test_duration(Some(last_timestamp), Some(timestamp));
+                    (thread_statistic.cpu_user_time, thread_statistic.cpu_system_time, thread_statistic.cpu_total_time) = thread_cpu_time.utilization(thread_statistic.test_duration);
+                    statistic.threads.push(thread_statistic);
+                }
+            }
+            interval_counts.write_to(&mut statistic);
+            statistic.interval_id = interval_id;
+            statistic.set_test_duration(Some(last_timestamp), Some(timestamp));
+            // Only the node threads are accounted, not the timer and reporting threads
+            (statistic.cpu_user_time, statistic.cpu_system_time, statistic.cpu_total_time) = interval_cpu_time.utilization(statistic.test_duration);
+            Self::print_interval(&self.parameter, statistic);
+
+            last_timestamp = timestamp;
+            interval_id += 1;
+            if finished {
+                break;
+            }
+        }
+        debug!("Printed {} of {} intervals", interval_id - 1, self.total_intervals);
+    }
+
+    fn print_interval(parameter: &Parameter, mut statistic: Statistic) {
+        if statistic.amount_datagrams != 0 {
+            statistic.print(parameter.output_format, true);
+        }
+    }
+
+    // Prints the last interval with the final counters of the threads
+    pub fn finish(&self, handle: thread::JoinHandle<()>) {
+        self.finished.store(true, Ordering::Relaxed);
+        handle.thread().unpark();
+        handle.join().unwrap_or_else(|x| log::warn!("Error joining interval reporter: {:?}", x));
+    }
+}
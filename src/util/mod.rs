@@ -4,22 +4,28 @@ pub mod msghdr_vec;
 pub mod packet_buffer;
 pub mod core_affinity_manager;
 pub mod cpu_util;
+pub mod session_manager;
+pub mod counters;
+pub mod interrupt;
+pub mod interval_reporter;
+pub mod timer;
 
 use std::io::IoSlice;
 use log::{debug, trace};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use statistic::Statistic;
-use crate::net::MessageHeader;
+use counters::CounterSnapshot;
+use statistic::{Measurement, Parameter, Statistic};
+use crate::net::{MessageHeader, MessageType};
 
-#[derive(clap::ValueEnum, PartialEq, Default, Debug, Copy, Clone, Serialize)]
+#[derive(clap::ValueEnum, PartialEq, Default, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum NPerfMode {
     #[default]
     Receiver,
     Sender,
 }
 
-#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize)]
+#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum ExchangeFunction {
     Normal,
     #[default]
@@ -27,33 +33,115 @@ pub enum ExchangeFunction {
     Mmsg
 }
 
-#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize)]
+#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum IOModel {
     #[default]
     Select,
     Poll,
     BusyWaiting,
-    IoUring
+    IoUring,
+    Epoll,
+    // AF_PACKET socket with memory mapped TPACKET_V3 rings, the IPv4/UDP frames are built and parsed by nPerf itself
+    PacketRing,
+    // AF_XDP socket in XDP generic mode on the receiver, which gets the IPv4/UDP frames redirected by a minimal XDP program. The sender uses poll.
+    AfXdp
+}
+
+#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum EpollTrigger {
+    #[default]
+    Level,
+    // The socket is only reported again after new data arrived, so the node has to drain it until EAGAIN
+    Edge
+}
+
+#[derive(clap::ValueEnum, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum Transport {
+    #[default]
+    Udp,
+    // Unix domain datagram sockets, to measure the host-local overhead without the IP/UDP stack
+    Unix,
+    // One TCP stream per thread, as a baseline to compare UDP with
+    Tcp
+}
+
+// The connections are spread round robin over the threads. With fewer connections than threads, a connection is sent by several threads, like a QUIC connection using several paths.
+pub fn thread_connections(thread_id: u64, amount_threads: u16, amount_connections: u32) -> Vec<u64> {
+    let amount_threads = amount_threads as u64;
+    let amount_connections = amount_connections as u64;
+    if amount_connections < amount_threads {
+        vec![thread_id % amount_connections]
+    } else {
+        (thread_id..amount_connections).step_by(amount_threads as usize).collect()
+    }
+}
+
+// TCP has no datagram boundaries, so every full datagram size of the byte stream is counted as one datagram, which keeps the numbers comparable to UDP
+pub fn count_stream_datagrams(stream_bytes: &mut u64, amount_bytes: usize, datagram_size: usize) -> u64 {
+    let previous_datagrams = *stream_bytes / datagram_size as u64;
+    *stream_bytes += amount_bytes as u64;
+    *stream_bytes / datagram_size as u64 - previous_datagrams
 }
 
 
-pub fn process_packet_buffer(buffer: &[u8], datagram_size: usize, next_packet_id: u64, statistic: &mut Statistic) -> u64 {
+// Every datagram is assigned to the measurement of the connection in its header, so a GSO buffer may carry datagrams of several connections.
+// Returns the amount of datagrams with a valid header and the amount of foreign datagrams.
+pub fn process_packet_buffer(buffer: &[u8], datagram_size: usize, measurements: &mut [Measurement], batch: &mut CounterSnapshot, parameter: &Parameter) -> (u64, u64) {
     let mut amount_received_packets = 0;
+    let mut amount_foreign_packets = 0;
     for packet in buffer.chunks(datagram_size) {
-        amount_received_packets += process_packet(packet, next_packet_id, statistic);
+        match process_packet(packet, measurements, batch, parameter) {
+            Some(_) => amount_received_packets += 1,
+            None => amount_foreign_packets += 1
+        }
     }
-    amount_received_packets
+    (amount_received_packets, amount_foreign_packets)
 }
 
-pub fn process_packet(buffer: &[u8], next_packet_id: u64, statistic: &mut Statistic) -> u64 {
-    let packet_id = MessageHeader::get_packet_id(buffer);
-    debug!("Received packet number: {}", packet_id);
-    process_packet_number(packet_id, next_packet_id, statistic)
+// Returns None, if the packet doesn't carry a valid measurement header. These packets are counted as foreign datagrams and dropped.
+// The datagram is counted for its connection and in the batch of the receive call, which is added to the counters of the thread afterwards
+pub fn process_packet(buffer: &[u8], measurements: &mut [Measurement], batch: &mut CounterSnapshot, parameter: &Parameter) -> Option<usize> {
+    let header = match MessageHeader::decode(buffer) {
+        Ok(header) => header,
+        Err(x) => {
+            debug!("Dropping foreign datagram: {}", x);
+            return None;
+        }
+    };
+
+    if header.mtype != MessageType::MEASUREMENT {
+        debug!("Dropping hole punch datagram, since the receiver doesn't transmit");
+        return None;
+    }
+
+    let test_id = header.test_id as usize;
+    let connection_id = header.connection_id as usize;
+    if test_id >= crate::MAX_TEST_ID {
+        debug!("Dropping datagram with test id {} greater than the maximum {}!", test_id, crate::MAX_TEST_ID);
+        return None;
+    }
+
+    // The measurements are indexed by the connection ID, and only exist for the connections of the test.
+    // Datagrams with an ID out of range are counted as foreign datagrams, which the receiver reports once after the test.
+    if connection_id >= measurements.len() {
+        debug!("Dropping datagram with connection id {}, the test only has {} connections!", connection_id, parameter.amount_connections);
+        return None;
+    }
+
+    debug!("Received packet number {} of connection {} from test {}", header.packet_id, connection_id, test_id);
+    let measurement = Measurement::start(measurements, connection_id);
+    let next_packet_id = measurement.next_packet_id(test_id);
+    measurement.next_packet_ids[test_id] = next_packet_id + process_packet_number(header.packet_id, next_packet_id, &mut measurement.statistic, batch);
+    measurement.statistic.amount_datagrams += 1;
+    measurement.statistic.amount_data_bytes += buffer.len();
+    batch.amount_datagrams += 1;
+    batch.amount_data_bytes += buffer.len() as u64;
+    Some(connection_id)
 }
 
 // Packet reordering taken from iperf3 and rperf https://github.com/opensource-3d-p/rperf/blob/14d382683715594b7dce5ca0b3af67181098698f/src/stream/udp.rs#L225
 // https://github.com/opensource-3d-p/rperf/blob/14d382683715594b7dce5ca0b3af67181098698f/src/stream/udp.rs#L225 
-fn process_packet_number(packet_id: u64, next_packet_id: u64, statistic: &mut Statistic) -> u64 {
+fn process_packet_number(packet_id: u64, next_packet_id: u64, statistic: &mut Statistic, batch: &mut CounterSnapshot) -> u64 {
     match packet_id {
         _ if packet_id == next_packet_id => {
             1
@@ -61,6 +149,7 @@ fn process_packet_number(packet_id: u64, next_packet_id: u64, statistic: &mut St
         _ if packet_id > next_packet_id => {
             let lost_packet_count = packet_id - next_packet_id;
             statistic.amount_omitted_datagrams += lost_packet_count as i64;
+            batch.amount_omitted_datagrams += lost_packet_count as i64;
             debug!("Reordered or lost packet received! Expected number {}, but received {}. {} packets are currently missing", next_packet_id, packet_id, lost_packet_count);
             lost_packet_count + 1 // This is the next packet id that we expect, since we assume that the missing packets are lost
         },
@@ -68,9 +157,12 @@ fn process_packet_number(packet_id: u64, next_packet_id: u64, statistic: &mut St
             if statistic.amount_omitted_datagrams > 0 { 
                 statistic.amount_omitted_datagrams -= 1;
                 statistic.amount_reordered_datagrams  += 1;
+                batch.amount_omitted_datagrams -= 1;
+                batch.amount_reordered_datagrams += 1;
                 debug!("Received reordered packet number {}, but expected {}", packet_id, next_packet_id);
             } else { 
                 statistic.amount_duplicated_datagrams += 1;
+                batch.amount_duplicated_datagrams += 1;
                 debug!("Received duplicated packet: {}", packet_id);
             }
             0
@@ -98,8 +190,10 @@ pub fn get_gso_size_from_cmsg(msghdr: &mut libc::msghdr) -> Option<u32> {
     None
 }
 
-pub fn process_packet_msghdr(msghdr: &mut libc::msghdr, amount_received_bytes: usize, mut next_packet_id: u64, statistic: &mut Statistic) -> (u64, u64) {
+// Returns the amount of datagrams with a valid header and the amount of foreign datagrams
+pub fn process_packet_msghdr(msghdr: &mut libc::msghdr, amount_received_bytes: usize, measurements: &mut [Measurement], batch: &mut CounterSnapshot, parameter: &Parameter) -> (u64, u64) {
     let mut absolut_packets_received = 0;
+    let mut amount_foreign_packets = 0;
     let single_packet_size = match get_gso_size_from_cmsg(msghdr) {
         Some(gso_size) => gso_size,
         None => {
@@ -121,12 +215,14 @@ pub fn process_packet_msghdr(msghdr: &mut libc::msghdr, amount_received_bytes: u
     };
 
     for packet in datagrams.chunks(single_packet_size as usize) {
-        next_packet_id += process_packet(packet, next_packet_id, statistic);
-        absolut_packets_received += 1;
-        trace!("iovec buffer: {:?} with now absolut packets received {} and next packet id: {}", packet, next_packet_id, absolut_packets_received);
+        match process_packet(packet, measurements, batch, parameter) {
+            Some(_) => absolut_packets_received += 1,
+            None => amount_foreign_packets += 1
+        }
+        trace!("iovec buffer: {:?} with now absolut packets received {}", packet, absolut_packets_received);
     }
 
-    (next_packet_id, absolut_packets_received)
+    (absolut_packets_received, amount_foreign_packets)
 } 
 
 
@@ -6,9 +6,8 @@ use crate::net::{MessageHeader, MessageType};
 #[allow(non_camel_case_types)]
 pub struct WrapperMsghdr {
     msghdr: libc::msghdr,
-    buffer_length: usize,
     with_cmsg: bool,
-    sockaddr: libc::sockaddr_in,
+    sockaddr: libc::sockaddr_storage,
     pub datagram_size: u32,
     pub packets_amount: usize,
 }
@@ -29,19 +28,23 @@ impl WrapperMsghdr {
         debug!("Created msghdr with datagram size: {}, last packet size: {}, buffer length: {}, packets amount: {}", datagram_size, _last_packet_size, mss, packets_amount);
 
         let buffer = Box::leak(vec![0_u8; mss as usize].into_boxed_slice()); // Could solve using the heap by using always a MAX_PACKET_SIZE buffer (which is 2^16)
+        Some(Self::with_buffer(buffer, datagram_size, packets_amount))
+    }
+
+    // The buffer is owned by the caller and has to outlive the msghdr
+    pub fn with_buffer(buffer: &mut [u8], datagram_size: u32, packets_amount: usize) -> Self {
         let iov = Self::create_iovec(buffer);
 
         let msghdr = Self::create_msghdr(iov);
-        let sockaddr: libc::sockaddr_in = unsafe { MaybeUninit::zeroed().assume_init() };
+        let sockaddr: libc::sockaddr_storage = unsafe { MaybeUninit::zeroed().assume_init() };
 
-        Some(WrapperMsghdr {
+        WrapperMsghdr {
             msghdr,
-            buffer_length: mss as usize,
             with_cmsg: false,
             sockaddr,
             datagram_size,
             packets_amount,
-        })
+        }
     }
 
     // Similar to iperf3's fill_with_repeating_pattern
@@ -60,14 +63,14 @@ impl WrapperMsghdr {
 
     pub fn add_message_header(&mut self, test_id: u64, packet_id: u64) -> Result<u64, &'static str> {
         let mut amount_used_packet_ids: u64 = 0;
-        let mut header = MessageHeader::new(MessageType::MEASUREMENT, test_id, packet_id);
+        let mut header = MessageHeader::new(MessageType::MEASUREMENT, test_id, packet_id, 0);
 
         for i in 0..self.packets_amount {
             let start_of_packet = i * self.datagram_size as usize;
             header.set_packet_id(packet_id + amount_used_packet_ids);
             let serialized_header = header.serialize();
             let buffer = self.get_buffer_pointer();
-            buffer[start_of_packet..(start_of_packet + serialized_header.len())].copy_from_slice(serialized_header);
+            buffer[start_of_packet..(start_of_packet + serialized_header.len())].copy_from_slice(&serialized_header);
             amount_used_packet_ids += 1;
         }
         debug!("Added packet IDs to buffer! Used packet IDs: {}, Next packet ID: {}", amount_used_packet_ids, packet_id + amount_used_packet_ids);
@@ -88,10 +91,11 @@ impl WrapperMsghdr {
         msghdr
     }
 
-    pub fn set_address(&mut self, address: libc::sockaddr_in) {
+    // The address is either a sockaddr_in or sockaddr_in6, so msg_namelen has to match the address family
+    pub fn set_address(&mut self, address: libc::sockaddr_storage, address_len: libc::socklen_t) {
         self.sockaddr = address;
         self.msghdr.msg_name = (&mut self.sockaddr) as *mut _ as *mut libc::c_void;
-        self.msghdr.msg_namelen = std::mem::size_of::<libc::sockaddr_in>() as u32;
+        self.msghdr.msg_namelen = address_len;
     }
 
     pub fn add_cmsg_buffer(&mut self) {
@@ -108,25 +112,6 @@ impl WrapperMsghdr {
         }))
     }
 
-    pub fn copy_buffer(&mut self, buffer: &[u8]) {
-        if buffer.len() <= self.buffer_length {
-            self.buffer_length = buffer.len();
-            let buf = unsafe { (*self.msghdr.msg_iov).iov_base as *mut u8 };
-            // Copy buffer into msghdr iovec
-            unsafe { buf.copy_from(buffer.as_ptr(), buffer.len()) };
-        }
-    }
-
-    pub fn get_msghdr(&mut self) -> &mut libc::msghdr {
-        if self.with_cmsg {
-            // Has to be set, since recvmsg overwrites this value 
-            self.msghdr.msg_controllen = crate::LENGTH_MSGHDR_CONTROL_MESSAGE_BUFFER;
-        }
-        self.msghdr.msg_flags = 0;
-
-        &mut self.msghdr
-    }
-
     pub fn move_msghdr(mut self) -> libc::msghdr {
         if self.with_cmsg {
             // Has to be set, since recvmsg overwrites this value 
@@ -19,6 +19,17 @@ impl MsghdrVec {
         }
     }
 
+    // A single msghdr on a buffer owned by the caller
+    pub fn from_buffer(buffer: &mut [u8], datagram_size: usize) -> MsghdrVec {
+        let packets_amount_per_msghdr = buffer.len() / datagram_size;
+
+        MsghdrVec {
+            msghdr_vec: vec![WrapperMsghdr::with_buffer(buffer, datagram_size as u32, packets_amount_per_msghdr)],
+            datagram_size,
+            packets_amount_per_msghdr
+        }
+    }
+
     pub fn with_cmsg_buffer(mut self) -> MsghdrVec {
         self.msghdr_vec.iter_mut().for_each(|msghdr| msghdr.add_cmsg_buffer());
         self
@@ -38,8 +49,8 @@ impl MsghdrVec {
         self
     }
 
-    pub fn with_target_address(mut self, sockaddr: libc::sockaddr_in) -> MsghdrVec {
-        self.msghdr_vec.iter_mut().for_each(|wrapper_msghdr| wrapper_msghdr.set_address(sockaddr));
+    pub fn with_target_address(mut self, sockaddr: libc::sockaddr_storage, sockaddr_len: libc::socklen_t) -> MsghdrVec {
+        self.msghdr_vec.iter_mut().for_each(|wrapper_msghdr| wrapper_msghdr.set_address(sockaddr, sockaddr_len));
         self
     }
 
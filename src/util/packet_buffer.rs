@@ -1,13 +1,29 @@
+use std::alloc::{self, Layout};
+
 use log::debug;
 
 use crate::net::MessageHeader;
 use super::msghdr_vec::MsghdrVec;
 
+// Page aligned memory, which is registered with the kernel, like the UMEM of an AF_XDP socket. Unlike the buffers of the msghdrs, it is freed with the packet buffer.
+struct PageAlignedBuffer {
+    pointer: *mut u8,
+    layout: Layout
+}
+
+impl Drop for PageAlignedBuffer {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.pointer, self.layout) };
+    }
+}
+
 pub struct PacketBuffer {
     pub mmsghdr_vec: Vec<libc::mmsghdr>,
     datagram_size: usize, // ASSUMPTION: It's the same for all msghdrs
     packets_amount_per_msghdr: usize, // ASSUMPTION: It's the same for all msghdrs
-    index_pool: Vec<usize> // When buffers are used for io_uring, we need to know which buffers can be reused. VecDeque (RingBuffer) would be more logical, but is less performant.
+    connection_id: u64, // Written into the headers together with the packet IDs
+    index_pool: Vec<usize>, // When buffers are used for io_uring, we need to know which buffers can be reused. VecDeque (RingBuffer) would be more logical, but is less performant.
+    _page_aligned_buffer: Option<PageAlignedBuffer> // Only held to free the page aligned buffer together with the packet buffer
 }
 
 impl PacketBuffer {
@@ -30,7 +46,25 @@ impl PacketBuffer {
             index_pool: (0..mmsghdr_vec.len()).collect(),
             mmsghdr_vec,
             datagram_size,
-            packets_amount_per_msghdr
+            packets_amount_per_msghdr,
+            connection_id: 0,
+            _page_aligned_buffer: None
+        }
+    }
+
+    // A single page aligned buffer of the given size, e.g. the UMEM of an AF_XDP socket, which holds the frames of the kernel
+    pub fn new_page_aligned(size: usize, datagram_size: usize) -> PacketBuffer {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let layout = Layout::from_size_align(size, page_size).expect("Invalid size of page aligned packet buffer");
+        let pointer = unsafe { alloc::alloc_zeroed(layout) };
+        if pointer.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        let buffer = unsafe { std::slice::from_raw_parts_mut(pointer, size) };
+        PacketBuffer {
+            _page_aligned_buffer: Some(PageAlignedBuffer { pointer, layout }),
+            ..PacketBuffer::new(MsghdrVec::from_buffer(buffer, datagram_size))
         }
     }
 
@@ -68,6 +102,7 @@ impl PacketBuffer {
     pub fn add_packet_ids(&mut self, packet_id: u64, amount_packets: Option<usize>) -> Result<u64, &'static str> {
         let mut amount_used_packet_ids: u64 = 0;
         let mmsghdr_vec_len = self.mmsghdr_vec.len();
+        let connection_id = self.connection_id;
 
         // Iterate over all mmsghdr structs (or up to amount_packets if specified)
         for mmsghdr in self.mmsghdr_vec.iter_mut().take(amount_packets.unwrap_or(mmsghdr_vec_len)) {
@@ -75,7 +110,7 @@ impl PacketBuffer {
 
             for i in 0..self.packets_amount_per_msghdr {
                 let start_of_packet = i * self.datagram_size;
-                MessageHeader::set_packet_id_raw(&mut msghdr_buffer[start_of_packet..], packet_id + amount_used_packet_ids);
+                MessageHeader::set_packet_id_raw(&mut msghdr_buffer[start_of_packet..], packet_id + amount_used_packet_ids, connection_id);
                 amount_used_packet_ids += 1;
             }
         }
@@ -89,11 +124,12 @@ impl PacketBuffer {
         let mut amount_used_packet_ids: u64 = 0;
         let datagram_size = self.datagram_size;
         let packets_amount_per_msghdr = self.packets_amount_per_msghdr;
+        let connection_id = self.connection_id;
         let msghdr_buffer = self.get_buffer_pointer_from_index(index)?;
 
         for i in 0..packets_amount_per_msghdr {
             let start_of_packet = i * datagram_size;
-            MessageHeader::set_packet_id_raw(&mut msghdr_buffer[start_of_packet..], packet_id + amount_used_packet_ids);
+            MessageHeader::set_packet_id_raw(&mut msghdr_buffer[start_of_packet..], packet_id + amount_used_packet_ids, connection_id);
             amount_used_packet_ids += 1;
         }
 
@@ -101,6 +137,11 @@ impl PacketBuffer {
         Ok(amount_used_packet_ids)
     }
 
+    // The packets of the next send call belong to the given simulated connection
+    pub fn set_connection_id(&mut self, connection_id: u64) {
+        self.connection_id = connection_id;
+    }
+
     pub fn packets_amount_per_msghdr(&self) -> usize {
         self.packets_amount_per_msghdr
     }
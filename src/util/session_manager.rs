@@ -0,0 +1,74 @@
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use log::{debug, info};
+
+// Hands out the data ports of the receiver to its sessions, so concurrent tests never share a socket, thread or statistic.
+// Every session gets a consecutive block of ports, starting at the lowest free port after the first port of the receiver.
+pub struct SessionManager {
+    first_port: u16,
+    control_port: u16,
+    next_session_id: u64,
+    sessions: Vec<(u64, Range<u16>)>,
+}
+
+// The ports of a session are released, as soon as the session is dropped
+pub struct Session {
+    pub id: u64,
+    pub port: u16,
+    manager: Arc<Mutex<SessionManager>>,
+}
+
+impl SessionManager {
+    pub fn new(first_port: u16, control_port: u16) -> Arc<Mutex<SessionManager>> {
+        Arc::new(Mutex::new(SessionManager {
+            first_port,
+            control_port,
+            next_session_id: 1,
+            sessions: Vec::new(),
+        }))
+    }
+
+    // TCP data ports skip the port of the control channel, which is a TCP port as well
+    pub fn open_session(manager: &Arc<Mutex<SessionManager>>, amount_ports: u16, tcp: bool) -> Result<Session, &'static str> {
+        let mut session_manager = manager.lock().unwrap();
+
+        let mut port = session_manager.first_port;
+        let control_port = session_manager.control_port;
+        let mut used_ports: Vec<Range<u16>> = session_manager.sessions.iter().map(|(_, ports)| ports.clone()).collect();
+        if tcp {
+            used_ports.push(control_port..control_port.saturating_add(1));
+        }
+        used_ports.sort_by_key(|ports| ports.start);
+        for ports in used_ports.iter() {
+            if port.checked_add(amount_ports).ok_or("No free ports left for a new session")? <= ports.start {
+                break;
+            }
+            port = port.max(ports.end);
+        }
+        let end_port = port.checked_add(amount_ports).ok_or("No free ports left for a new session")?;
+
+        let id = session_manager.next_session_id;
+        session_manager.next_session_id += 1;
+        session_manager.sessions.push((id, port..end_port));
+        info!("Opened session {} with data ports {} to {}", id, port, end_port - 1);
+
+        Ok(Session {
+            id,
+            port,
+            manager: Arc::clone(manager),
+        })
+    }
+
+    pub fn amount_sessions(&self) -> usize {
+        self.sessions.len()
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let mut session_manager = self.manager.lock().unwrap();
+        session_manager.sessions.retain(|(id, _)| *id != self.id);
+        debug!("Closed session {}, {} sessions still running", self.id, session_manager.amount_sessions());
+    }
+}
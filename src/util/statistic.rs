@@ -1,12 +1,13 @@
-use std::{fs::OpenOptions, ops::Add, path, thread, time::{Instant, SystemTime, UNIX_EPOCH}};
+use std::{fs::OpenOptions, ops::Add, path, thread, time::{SystemTime, UNIX_EPOCH}};
 use log::{debug, error, info};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{self};
 use crate::{io_uring::{UringMode, UringSqFillingMode, UringTaskWork}, net::socket_options::SocketOptions};
 use serde::Serializer;
 use std::collections::HashMap;
+use std::sync::Mutex;
 
-#[derive(clap::ValueEnum, Default, PartialEq, Debug, Clone, Copy, Serialize)]
+#[derive(clap::ValueEnum, Default, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum OutputFormat {
     #[default]
     Text,
@@ -14,7 +15,7 @@ pub enum OutputFormat {
     File
 }
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
+#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum MultiplexPort {
     #[default]
     Individual,
@@ -22,73 +23,67 @@ pub enum MultiplexPort {
     Sharding
 }
 
-#[derive(clap::ValueEnum, Debug, PartialEq, Serialize, Clone, Copy, Default)]
-pub enum SimulateConnection {
-    Single,
+// Why a node thread stopped. A timeout means the sender stopped sending or its datagrams were lost on the way.
+// An interrupted test was stopped early by SIGINT or SIGTERM and only covers the time until then.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
+pub enum TerminationReason {
     #[default]
-    Multiple
+    Stopped,
+    StartTimeout,
+    IdleTimeout,
+    Interrupted
 }
 
-#[derive(Debug, Clone)]
-pub struct StatisticInterval {
-    interval_id: u64,
-    pub output_interval: f64,
-    pub last_send_instant: Instant,
-    pub last_send_timestamp: f64,
-    pub total_interval_outputs: u64,
-    amount_interval_outputs: u64,
-    pub statistics: Vec<Statistic>,
+// Receiver side accounting of a simulated connection, summed up over all ports and threads the connection arrived on
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConnectionStatistic {
+    pub connection_id: u64,
+    pub amount_datagrams: u64,
+    pub amount_data_bytes: usize,
+    pub amount_reordered_datagrams: u64,
+    pub amount_duplicated_datagrams: u64,
+    pub amount_omitted_datagrams: i64,
 }
 
-impl StatisticInterval {
-    pub fn new(last_send_instant: Instant, output_interval: f64, runtime_length: u64) -> StatisticInterval {
-        let total_interval_outputs = if output_interval == 0.0 { 0 } else { (runtime_length as f64 / output_interval).floor() as u64 };
-        StatisticInterval {
-            interval_id: 1,
-            output_interval,
-            last_send_instant,
-            last_send_timestamp: Statistic::get_unix_timestamp(),
-            amount_interval_outputs: 0,
-            total_interval_outputs,
-            statistics: Vec::with_capacity(total_interval_outputs as usize)
-        }
-    }
-
-    pub fn start(&mut self, offset_in_milliseconds: Option<u64>) {
-        self.last_send_instant = Instant::now() + std::time::Duration::from_millis(offset_in_milliseconds.unwrap_or(0));
-        self.last_send_timestamp = Statistic::get_unix_timestamp() + offset_in_milliseconds.unwrap_or(0) as f64;
-    }
+impl Add for ConnectionStatistic {
+    type Output = Self;
 
-    pub fn finished(&self) -> bool {
-        self.amount_interval_outputs >= self.total_interval_outputs
+    fn add(self, other: Self) -> Self {
+        ConnectionStatistic {
+            connection_id: self.connection_id,
+            amount_datagrams: self.amount_datagrams + other.amount_datagrams,
+            amount_data_bytes: self.amount_data_bytes + other.amount_data_bytes,
+            amount_reordered_datagrams: self.amount_reordered_datagrams + other.amount_reordered_datagrams,
+            amount_duplicated_datagrams: self.amount_duplicated_datagrams + other.amount_duplicated_datagrams,
+            amount_omitted_datagrams: self.amount_omitted_datagrams + other.amount_omitted_datagrams,
+        }
     }
+}
 
-    pub fn calculate_interval(&mut self, mut statistic_new: Statistic) {
-        let current_time = Instant::now();
-        let current_time_unix = Statistic::get_unix_timestamp();
-
-        if self.amount_interval_outputs >= self.total_interval_outputs {
-            self.last_send_instant = current_time;
-            debug!("{:?}: Last interval already sent. No more intervals to send.", thread::current().id());
-            return;
-        } 
-
-        statistic_new.interval_id = self.interval_id;
-        statistic_new.set_test_duration(Some(self.last_send_timestamp), Some(current_time_unix));
-        statistic_new.calculate_statistics();
+// Accounting of a single socket, if a thread drives several sockets. The sockets are identified by their data port.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SocketStatistic {
+    pub port: u16,
+    pub amount_datagrams: u64,
+    pub amount_data_bytes: usize,
+    pub amount_eagain: u64,
+}
 
-        // Update the last send operation instant to the current instant
-        self.last_send_instant = current_time;
-        self.last_send_timestamp = current_time_unix;
-        self.amount_interval_outputs += 1;
-        self.interval_id += 1;
+impl Add for SocketStatistic {
+    type Output = Self;
 
-        self.statistics.push(statistic_new);
+    fn add(self, other: Self) -> Self {
+        SocketStatistic {
+            port: self.port,
+            amount_datagrams: self.amount_datagrams + other.amount_datagrams,
+            amount_data_bytes: self.amount_data_bytes + other.amount_data_bytes,
+            amount_eagain: self.amount_eagain + other.amount_eagain,
+        }
     }
 }
 
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Statistic {
     #[serde(flatten)]
     pub parameter: Parameter,
@@ -102,26 +97,52 @@ pub struct Statistic {
     pub amount_reordered_datagrams: u64,
     pub amount_duplicated_datagrams: u64,
     pub amount_omitted_datagrams: i64,
+    pub amount_foreign_datagrams: u64,
     pub amount_syscalls: u64,
     pub amount_io_model_calls: u64,
     pub amount_eagain: u64,
     pub data_rate_gbit: f64,
+    pub packets_per_second: f64,
+    // Omitted datagrams in percent of the received and omitted datagrams
     pub packet_loss: f64,
     pub cpu_user_time: f64,
     pub cpu_system_time: f64,
     pub cpu_total_time: f64,
-    #[serde(skip_serializing)]
+    // CPU seconds spent for every Gibit, to compare the efficiency of the IO models independent of the data rate
+    pub cpu_time_per_gbit: f64,
+    pub termination_reason: TerminationReason,
+    // Time between the first and the last thread starting its measurement, in milliseconds
+    pub thread_start_spread: f64,
+    // Only set in the statistic of a single thread. The aggregate of all threads isn't tagged.
+    pub thread_index: Option<u64>,
+    pub test_id: Option<u64>,
+    // First data port of the thread
+    pub port: Option<u16>,
+    // Only known with core affinity
+    pub core: Option<usize>,
+    // Only set in the record of a single connection, which the receiver accounts on its own
+    pub connection_id: Option<u64>,
+    #[serde(skip_serializing, default)]
     pub uring_cq_overflows: u64,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub uring_out_of_buffers: u64,
     pub uring_copied_zc: u64,
     pub uring_canceled_multishot: u64,
-    #[serde(with = "utilization_option_box_slice")]
+    #[serde(serialize_with = "utilization_option_box_slice::serialize", skip_deserializing)]
     pub uring_sq_utilization: Option<Box<[usize]>>,
-    #[serde(with = "utilization_option_box_slice")]
+    #[serde(serialize_with = "utilization_option_box_slice::serialize", skip_deserializing)]
     pub uring_cq_utilization: Option<Box<[usize]>>,
-    #[serde(with = "utilization_option_box_slice")]
+    #[serde(serialize_with = "utilization_option_box_slice::serialize", skip_deserializing)]
     pub uring_inflight_utilization: Option<Box<[usize]>>,
+    // Sorted by connection ID. Only the receiver accounts per connection.
+    #[serde(skip)]
+    pub connections: Vec<ConnectionStatistic>,
+    // Sorted by port. Only filled, if the threads drive several sockets.
+    #[serde(skip)]
+    pub sockets: Vec<SocketStatistic>,
+    // Sorted by thread index. Only filled, if the test uses several threads.
+    #[serde(skip)]
+    pub threads: Vec<Statistic>,
 }
 
 
@@ -133,7 +154,8 @@ pub struct Measurement {
     pub end_time: f64,
     pub statistic: Statistic,
     pub first_packet_received: bool,
-    pub last_packet_received: bool,
+    // Every sender thread has its own packet ID space in a connection, indexed by its test id
+    pub next_packet_ids: Vec<u64>,
 }
 
 impl Statistic {
@@ -151,14 +173,24 @@ impl Statistic {
             amount_reordered_datagrams: 0,
             amount_duplicated_datagrams: 0,
             amount_omitted_datagrams: 0,
+            amount_foreign_datagrams: 0,
             amount_syscalls: 0,
             amount_io_model_calls: 0,
             amount_eagain: 0,
             data_rate_gbit: 0.0,
+            packets_per_second: 0.0,
             packet_loss: 0.0,
             cpu_user_time: 0.0,
             cpu_system_time: 0.0,
             cpu_total_time: 0.0,
+            cpu_time_per_gbit: 0.0,
+            termination_reason: TerminationReason::Stopped,
+            thread_start_spread: 0.0,
+            thread_index: None,
+            test_id: None,
+            port: None,
+            core: None,
+            connection_id: None,
             uring_cq_overflows: 0,
             uring_out_of_buffers: 0,
             uring_copied_zc: 0,
@@ -166,6 +198,9 @@ impl Statistic {
             uring_sq_utilization: if uring_record_utilization { Some(vec![0_usize; (crate::URING_MAX_RING_SIZE + 1) as usize].into_boxed_slice()) } else { None },
             uring_cq_utilization: if uring_record_utilization { Some(vec![0_usize; ((crate::URING_MAX_RING_SIZE * 2) + 1) as usize].into_boxed_slice()) } else { None },
             uring_inflight_utilization: if uring_record_utilization { Some(vec![0_usize; ((crate::URING_MAX_RING_SIZE * crate::URING_BUFFER_SIZE_MULTIPLICATOR) + 1) as usize].into_boxed_slice()) } else { None },
+            connections: Vec::new(),
+            sockets: Vec::new(),
+            threads: Vec::new(),
         }
     }
 
@@ -180,13 +215,27 @@ impl Statistic {
         debug!("Updating statistic...");
         self.total_data_gbyte = self.calculate_total_data();
         self.data_rate_gbit = self.calculate_data_rate();
+        self.packets_per_second = self.calculate_packet_rate();
         self.packet_loss = self.calculate_packet_loss();
+        self.cpu_time_per_gbit = self.calculate_cpu_time_per_gbit();
         debug!("Statistic updated: {:?}", self);
     }
 
     pub fn print(&mut self, output_format: OutputFormat, interval_print: bool) {
         self.calculate_statistics();
 
+        // Every thread is reported on its own before the aggregate. The text summary lists the threads in its own section instead.
+        if interval_print || output_format != OutputFormat::Text {
+            for thread in self.threads.iter_mut() {
+                thread.print(output_format, interval_print);
+            }
+        }
+
+        // The connections of the receiver are summed up over all threads, so they are only reported with the aggregate
+        if !interval_print && output_format != OutputFormat::Text && self.thread_index.is_none() {
+            self.print_connections(output_format);
+        }
+
         match output_format {
             OutputFormat::Json => {
                 println!("{}", serde_json::to_string(&self).unwrap());
@@ -196,15 +245,18 @@ impl Statistic {
 
                 if interval_print {
                     println!(
-                        "[{:3}] {:2.2}-{:2.2} sec  {:.2} GBytes  {:.2} Gbits/sec  {}/{} ({:.1}%)",
+                        "[{:3}] {:2.2}-{:2.2} sec  {:.2} GBytes  {:.2} Gbits/sec  {:.0} packets/sec  {}/{} ({:.1}%)  {:.1}% CPU{}",
                         self.interval_id, 
                         if interval_timestamp == 0.0 { 0.0 } else { interval_timestamp - self.parameter.output_interval }, 
                         interval_timestamp, 
                         self.total_data_gbyte, 
                         self.data_rate_gbit, 
+                        self.packets_per_second,
                         self.amount_omitted_datagrams, 
                         self.amount_datagrams, 
-                        self.packet_loss
+                        self.packet_loss,
+                        self.cpu_total_time,
+                        self.thread_index.map(|thread_index| format!("  thread {}", thread_index)).unwrap_or_default()
                     );
                 } else {
                 println!("------------------------");
@@ -213,22 +265,64 @@ impl Statistic {
                 println!("Total time: {:.2}s", self.test_duration);
                 println!("Total data: {:.2} GiBytes", self.total_data_gbyte);
                 println!("Data rate: {:.2} GiBytes/s / {:.2} Gibit/s", self.data_rate_gbit / 8.0, self.data_rate_gbit);
+                println!("Packets per second: {:.0}", self.packets_per_second);
                 println!("Packet loss: {:.2}%", self.packet_loss);
                 println!("------------------------");
                 println!("CPU user space: {:.2}%", self.cpu_user_time);
                 println!("CPU system space: {:.2}%", self.cpu_system_time);
                 println!("CPU total: {:.2}%", self.cpu_total_time);
+                println!("CPU time per Gibit: {:.3}s", self.cpu_time_per_gbit);
                 println!("Threads used: {}", self.parameter.amount_threads);
+                println!("Thread start spread: {:.3}ms", self.thread_start_spread);
+                println!("Termination reason: {:?}", self.termination_reason);
                 println!("------------------------");
                 println!("Amount of datagrams: {}", self.amount_datagrams);
                 println!("Amount of reordered datagrams: {}", self.amount_reordered_datagrams);
                 println!("Amount of duplicated datagrams: {}", self.amount_duplicated_datagrams);
                 println!("Amount of omitted datagrams: {}", self.amount_omitted_datagrams);
+                println!("Amount of dropped foreign datagrams: {}", self.amount_foreign_datagrams);
                 println!("Amount of syscalls: {}", self.amount_syscalls);
                 println!("Amount of datagrams per syscall: {:.0}", if self.amount_syscalls != 0 { self.amount_datagrams / self.amount_syscalls } else { 0 });
                 println!("Amount of IO model syscalls: {}", self.amount_io_model_calls);
                 println!("Amount of received EAGAIN errors: {}", self.amount_eagain);
                 println!("------------------------");
+                if self.threads.len() > 1 {
+                    println!("Threads");
+                    println!("------------------------");
+                    for thread in self.threads.iter_mut() {
+                        thread.calculate_statistics();
+                        println!("[{:3}] port {}  core {}  test ID {}  {:.2} GBytes  {:.2} Gbit/s  {:.0} packets/sec  {}/{} ({:.2}%)  CPU {:.2}% / {:.2}%  {:.3}s per Gibit",
+                            thread.thread_index.unwrap_or_default(),
+                            thread.port.unwrap_or_default(),
+                            thread.core.map(|core| core.to_string()).unwrap_or(String::from("-")),
+                            thread.test_id.unwrap_or_default(),
+                            thread.total_data_gbyte,
+                            thread.data_rate_gbit,
+                            thread.packets_per_second,
+                            thread.amount_omitted_datagrams,
+                            thread.amount_datagrams,
+                            thread.packet_loss,
+                            thread.cpu_user_time,
+                            thread.cpu_system_time,
+                            thread.cpu_time_per_gbit
+                        );
+                    }
+                    println!("------------------------");
+                }
+                self.print_connections(output_format);
+                if self.sockets.len() > 1 {
+                    println!("Sockets");
+                    println!("------------------------");
+                    for socket in self.sockets.iter() {
+                        println!("[{:5}] {:.2} GBytes  {} datagrams  {} EAGAIN",
+                            socket.port,
+                            socket.amount_data_bytes as f64 / 1024.0 / 1024.0 / 1024.0,
+                            socket.amount_datagrams,
+                            socket.amount_eagain
+                        );
+                    }
+                    println!("------------------------");
+                }
                 if self.parameter.io_model == super::IOModel::IoUring {
                     println!("Io-Uring");
                     println!("------------------------");
@@ -264,44 +358,46 @@ impl Statistic {
                 }
             }
             },
-            OutputFormat::File => {
-                let mut output_file = self.parameter.output_file_path.clone();
-                output_file.set_extension("csv");
-
-                // Check if the output dir exists. If not, try to create it
-                if let Some(parent_dir) = output_file.parent() {
-                    if !parent_dir.exists() {
-                        if let Err(err) = std::fs::create_dir_all(parent_dir) {
-                            error!("Failed to create output directory: {:?}", err);
-                            return;
-                        } else {
-                            debug!("Output directory created: {:?}", parent_dir);
-                        }
-                    }
+            OutputFormat::File => write_csv_record(&self.parameter.output_file_path, self)
+        }
+    }
+
+    // Lists the connections in the text summary. JSON and CSV output get a record per connection, tagged with its connection ID.
+    pub fn print_connections(&self, output_format: OutputFormat) {
+        if self.connections.len() <= 1 {
+            return;
+        }
+
+        match output_format {
+            OutputFormat::Text => {
+                println!("Connections");
+                println!("------------------------");
+                for connection in self.connections.iter() {
+                    println!("[{:3}] {:.2} GBytes  {} datagrams  {} omitted  {} reordered  {} duplicated",
+                        connection.connection_id,
+                        connection.amount_data_bytes as f64 / 1024.0 / 1024.0 / 1024.0,
+                        connection.amount_datagrams,
+                        connection.amount_omitted_datagrams,
+                        connection.amount_reordered_datagrams,
+                        connection.amount_duplicated_datagrams
+                    );
                 }
-                let file = OpenOptions::new()
-                    .append(true)
-                    .create(true)
-                    .open(&output_file);
-
-                if let Ok(file) = file {
-                    // Check if the file exists is empty
-                    let is_empty = file.metadata().unwrap().len() == 0;
-
-                    // Use csv writer to write the results to a file
-                    let mut wtr = if is_empty {
-                        // If the file is empty, use automatically write the header and data
-                        csv::Writer::from_writer(file)
-                    } else {
-                        // If the file is not empty, manually write the data without the header
-                        csv::WriterBuilder::new().has_headers(false).from_writer(file)
-                    };
-
-                    wtr.serialize(self).unwrap();
-                    wtr.flush().unwrap();
-                    info!("Results saved to {}", output_file.display());
-                } else {
-                    error!("Failed to create file: {}", output_file.display());
+                println!("------------------------");
+            },
+            _ => {
+                for connection in self.connections.iter() {
+                    let mut statistic = Statistic::new(self.parameter.clone());
+                    statistic.connection_id = Some(connection.connection_id);
+                    statistic.start_timestamp = self.start_timestamp;
+                    statistic.end_timestamp = self.end_timestamp;
+                    statistic.test_duration = self.test_duration;
+                    statistic.amount_datagrams = connection.amount_datagrams;
+                    statistic.amount_data_bytes = connection.amount_data_bytes;
+                    statistic.amount_reordered_datagrams = connection.amount_reordered_datagrams;
+                    statistic.amount_duplicated_datagrams = connection.amount_duplicated_datagrams;
+                    statistic.amount_omitted_datagrams = connection.amount_omitted_datagrams;
+                    statistic.termination_reason = self.termination_reason;
+                    statistic.print(output_format, false);
                 }
             }
         }
@@ -324,11 +420,33 @@ impl Statistic {
     
     fn calculate_data_rate(&self) -> f64{
         let elapsed_time_in_seconds = self.test_duration;
+        if elapsed_time_in_seconds <= 0.0 {
+            return 0.0;
+        }
         ( self.total_data_gbyte / elapsed_time_in_seconds ) * 8.0
     }
     
+    fn calculate_cpu_time_per_gbit(&self) -> f64 {
+        if self.data_rate_gbit <= 0.0 {
+            return 0.0;
+        }
+        (self.cpu_total_time / 100.0) / self.data_rate_gbit
+    }
+
+    fn calculate_packet_rate(&self) -> f64 {
+        if self.test_duration <= 0.0 {
+            return 0.0;
+        }
+        self.amount_datagrams as f64 / self.test_duration
+    }
+
+    // The omitted datagrams never arrived, so they are part of the expected datagrams, and the loss stays below 100%
     fn calculate_packet_loss(&self) -> f64 {
-        (self.amount_omitted_datagrams as f64 / self.amount_datagrams as f64) * 100.0
+        let amount_expected_datagrams = self.amount_datagrams as i64 + self.amount_omitted_datagrams;
+        if amount_expected_datagrams <= 0 {
+            return 0.0;
+        }
+        (self.amount_omitted_datagrams as f64 / amount_expected_datagrams as f64) * 100.0
     }
     
     pub fn set_test_duration(&mut self, start_time: Option<f64>, end_time: Option<f64>) {
@@ -343,25 +461,15 @@ impl Add for Statistic {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
-        // Check if one is zero, to avoid division by zero
-        let data_rate_gbit = if self.data_rate_gbit == 0.0 {
-            other.data_rate_gbit
-        } else if other.data_rate_gbit == 0.0 {
-            self.data_rate_gbit
+        // A thread without any datagrams doesn't decide how the others ended, otherwise the timeout of one thread marks the whole statistic
+        let termination_reason = if self.amount_datagrams == 0 {
+            other.termination_reason
+        } else if other.amount_datagrams == 0 {
+            self.termination_reason
         } else {
-            ( self.data_rate_gbit + other.data_rate_gbit ) / 2.0 // Data rate is the average of both
+            std::cmp::max(self.termination_reason, other.termination_reason)
         };
 
-        // Check if one is zero, to avoid division by zero
-        let packet_loss = if self.packet_loss == 0.0 {
-            other.packet_loss
-        } else if other.packet_loss == 0.0 {
-            self.packet_loss
-        } else {
-            ( self.packet_loss + other.packet_loss ) / 2.0 // Average of packet loss
-        };
-
-
         // Add the arrays field by field
         let (uring_inflight_utilization, uring_sq_utilization, uring_cq_utilization) = 
             if self.parameter.uring_parameter.record_utilization {
@@ -390,7 +498,28 @@ impl Add for Statistic {
                 (None, None, None)
             };
 
-        Statistic {
+        // Statistics of the same connection from different threads or intervals are summed up
+        let mut connections = self.connections;
+        for connection in other.connections {
+            match connections.binary_search_by_key(&connection.connection_id, |x| x.connection_id) {
+                Ok(index) => connections[index] = connections[index].clone() + connection,
+                Err(index) => connections.insert(index, connection)
+            }
+        }
+
+        let mut sockets = self.sockets;
+        for socket in other.sockets {
+            match sockets.binary_search_by_key(&socket.port, |x| x.port) {
+                Ok(index) => sockets[index] = sockets[index].clone() + socket,
+                Err(index) => sockets.insert(index, socket)
+            }
+        }
+
+        let mut threads = self.threads;
+        threads.extend(other.threads);
+        threads.sort_by_key(|thread| thread.thread_index);
+
+        let mut statistic = Statistic {
             parameter: self.parameter, // Assumption is that both statistics have the same test parameters
             start_timestamp: f64::min(self.start_timestamp, other.start_timestamp),
             end_timestamp: f64::max(self.end_timestamp, other.end_timestamp),
@@ -402,22 +531,188 @@ impl Add for Statistic {
             amount_reordered_datagrams: self.amount_reordered_datagrams + other.amount_reordered_datagrams,
             amount_duplicated_datagrams: self.amount_duplicated_datagrams + other.amount_duplicated_datagrams,
             amount_omitted_datagrams: self.amount_omitted_datagrams + other.amount_omitted_datagrams,
+            amount_foreign_datagrams: self.amount_foreign_datagrams + other.amount_foreign_datagrams,
             amount_syscalls: self.amount_syscalls + other.amount_syscalls,
             amount_io_model_calls: self.amount_io_model_calls + other.amount_io_model_calls,
             amount_eagain: self.amount_eagain + other.amount_eagain,
-            data_rate_gbit, 
-            packet_loss,
+            data_rate_gbit: 0.0,
+            packets_per_second: 0.0,
+            packet_loss: 0.0,
             cpu_user_time: 0.0,
             cpu_system_time: 0.0,
             cpu_total_time: 0.0,
+            cpu_time_per_gbit: 0.0,
+            termination_reason,
+            thread_start_spread: f64::max(self.thread_start_spread, other.thread_start_spread),
+            thread_index: None,
+            test_id: None,
+            port: None,
+            core: None,
+            connection_id: None,
             uring_cq_overflows: self.uring_cq_overflows + other.uring_cq_overflows,
             uring_out_of_buffers: self.uring_out_of_buffers + other.uring_out_of_buffers,
             uring_copied_zc: self.uring_copied_zc + other.uring_copied_zc,
             uring_canceled_multishot: self.uring_canceled_multishot + other.uring_canceled_multishot,
             uring_sq_utilization,
             uring_cq_utilization,
-            uring_inflight_utilization
+            uring_inflight_utilization,
+            connections,
+            sockets,
+            threads
+        };
+        // Rates and loss are recomputed from the summed up totals, averaging them would weigh every thread the same
+        statistic.calculate_statistics();
+        statistic
+    }
+}
+
+
+// Single record of a test, combining the final statistic of the sender with the one the receiver returns over the control channel
+#[derive(Debug, Serialize, Clone)]
+pub struct CombinedStatistic {
+    #[serde(flatten)]
+    pub parameter: Parameter,
+    pub start_timestamp: f64,
+    pub end_timestamp: f64,
+    pub test_duration: f64,
+    pub sender_amount_datagrams: u64,
+    pub receiver_amount_datagrams: u64,
+    pub sender_data_rate_gbit: f64,
+    pub receiver_data_rate_gbit: f64,
+    pub amount_lost_datagrams: u64,
+    pub packet_loss: f64,
+    pub amount_reordered_datagrams: u64,
+    pub amount_duplicated_datagrams: u64,
+    pub sender_cpu_user_time: f64,
+    pub sender_cpu_system_time: f64,
+    pub sender_cpu_total_time: f64,
+    pub receiver_cpu_user_time: f64,
+    pub receiver_cpu_system_time: f64,
+    pub receiver_cpu_total_time: f64,
+    pub sender_cpu_time_per_gbit: f64,
+    pub receiver_cpu_time_per_gbit: f64,
+    pub sender_termination_reason: TerminationReason,
+    pub receiver_termination_reason: TerminationReason,
+}
+
+impl CombinedStatistic {
+    pub fn new(parameter: Parameter, mut sender: Statistic, mut receiver: Statistic) -> CombinedStatistic {
+        sender.calculate_statistics();
+        receiver.calculate_statistics();
+
+        // Duplicates are received multiple times, but only sent once
+        let amount_unique_datagrams = receiver.amount_datagrams.saturating_sub(receiver.amount_duplicated_datagrams);
+        let amount_lost_datagrams = sender.amount_datagrams.saturating_sub(amount_unique_datagrams);
+        let packet_loss = if sender.amount_datagrams == 0 { 0.0 } else { amount_lost_datagrams as f64 / sender.amount_datagrams as f64 * 100.0 };
+
+        CombinedStatistic {
+            start_timestamp: sender.start_timestamp,
+            end_timestamp: sender.end_timestamp,
+            test_duration: sender.test_duration,
+            sender_amount_datagrams: sender.amount_datagrams,
+            receiver_amount_datagrams: receiver.amount_datagrams,
+            sender_data_rate_gbit: sender.data_rate_gbit,
+            receiver_data_rate_gbit: receiver.data_rate_gbit,
+            amount_lost_datagrams,
+            packet_loss,
+            amount_reordered_datagrams: receiver.amount_reordered_datagrams,
+            amount_duplicated_datagrams: receiver.amount_duplicated_datagrams,
+            sender_cpu_user_time: sender.cpu_user_time,
+            sender_cpu_system_time: sender.cpu_system_time,
+            sender_cpu_total_time: sender.cpu_total_time,
+            receiver_cpu_user_time: receiver.cpu_user_time,
+            receiver_cpu_system_time: receiver.cpu_system_time,
+            receiver_cpu_total_time: receiver.cpu_total_time,
+            sender_cpu_time_per_gbit: sender.cpu_time_per_gbit,
+            receiver_cpu_time_per_gbit: receiver.cpu_time_per_gbit,
+            sender_termination_reason: sender.termination_reason,
+            receiver_termination_reason: receiver.termination_reason,
+            parameter,
+        }
+    }
+
+    pub fn print(&self, output_format: OutputFormat) {
+        match output_format {
+            OutputFormat::Json => {
+                println!("{}", serde_json::to_string(&self).unwrap());
+            },
+            OutputFormat::Text => {
+                println!("------------------------");
+                println!("Combined Summary Measurement");
+                println!("------------------------");
+                if self.parameter.bidirectional {
+                    println!("Direction: {}", if self.parameter.reverse { "receiver to sender" } else { "sender to receiver" });
+                }
+                println!("Total time: {:.2}s", self.test_duration);
+                println!("Sender data rate: {:.2} Gibit/s", self.sender_data_rate_gbit);
+                println!("Receiver data rate: {:.2} Gibit/s", self.receiver_data_rate_gbit);
+                println!("Packet loss: {:.2}% ({}/{})", self.packet_loss, self.amount_lost_datagrams, self.sender_amount_datagrams);
+                println!("------------------------");
+                println!("Amount of sent datagrams: {}", self.sender_amount_datagrams);
+                println!("Amount of received datagrams: {}", self.receiver_amount_datagrams);
+                println!("Amount of reordered datagrams: {}", self.amount_reordered_datagrams);
+                println!("Amount of duplicated datagrams: {}", self.amount_duplicated_datagrams);
+                println!("Sender/receiver termination reason: {:?} / {:?}", self.sender_termination_reason, self.receiver_termination_reason);
+                println!("------------------------");
+                println!("Sender CPU user/system/total: {:.2}% / {:.2}% / {:.2}%", self.sender_cpu_user_time, self.sender_cpu_system_time, self.sender_cpu_total_time);
+                println!("Receiver CPU user/system/total: {:.2}% / {:.2}% / {:.2}%", self.receiver_cpu_user_time, self.receiver_cpu_system_time, self.receiver_cpu_total_time);
+                println!("Sender/receiver CPU time per Gibit: {:.3}s / {:.3}s", self.sender_cpu_time_per_gbit, self.receiver_cpu_time_per_gbit);
+                println!("------------------------");
+            },
+            OutputFormat::File => {
+                // Combined records have different columns, so they are saved next to the records of the sender
+                let mut file_name = self.parameter.output_file_path.file_stem().unwrap_or_default().to_os_string();
+                file_name.push("-combined");
+                write_csv_record(&self.parameter.output_file_path.with_file_name(file_name), self)
+            }
+        }
+    }
+}
+
+// The sessions of a daemon write their records to the same file, so the check for the header and the append must not interleave
+static CSV_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+// Appends the record to the CSV file. The header is only written, if the file is empty.
+fn write_csv_record<T: Serialize>(output_file_path: &path::Path, record: &T) {
+    let _lock = CSV_FILE_LOCK.lock().unwrap_or_else(|x| x.into_inner());
+    let mut output_file = output_file_path.to_path_buf();
+    output_file.set_extension("csv");
+
+    // Check if the output dir exists. If not, try to create it
+    if let Some(parent_dir) = output_file.parent() {
+        if !parent_dir.exists() {
+            if let Err(err) = std::fs::create_dir_all(parent_dir) {
+                error!("Failed to create output directory: {:?}", err);
+                return;
+            } else {
+                debug!("Output directory created: {:?}", parent_dir);
+            }
+        }
+    }
+    let file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&output_file);
+
+    if let Ok(file) = file {
+        // Check if the file exists is empty
+        let is_empty = file.metadata().map(|metadata| metadata.len() == 0).unwrap_or(true);
+
+        // Use csv writer to write the results to a file
+        let mut wtr = if is_empty {
+            // If the file is empty, use automatically write the header and data
+            csv::Writer::from_writer(file)
+        } else {
+            // If the file is not empty, manually write the data without the header
+            csv::WriterBuilder::new().has_headers(false).from_writer(file)
+        };
+
+        match wtr.serialize(record).and_then(|_| wtr.flush().map_err(csv::Error::from)) {
+            Ok(_) => info!("Results saved to {}", output_file.display()),
+            Err(x) => error!("Failed to write results to {}: {}", output_file.display(), x)
         }
+    } else {
+        error!("Failed to create file: {}", output_file.display());
     }
 }
 
@@ -429,27 +724,82 @@ impl Measurement {
             end_time: Statistic::get_unix_timestamp(),
             statistic: Statistic::new(parameter),
             first_packet_received: false,
-            last_packet_received: false,
+            next_packet_ids: vec![0; crate::MAX_TEST_ID],
         }
     }
+
+    // The receiver creates the measurements of all connections before the test, so none is allocated while receiving
+    pub fn for_connections(parameter: &Parameter) -> Vec<Measurement> {
+        vec![Measurement::new(parameter.clone()); parameter.amount_connections.max(1) as usize]
+    }
+
+    // Returns the measurement of the connection, which is started with its first MEASUREMENT message
+    pub fn start(measurements: &mut [Measurement], connection_id: usize) -> &mut Measurement {
+        let measurement = &mut measurements[connection_id];
+
+        // Start measurement timer with receiving of the first MEASUREMENT message
+        if !measurement.first_packet_received {
+            info!("{:?}: First packet received from connection {}!", thread::current().id(), connection_id);
+            let start_time = Statistic::get_unix_timestamp();
+            measurement.start_time = start_time;
+            measurement.statistic.set_start_timestamp(Some(start_time));
+            measurement.first_packet_received = true;
+        }
+        measurement
+    }
+
+    // The test ID is checked against the maximum before
+    pub fn next_packet_id(&self, test_id: usize) -> u64 {
+        self.next_packet_ids[test_id]
+    }
+
+    // Statistic of the measurement, which keeps track of the connection it belongs to
+    pub fn connection_statistic(&self, connection_id: usize) -> Statistic {
+        let mut statistic = self.statistic.clone();
+        if !self.first_packet_received {
+            return statistic;
+        }
+
+        statistic.connections = vec![ConnectionStatistic {
+            connection_id: connection_id as u64,
+            amount_datagrams: statistic.amount_datagrams,
+            amount_data_bytes: statistic.amount_data_bytes,
+            amount_reordered_datagrams: statistic.amount_reordered_datagrams,
+            amount_duplicated_datagrams: statistic.amount_duplicated_datagrams,
+            amount_omitted_datagrams: statistic.amount_omitted_datagrams,
+        }];
+        statistic
+    }
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Parameter {
     pub test_name: String,
     pub run_name: String,
     pub repetition_id: u16,
     pub mode: super::NPerfMode,
-    pub ip: std::net::Ipv4Addr,
+    pub reverse: bool,
+    pub bidirectional: bool,
+    pub ip: std::net::IpAddr,
+    pub source_ip: Option<std::net::IpAddr>,
+    pub interface: Option<String>,
+    pub transport: super::Transport,
     pub amount_threads: u16,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub output_interval: f64,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub output_format: OutputFormat,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub output_file_path: path::PathBuf,
     pub io_model: super::IOModel,
+    pub epoll_trigger: super::EpollTrigger,
+    pub epoll_exclusive: bool,
     pub test_runtime_length: u64,
+    // Local settings of the receiver, in milliseconds
+    #[serde(skip_serializing, default)]
+    pub receive_start_timeout: u64,
+    #[serde(skip_serializing, default)]
+    pub receive_idle_timeout: u64,
     pub mss: u32,
     pub datagram_size: u32,
     pub packet_buffer_size: usize,
@@ -458,7 +808,8 @@ pub struct Parameter {
     pub exchange_function: super::ExchangeFunction,
     pub multiplex_port: MultiplexPort,
     pub multiplex_port_receiver: MultiplexPort,
-    pub simulate_connection: SimulateConnection,
+    pub amount_connections: u32,
+    pub sockets_per_thread: u16,
     pub core_affinity: bool,
     pub numa_affinity: bool,
     #[serde(flatten)]
@@ -472,13 +823,22 @@ impl Parameter {
         run_name: String,
         repetition_id: u16,
         mode: super::NPerfMode, 
-        ip: std::net::Ipv4Addr, 
+        reverse: bool,
+        bidirectional: bool,
+        ip: std::net::IpAddr, 
+        source_ip: Option<std::net::IpAddr>,
+        interface: Option<String>,
+        transport: super::Transport,
         amount_threads: u16, 
         output_interval: f64,
         output_format: OutputFormat, 
         output_file_path: path::PathBuf,
         io_model: super::IOModel, 
+        epoll_trigger: super::EpollTrigger,
+        epoll_exclusive: bool,
         test_runtime_length: u64, 
+        receive_start_timeout: u64,
+        receive_idle_timeout: u64,
         mss: u32, 
         datagram_size: u32, 
         packet_buffer_size: usize, 
@@ -486,7 +846,8 @@ impl Parameter {
         exchange_function: super::ExchangeFunction, 
         multiplex_port: MultiplexPort, 
         multiplex_port_receiver: MultiplexPort, 
-        simulate_connection: SimulateConnection, 
+        amount_connections: u32, 
+        sockets_per_thread: u16,
         core_affinity: bool, 
         numa_affinity: bool, 
         uring_parameter: UringParameter
@@ -496,13 +857,22 @@ impl Parameter {
             run_name,
             repetition_id,
             mode,
+            reverse,
+            bidirectional,
             ip,
+            source_ip,
+            interface,
+            transport,
             amount_threads,
             output_interval,
             output_format,
             output_file_path,
             io_model,
+            epoll_trigger,
+            epoll_exclusive,
             test_runtime_length,
+            receive_start_timeout,
+            receive_idle_timeout,
             mss,
             datagram_size,
             packet_buffer_size,
@@ -510,15 +880,114 @@ impl Parameter {
             exchange_function,
             multiplex_port,
             multiplex_port_receiver,
-            simulate_connection,
+            amount_connections,
+            sockets_per_thread,
             core_affinity,
             numa_affinity,
             uring_parameter
         }
     }
+
+    // Every thread uses a block of consecutive data ports, one for each of its sockets
+    pub fn amount_ports(&self) -> u16 {
+        self.amount_threads * self.sockets_per_thread
+    }
+
+    // Checks if the parameter set pushed by the sender over the control channel can be honored locally
+    fn check_sender_parameter(&self, sender_parameter: &Parameter) -> Result<(), &'static str> {
+        if sender_parameter.mode != super::NPerfMode::Sender {
+            return Err("Received parameter set doesn't belong to a sender");
+        }
+
+        if sender_parameter.datagram_size > crate::MAX_UDP_DATAGRAM_SIZE {
+            error!("Sender uses UDP datagram size {}, but the maximum is {}", sender_parameter.datagram_size, crate::MAX_UDP_DATAGRAM_SIZE);
+            return Err("UDP datagram size of the sender is too big");
+        }
+
+        if !sender_parameter.uring_parameter.ring_size.is_power_of_two() || sender_parameter.uring_parameter.ring_size > crate::URING_MAX_RING_SIZE {
+            error!("Sender uses uring ring size {}, but the receiver supports only powers of 2 up to {}", sender_parameter.uring_parameter.ring_size, crate::URING_MAX_RING_SIZE);
+            return Err("Uring ring size of the sender is not supported");
+        }
+
+        if self.output_interval > 0.0 && (self.output_interval * (sender_parameter.test_runtime_length as f64 / self.output_interval).round() - sender_parameter.test_runtime_length as f64).abs() > 1e-9 {
+            error!("Interval {} doesn't fit perfect in the test duration {} of the sender", self.output_interval, sender_parameter.test_runtime_length);
+            return Err("Interval doesn't fit in the test duration of the sender");
+        }
+
+        Ok(())
+    }
+
+    // Configures the receiver from the parameter set of the sender. In reverse mode, the sender configures its own receiving side with it.
+    // Only the listen address, source address, interface, output, receive timeout and affinity settings of the local command line are kept.
+    pub fn configure_receiver(&self, sender_parameter: Parameter) -> Result<Parameter, &'static str> {
+        self.check_sender_parameter(&sender_parameter)?;
+
+        let mut uring_parameter = sender_parameter.uring_parameter;
+        // Zero copy is only available for sending, so the receiver falls back to the normal io_uring mode
+        if uring_parameter.uring_mode == UringMode::Zerocopy {
+            uring_parameter.uring_mode = UringMode::Normal;
+        }
+
+        // Multicast receivers bind to the group address, so they only receive the traffic of the group
+        let multicast_group = Some(sender_parameter.ip).filter(|ip| ip.is_multicast());
+
+        Ok(Parameter {
+            mode: super::NPerfMode::Receiver,
+            ip: multicast_group.unwrap_or(self.ip),
+            source_ip: self.source_ip,
+            interface: self.interface.clone(),
+            output_interval: self.output_interval,
+            output_format: self.output_format,
+            output_file_path: self.output_file_path.clone(),
+            receive_start_timeout: self.receive_start_timeout,
+            receive_idle_timeout: self.receive_idle_timeout,
+            socket_options: sender_parameter.socket_options.for_receiver(sender_parameter.multiplex_port_receiver == MultiplexPort::Sharding, multicast_group),
+            core_affinity: self.core_affinity,
+            numa_affinity: self.numa_affinity,
+            uring_parameter,
+            ..sender_parameter
+        })
+    }
+
+    // In reverse mode, the receiver transmits with the parameter set of the sender to the address the hole punches arrive from
+    pub fn configure_reverse_sender(&self, sender_parameter: Parameter) -> Result<Parameter, &'static str> {
+        self.check_sender_parameter(&sender_parameter)?;
+
+        Ok(Parameter {
+            ip: self.ip,
+            source_ip: self.source_ip,
+            interface: self.interface.clone(),
+            output_interval: self.output_interval,
+            output_format: self.output_format,
+            output_file_path: self.output_file_path.clone(),
+            core_affinity: self.core_affinity,
+            numa_affinity: self.numa_affinity,
+            ..sender_parameter
+        })
+    }
+
+    // In bidirectional mode, the receiver measures the traffic of the sender and transmits to it at the same time.
+    // Returns the parameter sets of the receiving and the transmitting direction.
+    pub fn configure_bidirectional_receiver(&self, sender_parameter: Parameter) -> Result<(Parameter, Parameter), &'static str> {
+        let receiver_parameter = self.configure_receiver(sender_parameter.clone())?;
+        let sender_parameter = Parameter {
+            reverse: true,
+            ..self.configure_reverse_sender(sender_parameter)?
+        };
+
+        Ok((receiver_parameter, sender_parameter))
+    }
+
+    // Provided buffers and multishot only exist for receiving. The sender keeps them to push them to the receiver, but sends in normal mode itself.
+    pub fn configure_sending(mut self) -> Parameter {
+        if self.uring_parameter.uring_mode == UringMode::ProvidedBuffer || self.uring_parameter.uring_mode == UringMode::Multishot {
+            self.uring_parameter.uring_mode = UringMode::Normal;
+        }
+        self
+    }
 }
 
-#[derive(Debug, Serialize, Copy, Clone)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone)]
 pub struct UringParameter {
     pub uring_mode: UringMode,
     pub ring_size: u32,
@@ -580,7 +1049,7 @@ pub mod utilization {
 
 
 pub mod serialize_option_as_bool {
-    use serde::Serializer;
+    use serde::{Deserialize, Deserializer, Serializer};
 
     // This function will be used to serialize Option<u32> fields
     pub fn serialize<S>(option: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error>
@@ -592,4 +1061,23 @@ pub mod serialize_option_as_bool {
             None => serializer.serialize_bool(false),
         }
     }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OptionAsBool {
+        Value(u32),
+        Disabled(bool),
+    }
+
+    // Reverses serialize(), so that the Parameter can be sent to the receiver over the control channel
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match OptionAsBool::deserialize(deserializer)? {
+            OptionAsBool::Value(value) => Ok(Some(value)),
+            OptionAsBool::Disabled(false) => Ok(None),
+            OptionAsBool::Disabled(true) => Err(serde::de::Error::custom("expected a value or false")),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::interrupt;
+
+// Drives the end of the test and the interval boundaries of all node threads of a test from its own thread.
+// The hot loops of the nodes only load its atomics, instead of reading the clock with every iteration.
+#[derive(Debug)]
+pub struct TestTimer {
+    // Only the transmitting side ends the test after its duration, the receiving side is stopped by the executor
+    runtime_millis: Option<u64>,
+    interval_millis: u64,
+    // Coarse clock in milliseconds since the start of the test, updated every tick
+    elapsed_millis: AtomicU64,
+    // Clock of the timer, when the measurement window was opened over the control channel, or u64::MAX before.
+    // The duration and the interval boundaries are counted from it, so the intervals of both sides cover the same periods.
+    measurement_start_millis: AtomicU64,
+    amount_intervals: AtomicU64,
+    expired: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+impl TestTimer {
+    // Only the side opening the measurement window starts it with its own clock, every other side waits for it over the control channel
+    pub fn new(runtime_length: Option<u64>, wait_for_measurement: bool, output_interval: f64) -> Arc<TestTimer> {
+        Arc::new(TestTimer {
+            runtime_millis: runtime_length.map(|runtime_length| runtime_length * 1000),
+            // The interval is given in seconds, rounded to milliseconds
+            interval_millis: (output_interval * 1000.0).round() as u64,
+            elapsed_millis: AtomicU64::new(0),
+            measurement_start_millis: AtomicU64::new(if wait_for_measurement { u64::MAX } else { 0 }),
+            amount_intervals: AtomicU64::new(0),
+            expired: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+        })
+    }
+
+    // Starts the clock of the test. The thread runs until the timer is cancelled.
+    pub fn start(timer: &Arc<TestTimer>) -> thread::JoinHandle<()> {
+        let timer = Arc::clone(timer);
+        let start = Instant::now();
+        thread::spawn(move || timer.run(start))
+    }
+
+    fn run(&self, start: Instant) {
+        while !self.cancelled.load(Ordering::Relaxed) {
+            let elapsed_millis = start.elapsed().as_millis() as u64;
+            self.elapsed_millis.store(elapsed_millis, Ordering::Relaxed);
+
+            // The next wakeup is the next tick, unless an interval boundary or the end of the test comes first
+            let mut next_wakeup = elapsed_millis + crate::TIMER_TICK;
+            let measurement_start_millis = self.measurement_start_millis.load(Ordering::Relaxed);
+            if let Some(amount_intervals) = elapsed_millis.checked_sub(measurement_start_millis).and_then(|measurement_millis| measurement_millis.checked_div(self.interval_millis)) {
+                self.amount_intervals.store(amount_intervals, Ordering::Relaxed);
+                next_wakeup = next_wakeup.min(measurement_start_millis + (amount_intervals + 1) * self.interval_millis);
+            }
+            let runtime_end_millis = self.runtime_millis.map(|runtime_millis| measurement_start_millis.saturating_add(runtime_millis));
+            if runtime_end_millis.is_some_and(|runtime_end_millis| elapsed_millis >= runtime_end_millis) || interrupt::interrupted() {
+                self.expired.store(true, Ordering::Relaxed);
+            } else if let Some(runtime_end_millis) = runtime_end_millis {
+                next_wakeup = next_wakeup.min(runtime_end_millis);
+            }
+
+            thread::park_timeout(Duration::from_millis(next_wakeup).saturating_sub(start.elapsed()));
+        }
+    }
+
+    // Stops the thread of the timer, which is unparked to notice it
+    pub fn cancel(&self, handle: thread::JoinHandle<()>) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        handle.thread().unpark();
+        handle.join().unwrap_or_else(|x| log::warn!("Error joining timer thread: {:?}", x));
+    }
+
+    // Ends the test before its duration, if the remote host stopped it
+    pub fn stop(&self) {
+        self.expired.store(true, Ordering::Relaxed);
+    }
+
+    // Set after the duration of the test, or if it was interrupted or stopped
+    #[inline(always)]
+    pub fn expired(&self) -> bool {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    #[inline(always)]
+    pub fn elapsed_millis(&self) -> u64 {
+        self.elapsed_millis.load(Ordering::Relaxed)
+    }
+
+    // Called as soon as the sender opened the measurement window
+    pub fn start_measurement(&self) {
+        self.measurement_start_millis.store(self.elapsed_millis(), Ordering::Relaxed);
+    }
+
+    // The transmitting threads of a reverse test wait for it, so they don't send before the measuring side opened the measurement window
+    pub fn wait_for_measurement(&self) {
+        while self.measurement_elapsed_millis().is_none() && !self.expired() {
+            thread::sleep(Duration::from_millis(crate::TIMER_TICK));
+        }
+    }
+
+    // Milliseconds since the start of the measurement window, which is None as long as the handshake on the control channel isn't finished
+    #[inline(always)]
+    pub fn measurement_elapsed_millis(&self) -> Option<u64> {
+        let measurement_start_millis = self.measurement_start_millis.load(Ordering::Relaxed);
+        (measurement_start_millis != u64::MAX).then(|| self.elapsed_millis().saturating_sub(measurement_start_millis))
+    }
+
+    // Amount of output intervals, which finished since the start of the measurement window
+    #[inline(always)]
+    pub fn amount_intervals(&self) -> u64 {
+        self.amount_intervals.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,33 @@
+mod common;
+
+#[test]
+fn af_xdp_loopback() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=48801".to_string(), "--interface=lo".to_string()]));
+
+    let args = vec!["sender", "--ip=127.0.0.1", "--port=48801", "--io-model=af-xdp"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn af_xdp_invalid() -> Result<(), Box<dyn std::error::Error>>{
+    for args in [
+        vec!["sender", "--ip=::1", "--io-model=af-xdp"],
+        vec!["sender", "--io-model=af-xdp", "--with-gsro"],
+        vec!["sender", "--io-model=af-xdp", "--reverse"],
+        vec!["sender", "--io-model=af-xdp", "--multiplex-port=sharing"],
+        vec!["sender", "--io-model=af-xdp", "--multiplex-port-receiver=sharding"],
+        vec!["sender", "--io-model=af-xdp", "--sockets-per-thread=2"],
+        vec!["sender", "--io-model=af-xdp", "--datagram-size=4000"],
+    ] {
+        let nperf = nperf::nPerf::new().set_args(args);
+        assert!(nperf.parse_parameter().is_none());
+    }
+    Ok(())
+}
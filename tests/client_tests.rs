@@ -43,4 +43,19 @@ fn test_sender_sendmmsg() -> Result<(), Box<dyn std::error::Error>>{
 
     handle.join().unwrap();
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_sender_hostname_and_source_ip() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=45301".to_string()]));
+
+    let args = vec!["sender", "--ip=localhost", "--source-ip=127.0.0.1", "--port=45301"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
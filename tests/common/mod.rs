@@ -1,4 +1,8 @@
-use std::{process::Command, thread::{self, JoinHandle}};
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use assert_cmd::prelude::*;
 
 #[allow(dead_code)]
@@ -27,4 +31,55 @@ pub fn start_nperf_sender(args: Option<Vec<String>>) -> JoinHandle<()> {
         }
         cmd.output().unwrap();
     })
-}
\ No newline at end of file
+}
+
+#[allow(dead_code)]
+pub fn start_nperf_daemon(args: Option<Vec<String>>) -> Child {
+    let mut cmd = Command::cargo_bin("nperf").unwrap();
+    cmd.arg("receiver").arg("--daemon");
+    for arg in args.unwrap_or_default() {
+        cmd.arg(arg);
+    }
+    let child = cmd.spawn().unwrap();
+
+    std::thread::sleep(std::time::Duration::from_secs(2)); // Wait for daemon to start
+    child
+}
+
+// An nPerf process, whose log is read line by line, to know when it reached a certain state of the test
+#[allow(dead_code)]
+pub struct NperfProcess {
+    pub child: Child,
+    log_lines: mpsc::Receiver<String>,
+}
+
+#[allow(dead_code)]
+impl NperfProcess {
+    pub fn spawn(args: &[&str], stdout: Stdio) -> NperfProcess {
+        let mut child = Command::cargo_bin("nperf").unwrap().args(args).env("RUST_LOG", "info").stdout(stdout).stderr(Stdio::piped()).spawn().unwrap();
+
+        // The log is drained until the process exits, so it never blocks on a full pipe
+        let (sender, log_lines) = mpsc::channel();
+        let stderr = BufReader::new(child.stderr.take().unwrap());
+        thread::spawn(move || stderr.lines().map_while(Result::ok).for_each(|line| { let _ = sender.send(line); }));
+
+        NperfProcess { child, log_lines }
+    }
+
+    // Panics, if the line doesn't show up in the log within 10 seconds
+    pub fn wait_for_log(&self, pattern: &str) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut log = Vec::new();
+        loop {
+            match self.log_lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) if line.contains(pattern) => return,
+                Ok(line) => log.push(line),
+                Err(_) => panic!("nPerf didn't log \"{}\", but:\n{}", pattern, log.join("\n"))
+            }
+        }
+    }
+
+    pub fn signal(&self, signal: libc::c_int) {
+        unsafe { libc::kill(self.child.id() as libc::pid_t, signal) };
+    }
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use std::process::{Command, Stdio};
+use assert_cmd::prelude::*;
+
+#[test]
+fn many_connections_few_threads() -> Result<(), Box<dyn std::error::Error>>{
+    let receiver = Command::cargo_bin("nperf")?.args(["receiver", "--port=48001", "--parallel=2"]).stdout(Stdio::piped()).spawn()?;
+    std::thread::sleep(std::time::Duration::from_secs(2)); // Wait for receiver to start
+
+    let args = vec!["sender", "--port=48001", "--parallel=2", "--connections=5", "--exchange-function=mmsg"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    // The receiver accounts every connection on its own, although both threads carry several connections
+    let output = String::from_utf8(receiver.wait_with_output()?.stdout)?;
+    for connection_id in 0..5 {
+        assert!(output.contains(&format!("[{:3}]", connection_id)));
+    }
+    Ok(())
+}
+
+#[test]
+fn connections_reported_to_sender() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=49701".to_string(), "--parallel=2".to_string()]));
+
+    // The sender gets the connections of the receiver with its final statistic and writes a record per connection
+    let sender = Command::cargo_bin("nperf")?.args(["sender", "--port=49701", "--parallel=2", "--connections=3", "--time=3", "--output-format=json"]).stdout(Stdio::piped()).spawn()?;
+    let output = String::from_utf8(sender.wait_with_output()?.stdout)?;
+    for connection_id in 0..3 {
+        assert!(output.contains(&format!("\"connection_id\":{}", connection_id)));
+    }
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn one_connection_many_threads() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=48101".to_string()]));
+
+    let args = vec!["sender", "--port=48101", "--parallel=4", "--connections=1", "--multiplex-port-receiver=sharing"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn connections_invalid() -> Result<(), Box<dyn std::error::Error>>{
+    let args = vec!["sender", "--connections=0"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    assert!(nperf.parse_parameter().is_none());
+
+    let args = vec!["sender", "--transport=tcp", "--connections=4"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    assert!(nperf.parse_parameter().is_none());
+    Ok(())
+}
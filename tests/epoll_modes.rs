@@ -0,0 +1,31 @@
+mod common;
+
+#[test]
+fn epoll_level_triggered() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=48201".to_string()]));
+
+    let args = vec!["sender", "--port=48201", "--parallel=2", "--io-model=epoll"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn epoll_edge_triggered_exclusive_sharing() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=48301".to_string()]));
+
+    let args = vec!["sender", "--port=48301", "--parallel=2", "--io-model=epoll", "--epoll-trigger=edge", "--epoll-exclusive", "--exchange-function=mmsg", "--multiplex-port-receiver=sharing"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -45,3 +45,18 @@ fn gso_gro() -> Result<(), Box<dyn std::error::Error>>{
     handle.join().unwrap();
     Ok(())
 }
+
+#[test]
+fn gso_gro_ipv6() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--ip=::".to_string(), "--port=45301".to_string()]));
+
+    let args = vec!["sender", "--ip=::1", "--with-gsro", "--port=45301"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
@@ -1,6 +1,10 @@
 mod common;
 
-// Test sender send/sendmsg/sendmmsg with receiver send/sendmsg/sendmmsg in different combinations
+use std::process::{Command, Stdio};
+use assert_cmd::prelude::*;
+
+// Test sender send/sendmsg/sendmmsg with receiver send/sendmsg/sendmmsg in different combinations.
+// The sender pushes its exchange function to the receiver, so the receiver always uses the one of the sender.
 
 #[test]
 fn sendmsg_recvmsg() -> Result<(), Box<dyn std::error::Error>>{
@@ -18,8 +22,9 @@ fn sendmsg_recvmsg() -> Result<(), Box<dyn std::error::Error>>{
 }
 
 #[test]
-fn sendmmsg_recvmsg() -> Result<(), Box<dyn std::error::Error>>{
-    let handle = common::start_nperf_receiver(Some(vec!["--port=45101".to_string()]));
+fn sendmmsg_pushed_to_receiver() -> Result<(), Box<dyn std::error::Error>>{
+    let receiver = Command::cargo_bin("nperf")?.args(["receiver", "--port=45101", "--output-format=json"]).stdout(Stdio::piped()).spawn()?;
+    std::thread::sleep(std::time::Duration::from_secs(2)); // Wait for receiver to start
 
     let args = vec!["sender", "--exchange-function=mmsg", "--port=45101"];
     let nperf = nperf::nPerf::new().set_args(args);
@@ -28,7 +33,8 @@ fn sendmmsg_recvmsg() -> Result<(), Box<dyn std::error::Error>>{
         assert!(x.amount_datagrams > 10000);
     };
 
-    handle.join().unwrap();
+    let output = String::from_utf8(receiver.wait_with_output()?.stdout)?;
+    assert!(output.contains("\"exchange_function\":\"Mmsg\""));
     Ok(())
 }
 
@@ -48,8 +54,9 @@ fn sendmmsg_recvmmsg() -> Result<(), Box<dyn std::error::Error>>{
 }
 
 #[test]
-fn sendmsg_recvmmsg() -> Result<(), Box<dyn std::error::Error>>{
-    let handle = common::start_nperf_receiver(Some(vec!["--exchange-function=mmsg".to_string(), "--port=45301".to_string()]));
+fn sendmsg_overrides_receiver_mmsg() -> Result<(), Box<dyn std::error::Error>>{
+    let receiver = Command::cargo_bin("nperf")?.args(["receiver", "--exchange-function=mmsg", "--port=45301", "--output-format=json"]).stdout(Stdio::piped()).spawn()?;
+    std::thread::sleep(std::time::Duration::from_secs(2)); // Wait for receiver to start
 
     let args = vec!["sender", "--port=45301"];
     let nperf = nperf::nPerf::new().set_args(args);
@@ -58,7 +65,129 @@ fn sendmsg_recvmmsg() -> Result<(), Box<dyn std::error::Error>>{
         assert!(x.amount_datagrams > 10000);
     };
 
+    let output = String::from_utf8(receiver.wait_with_output()?.stdout)?;
+    assert!(output.contains("\"exchange_function\":\"Msg\""));
+    Ok(())
+}
+
+
+#[test]
+fn separate_control_port() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=45401".to_string(), "--control-port=47401".to_string()]));
+
+    let args = vec!["sender", "--port=45401", "--control-port=47401"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn receiver_configured_by_sender() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=45501".to_string()]));
+
+    let args = vec!["sender", "--exchange-function=mmsg", "--with-gsro", "--parallel=2", "--port=45501"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn receiver_rejects_parameter() -> Result<(), Box<dyn std::error::Error>>{
+    // An interval of 2s doesn't fit into the test duration of the sender
+    let handle = common::start_nperf_receiver(Some(vec!["--interval=2".to_string(), "--port=45601".to_string()]));
+
+    let args = vec!["sender", "--time=3", "--port=45601"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    assert!(nperf.exec(arguments).is_none());
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn receiver_interval_fits_sender_time() -> Result<(), Box<dyn std::error::Error>>{
+    // An interval of 3s doesn't fit into the default time of the receiver, but into the test duration of the sender
+    let handle = common::start_nperf_receiver(Some(vec!["--interval=3".to_string(), "--port=46401".to_string()]));
+
+    let args = vec!["sender", "--time=6", "--port=46401"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    assert!(nperf.exec(arguments).is_some());
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn reverse_mode() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=45701".to_string()]));
+
+    let args = vec!["sender", "--reverse", "--parallel=2", "--exchange-function=mmsg", "--port=45701"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn bidirectional_mode() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=45801".to_string()]));
+
+    let args = vec!["sender", "--bidir", "--parallel=2", "--port=45801"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
     handle.join().unwrap();
     Ok(())
 }
 
+#[test]
+fn ipv6_loopback() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--ip=::".to_string(), "--port=45901".to_string()]));
+
+    let args = vec!["sender", "--ip=::1", "--parallel=2", "--exchange-function=mmsg", "--port=45901"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn multicast_two_receivers() -> Result<(), Box<dyn std::error::Error>>{
+    // Both receivers join the group on the same data port, but listen on their own control port
+    let handle1 = common::start_nperf_receiver(Some(vec!["--port=46001".to_string(), "--control-port=46011".to_string()]));
+    let handle2 = common::start_nperf_receiver(Some(vec!["--port=46001".to_string(), "--control-port=46012".to_string()]));
+
+    let args = vec!["sender", "--ip=239.1.1.1", "--multicast-receivers=127.0.0.1:46011,127.0.0.1:46012", "--multicast-loop", "--datagram-size=1200", "--with-mss=1200", "--port=46001"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle1.join().unwrap();
+    handle2.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::process::Stdio;
+use common::NperfProcess;
+
+#[test]
+fn sender_interrupted() -> Result<(), Box<dyn std::error::Error>>{
+    let receiver = NperfProcess::spawn(&["receiver", "--port=49101"], Stdio::piped());
+    receiver.wait_for_log("Waiting for a sender");
+
+    let sender = NperfProcess::spawn(&["sender", "--port=49101", "--time=30"], Stdio::piped());
+    receiver.wait_for_log("First packet received");
+    sender.signal(libc::SIGINT);
+
+    // Both sides print the statistics collected until the interrupt, long before the end of the test
+    let sender_output = String::from_utf8(sender.child.wait_with_output()?.stdout)?;
+    assert!(sender_output.contains("Termination reason: Interrupted"));
+    assert!(sender_output.contains("Sender/receiver termination reason: Interrupted / Stopped"));
+
+    let receiver_output = receiver.child.wait_with_output()?;
+    assert!(receiver_output.status.success());
+    assert!(String::from_utf8(receiver_output.stdout)?.contains("Summary Measurement"));
+    Ok(())
+}
+
+#[test]
+fn receiver_interrupted() -> Result<(), Box<dyn std::error::Error>>{
+    let receiver = NperfProcess::spawn(&["receiver", "--port=49201"], Stdio::piped());
+    receiver.wait_for_log("Waiting for a sender");
+
+    let sender = NperfProcess::spawn(&["sender", "--port=49201", "--time=30"], Stdio::piped());
+    receiver.wait_for_log("First packet received");
+    receiver.signal(libc::SIGINT);
+
+    let receiver_output = String::from_utf8(receiver.child.wait_with_output()?.stdout)?;
+    assert!(receiver_output.contains("Termination reason: Interrupted"));
+
+    // The receiver tells the sender to stop, and still returns its statistic
+    let sender_output = String::from_utf8(sender.child.wait_with_output()?.stdout)?;
+    assert!(sender_output.contains("Sender/receiver termination reason: Stopped / Interrupted"));
+    Ok(())
+}
+
+#[test]
+fn reverse_sender_interrupted() -> Result<(), Box<dyn std::error::Error>>{
+    let receiver = NperfProcess::spawn(&["receiver", "--port=49801"], Stdio::null());
+    receiver.wait_for_log("Waiting for a sender");
+
+    // The receiver stops transmitting, as soon as the measuring sender is interrupted, and returns its statistic
+    let sender = NperfProcess::spawn(&["sender", "--port=49801", "--time=30", "--reverse"], Stdio::piped());
+    sender.wait_for_log("First packet received");
+    sender.signal(libc::SIGINT);
+
+    let sender_output = String::from_utf8(sender.child.wait_with_output()?.stdout)?;
+    assert!(sender_output.contains("Sender/receiver termination reason: Stopped / Interrupted"));
+    assert!(receiver.child.wait_with_output()?.status.success());
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use assert_cmd::prelude::*;
+
+#[test]
+fn intervals_printed_during_test() -> Result<(), Box<dyn std::error::Error>>{
+    let mut receiver = Command::cargo_bin("nperf")?.args(["receiver", "--port=49401", "--parallel=2"]).stdout(Stdio::null()).spawn()?;
+    std::thread::sleep(std::time::Duration::from_secs(1)); // Wait for receiver to start
+
+    let mut sender = Command::cargo_bin("nperf")?.args(["sender", "--port=49401", "--parallel=2", "--time=4", "--interval=1"]).stdout(Stdio::piped()).spawn()?;
+    let mut lines = BufReader::new(sender.stdout.take().unwrap()).lines();
+
+    // The first interval of both threads arrives, while the sender is still sending
+    let first_line = lines.next().unwrap()?;
+    let running = sender.try_wait()?.is_none();
+    let remaining_lines: Vec<String> = lines.collect::<Result<_, _>>()?;
+
+    assert!(sender.wait()?.success());
+    assert!(receiver.wait()?.success());
+    assert!(running);
+    assert!(first_line.starts_with("[  1]"));
+    assert!(remaining_lines.iter().any(|line| line.starts_with("[  4]")));
+    Ok(())
+}
@@ -29,3 +29,57 @@ fn multiple_senders_multiple_receiver() -> Result<(), Box<dyn std::error::Error>
     handle.join().unwrap();
     Ok(())
 }
+
+#[test]
+fn threads_start_together() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=49301".to_string(), "--parallel=4".to_string()]));
+
+    let args = vec!["sender", "--parallel=4", "--port=49301", "--io-model=io-uring"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        // All threads are released by one barrier after their io_uring setup
+        assert!(x.thread_start_spread < 500.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn statistic_per_thread() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=49501".to_string(), "--parallel=3".to_string()]));
+
+    let args = vec!["sender", "--parallel=3", "--port=49501"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert_eq!(x.threads.len(), 3);
+        assert_eq!(x.threads.iter().map(|thread| thread.port.unwrap()).collect::<Vec<_>>(), vec![49501, 49502, 49503]);
+        // The aggregate is recomputed from the totals of all threads
+        assert_eq!(x.threads.iter().map(|thread| thread.amount_datagrams).sum::<u64>(), x.amount_datagrams);
+        assert!(x.packets_per_second > 0.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn cpu_per_thread() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=49601".to_string(), "--parallel=2".to_string()]));
+
+    let args = vec!["sender", "--parallel=2", "--port=49601"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert_eq!(x.threads.len(), 2);
+        // Every sending thread keeps a core busy for a part of the test
+        assert!(x.threads.iter().all(|thread| thread.cpu_total_time > 0.0 && thread.cpu_time_per_gbit > 0.0));
+        assert!(x.cpu_time_per_gbit > 0.0);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
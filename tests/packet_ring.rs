@@ -0,0 +1,30 @@
+mod common;
+
+#[test]
+fn packet_ring_loopback() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=48701".to_string()]));
+
+    let args = vec!["sender", "--ip=127.0.0.1", "--port=48701", "--parallel=2", "--io-model=packet-ring", "--exchange-function=mmsg", "--with-mmsg-amount=16"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn packet_ring_invalid() -> Result<(), Box<dyn std::error::Error>>{
+    for args in [
+        vec!["sender", "--ip=::1", "--io-model=packet-ring"],
+        vec!["sender", "--io-model=packet-ring", "--with-gsro"],
+        vec!["sender", "--io-model=packet-ring", "--multiplex-port=sharing"],
+        vec!["sender", "--io-model=packet-ring", "--sockets-per-thread=2"],
+    ] {
+        let nperf = nperf::nPerf::new().set_args(args);
+        assert!(nperf.parse_parameter().is_none());
+    }
+    Ok(())
+}
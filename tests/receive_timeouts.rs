@@ -0,0 +1,53 @@
+mod common;
+
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use common::NperfProcess;
+
+// Returns as soon as the datagrams of the sender arrive at the receiver
+fn start_sender(receiver: &NperfProcess, port: &str) -> NperfProcess {
+    receiver.wait_for_log("Waiting for a sender");
+    let sender = NperfProcess::spawn(&["sender", port, "--time=4"], Stdio::null());
+    receiver.wait_for_log("First packet received");
+    sender
+}
+
+#[test]
+fn receive_idle_timeout() -> Result<(), Box<dyn std::error::Error>>{
+    let receiver = NperfProcess::spawn(&["receiver", "--port=48901", "--receive-idle-timeout=300"], Stdio::piped());
+
+    // The sender pauses longer than the idle timeout, but stops the test over the control channel afterwards
+    let mut sender = start_sender(&receiver, "--port=48901");
+    sender.signal(libc::SIGSTOP);
+    std::thread::sleep(Duration::from_secs(1));
+    sender.signal(libc::SIGCONT);
+    assert!(sender.child.wait()?.success());
+
+    let output = String::from_utf8(receiver.child.wait_with_output()?.stdout)?;
+    assert!(output.contains("Termination reason: IdleTimeout"));
+    Ok(())
+}
+
+#[test]
+fn receive_idle_timeout_sender_vanished() -> Result<(), Box<dyn std::error::Error>>{
+    let mut receiver = NperfProcess::spawn(&["receiver", "--port=49001", "--receive-idle-timeout=300"], Stdio::null());
+
+    // The sender never sends STOP, so the receiver gives up on the control channel after the test duration and the idle timeout
+    let mut sender = start_sender(&receiver, "--port=49001");
+    sender.signal(libc::SIGSTOP);
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while receiver.child.try_wait()?.is_none() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let finished = receiver.child.try_wait()?.is_some();
+
+    sender.signal(libc::SIGCONT);
+    sender.child.kill()?;
+    sender.child.wait()?;
+    if !finished {
+        receiver.child.kill()?;
+    }
+    assert!(finished);
+    Ok(())
+}
@@ -32,9 +32,9 @@ fn test_receiver_sendmsg() -> Result<(), Box<dyn std::error::Error>>{
 
 #[test]
 fn test_receiver_sendmmsg() -> Result<(), Box<dyn std::error::Error>>{
-    let handle = common::start_nperf_sender(Some(vec!["--exchange-function=normal".to_string(), "--port=45201".to_string()]));
+    let handle = common::start_nperf_sender(Some(vec!["--exchange-function=mmsg".to_string(), "--with-mmsg-amount=20".to_string(), "--port=45201".to_string()]));
 
-    let args = vec!["receiver", "--exchange-function=mmsg", "--with-mmsg-amount=20", "--port=45201"];
+    let args = vec!["receiver", "--port=45201"];
     let nperf = nperf::nPerf::new().set_args(args);
     let arguments = nperf.parse_parameter().unwrap();
     if let Some(x) = nperf.exec(arguments) {
@@ -43,4 +43,51 @@ fn test_receiver_sendmmsg() -> Result<(), Box<dyn std::error::Error>>{
 
     handle.join().unwrap();
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_receiver_drops_foreign_datagrams() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_sender(Some(vec!["--port=45401".to_string()]));
+
+    // Datagrams without a valid header hit the receive port during the test
+    let foreign_handle = std::thread::spawn(|| {
+        std::thread::sleep(std::time::Duration::from_secs(5));
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        for _ in 0..100 {
+            socket.send_to(&[0xff; 64], "127.0.0.1:45401").unwrap();
+        }
+    });
+
+    let args = vec!["receiver", "--port=45401"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert!(x.amount_foreign_datagrams > 0);
+    };
+
+    foreign_handle.join().unwrap();
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_daemon_concurrent_senders() -> Result<(), Box<dyn std::error::Error>>{
+    let mut daemon = common::start_nperf_daemon(Some(vec!["--port=45501".to_string()]));
+
+    // Both senders run at the same time, each in its own session of the daemon
+    let handles: Vec<_> = ["--parallel=2", "--parallel=1"].into_iter().map(|parallel| std::thread::spawn(move || {
+        let args = vec!["sender", parallel, "--port=45501"];
+        let nperf = nperf::nPerf::new().set_args(args);
+        let arguments = nperf.parse_parameter().unwrap();
+        nperf.exec(arguments).map(|x| x.amount_datagrams)
+    })).collect();
+
+    for handle in handles {
+        assert!(handle.join().unwrap().is_some_and(|x| x > 10000));
+    }
+
+    daemon.kill()?;
+    daemon.wait()?;
+    Ok(())
+}
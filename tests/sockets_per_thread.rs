@@ -0,0 +1,49 @@
+mod common;
+
+#[test]
+fn sockets_per_thread_poll() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=48401".to_string()]));
+
+    let args = vec!["sender", "--port=48401", "--parallel=2", "--sockets-per-thread=3", "--io-model=poll", "--exchange-function=mmsg"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert_eq!(x.sockets.iter().map(|socket| socket.port).collect::<Vec<_>>(), (48401..48407).collect::<Vec<_>>());
+        assert!(x.sockets.iter().all(|socket| socket.amount_datagrams > 0));
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn sockets_per_thread_io_uring() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=48451".to_string()]));
+
+    let args = vec!["sender", "--port=48451", "--sockets-per-thread=4", "--io-model=io-uring"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+        assert_eq!(x.sockets.len(), 4);
+        assert!(x.sockets.iter().all(|socket| socket.amount_datagrams > 0));
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn sockets_per_thread_invalid() -> Result<(), Box<dyn std::error::Error>>{
+    for args in [
+        vec!["sender", "--sockets-per-thread=0"],
+        vec!["sender", "--sockets-per-thread=2", "--transport=tcp"],
+        vec!["sender", "--sockets-per-thread=2", "--multiplex-port=sharing"],
+        vec!["sender", "--sockets-per-thread=2", "--io-model=io-uring", "--uring-mode=multishot"],
+    ] {
+        let nperf = nperf::nPerf::new().set_args(args);
+        assert!(nperf.parse_parameter().is_none());
+    }
+    Ok(())
+}
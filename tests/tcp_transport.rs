@@ -0,0 +1,39 @@
+mod common;
+
+#[test]
+fn tcp_send_recv() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=47301".to_string()]));
+
+    let args = vec!["sender", "--port=47301", "--parallel=2", "--transport=tcp", "--exchange-function=normal"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn tcp_io_uring() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=47501".to_string(), "--control-port=47500".to_string()]));
+
+    let args = vec!["sender", "--port=47501", "--control-port=47500", "--transport=tcp", "--io-model=io-uring"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn tcp_rejects_port_sharing() -> Result<(), Box<dyn std::error::Error>>{
+    let args = vec!["sender", "--transport=tcp", "--multiplex-port=sharing"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    assert!(nperf.parse_parameter().is_none());
+    Ok(())
+}
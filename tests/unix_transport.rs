@@ -0,0 +1,39 @@
+mod common;
+
+#[test]
+fn unix_sendmsg_recvmsg() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=47001".to_string()]));
+
+    let args = vec!["sender", "--port=47001", "--parallel=2", "--transport=unix"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn unix_sendmmsg_poll_sharing() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=47101".to_string()]));
+
+    let args = vec!["sender", "--port=47101", "--parallel=2", "--transport=unix", "--exchange-function=mmsg", "--io-model=poll", "--multiplex-port=sharing", "--sender-port=47201"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn unix_rejects_gso() -> Result<(), Box<dyn std::error::Error>>{
+    let args = vec!["sender", "--transport=unix", "--with-gsro"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    assert!(nperf.parse_parameter().is_none());
+    Ok(())
+}
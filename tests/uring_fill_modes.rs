@@ -2,9 +2,9 @@ mod common;
 
 #[test]
 fn uring_fillmode_topup() -> Result<(), Box<dyn std::error::Error>>{
-    let handle = common::start_nperf_sender(Some(vec!["--port=45001".to_string(), "--with-gsro".to_string()]));
+    let handle = common::start_nperf_sender(Some(vec!["--port=45001".to_string(), "--with-gsro".to_string(), "--io-model=io-uring".to_string(), "--uring-sq-mode=topup".to_string()]));
 
-    let args = vec!["receiver", "--io-model=io-uring", "--port=45001", "--uring-sq-mode=topup"];
+    let args = vec!["receiver", "--port=45001"];
     let nperf = nperf::nPerf::new().set_args(args);
     let arguments = nperf.parse_parameter().unwrap();
     if let Some(x) = nperf.exec(arguments) {
@@ -17,9 +17,9 @@ fn uring_fillmode_topup() -> Result<(), Box<dyn std::error::Error>>{
 
 #[test]
 fn uring_fillmode_syscall() -> Result<(), Box<dyn std::error::Error>>{
-    let handle = common::start_nperf_sender(Some(vec!["--port=45002".to_string(), "--with-gsro".to_string()]));
+    let handle = common::start_nperf_sender(Some(vec!["--port=45002".to_string(), "--with-gsro".to_string(), "--io-model=io-uring".to_string(), "--uring-sq-mode=syscall".to_string()]));
 
-    let args = vec!["receiver", "--io-model=io-uring", "--port=45002", "--uring-sq-mode=syscall"];
+    let args = vec!["receiver", "--port=45002"];
     let nperf = nperf::nPerf::new().set_args(args);
     let arguments = nperf.parse_parameter().unwrap();
     if let Some(x) = nperf.exec(arguments) {
@@ -2,9 +2,9 @@ mod common;
 
 #[test]
 fn uring_normal() -> Result<(), Box<dyn std::error::Error>>{
-    let handle = common::start_nperf_sender(Some(vec!["--port=45001".to_string(), "--with-gsro".to_string()]));
+    let handle = common::start_nperf_sender(Some(vec!["--port=45001".to_string(), "--with-gsro".to_string(), "--io-model=io-uring".to_string(), "--uring-mode=normal".to_string()]));
 
-    let args = vec!["receiver", "--io-model=io-uring", "--port=45001", "--uring-mode=normal"];
+    let args = vec!["receiver", "--port=45001"];
     let nperf = nperf::nPerf::new().set_args(args);
     let arguments = nperf.parse_parameter().unwrap();
     if let Some(x) = nperf.exec(arguments) {
@@ -17,9 +17,9 @@ fn uring_normal() -> Result<(), Box<dyn std::error::Error>>{
 
 //#[test]
 //fn uring_multishot() -> Result<(), Box<dyn std::error::Error>>{
-//    let handle = common::start_nperf_sender(Some(vec!["--port=45002".to_string(), "--with-gsro".to_string()]));
+//    let handle = common::start_nperf_sender(Some(vec!["--port=45002".to_string(), "--with-gsro".to_string(), "--io-model=io-uring".to_string(), "--uring-mode=multishot".to_string()]));
 //
-//    let args = vec!["receiver", "--io-model=io-uring", "--port=45002", "--uring-mode=multishot"];
+//    let args = vec!["receiver", "--port=45002"];
 //    let nperf = nperf::nPerf::new().set_args(args);
 //    let arguments = nperf.parse_parameter().unwrap();
 //    if let Some(x) = nperf.exec(arguments) {
@@ -32,9 +32,9 @@ fn uring_normal() -> Result<(), Box<dyn std::error::Error>>{
 
 #[test]
 fn uring_provided_buffer() -> Result<(), Box<dyn std::error::Error>>{
-    let handle = common::start_nperf_sender(Some(vec!["--port=45003".to_string(), "--with-gsro".to_string()]));
+    let handle = common::start_nperf_sender(Some(vec!["--port=45003".to_string(), "--with-gsro".to_string(), "--io-model=io-uring".to_string(), "--uring-mode=provided-buffer".to_string()]));
 
-    let args = vec!["receiver", "--io-model=io-uring", "--port=45003", "--uring-mode=provided-buffer"];
+    let args = vec!["receiver", "--port=45003"];
     let nperf = nperf::nPerf::new().set_args(args);
     let arguments = nperf.parse_parameter().unwrap();
     if let Some(x) = nperf.exec(arguments) {
@@ -43,4 +43,19 @@ fn uring_provided_buffer() -> Result<(), Box<dyn std::error::Error>>{
 
     handle.join().unwrap();
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn uring_reverse() -> Result<(), Box<dyn std::error::Error>>{
+    let handle = common::start_nperf_receiver(Some(vec!["--port=45005".to_string()]));
+
+    let args = vec!["sender", "--reverse", "--io-model=io-uring", "--port=45005", "--uring-mode=normal"];
+    let nperf = nperf::nPerf::new().set_args(args);
+    let arguments = nperf.parse_parameter().unwrap();
+    if let Some(x) = nperf.exec(arguments) {
+        assert!(x.amount_datagrams > 10000);
+    };
+
+    handle.join().unwrap();
+    Ok(())
+}